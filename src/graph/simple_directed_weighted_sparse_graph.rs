@@ -6,7 +6,8 @@
 pub use sdws_graph::{Graph, IndexVertex, PrepareInput, Vertex};
 mod sdws_graph {
     use serde::{Deserialize, Serialize};
-    use std::cmp::Ordering;
+    use crate::queue_priority_indexed::{Handle, IndexedPriorityQueue};
+    use std::cmp::Reverse;
     use std::collections::{BinaryHeap, HashSet, VecDeque};
     use std::fmt::{Debug, Display};
     use std::ops::Add;
@@ -240,6 +241,119 @@ mod sdws_graph {
             }
         }
 
+        /// Поиск кратчайших путей от нескольких стартовых вершин сразу (например, ближайший шлюз для каждого устройства).
+        /// Возвращает для каждой достижимой вершины (вершина, вес пути, ближайшая стартовая вершина) в порядке добавления вершин.
+        /// При равном весе выигрывает стартовая вершина, стоящая раньше в `sources`, поэтому результат детерминирован.
+        pub fn dijkstras_multi_source<'a>(&'a self, sources: &'a [T]) -> Vec<(&'a T, W, &'a T)> {
+            let len = self.vertexes.len();
+            let mut best: Vec<Option<(W, usize)>> = vec![None; len];
+            let mut heap_queue_visit: BinaryHeap<Reverse<(W, usize, usize)>> =
+                BinaryHeap::with_capacity(64);
+
+            for (rank, source) in sources.iter().enumerate() {
+                if let Some(index_vertex) = self.find_vertex(source) {
+                    if best[index_vertex.0].is_none() {
+                        best[index_vertex.0] = Some((W::default(), rank));
+                        heap_queue_visit.push(Reverse((W::default(), rank, index_vertex.0)));
+                    }
+                }
+            }
+
+            let mut visited = vec![false; len];
+            while let Some(Reverse((sum_weight, rank, index))) = heap_queue_visit.pop() {
+                if visited[index] {
+                    continue;
+                }
+                visited[index] = true;
+                if let Some(from_to) = self.get_edges(&IndexVertex(index)) {
+                    for (weight, to_vertex) in from_to {
+                        let candidate = (sum_weight + *weight, rank);
+                        let better = match best[to_vertex.0] {
+                            Some(current) => candidate < current,
+                            None => true,
+                        };
+                        if better && !visited[to_vertex.0] {
+                            best[to_vertex.0] = Some(candidate);
+                            heap_queue_visit.push(Reverse((candidate.0, rank, to_vertex.0)));
+                        }
+                    }
+                }
+            }
+
+            best.into_iter()
+                .enumerate()
+                .filter_map(|(index, best)| {
+                    let (sum_weight, rank) = best?;
+                    let vertex = self.get_vertex(&IndexVertex(index))?;
+                    Some((&vertex.payload, sum_weight, &sources[rank]))
+                })
+                .collect()
+        }
+
+        /// Обход в ширину по уровням: текущий фронт делится между `threads` потоками,
+        /// каждый поток собирает соседей своей части, затем результаты сливаются по порядку частей.
+        /// Порядок вершин в `ret` не зависит от числа потоков и совпадает с последовательным обходом.
+        pub fn breadth_first_search_parallel<'a, 'b: 'a>(
+            &'b self,
+            start_vertex: T,
+            threads: usize,
+            ret: &mut Vec<&'a T>,
+        ) where
+            T: Sync,
+            W: Sync,
+        {
+            let index_start_vertex = match self.find_vertex(&start_vertex) {
+                Some(index_vertex) => index_vertex,
+                None => return,
+            };
+            let threads = threads.max(1);
+            let mut visited = vec![false; self.vertexes.len()];
+            visited[index_start_vertex.0] = true;
+            let mut frontier = vec![index_start_vertex.0];
+
+            while !frontier.is_empty() {
+                for index in frontier.iter() {
+                    if let Some(vertex) = self.get_vertex(&IndexVertex(*index)) {
+                        ret.push(&vertex.payload);
+                    }
+                }
+
+                let chunk_size = frontier.len().div_ceil(threads);
+                let neighbours: Vec<Vec<usize>> = if threads == 1 || frontier.len() == 1 {
+                    vec![self.frontier_neighbours(&frontier)]
+                } else {
+                    std::thread::scope(|scope| {
+                        let handles: Vec<_> = frontier
+                            .chunks(chunk_size)
+                            .map(|chunk| scope.spawn(move || self.frontier_neighbours(chunk)))
+                            .collect();
+                        handles
+                            .into_iter()
+                            .map(|handle| handle.join().unwrap())
+                            .collect()
+                    })
+                };
+
+                let mut next_frontier = Vec::with_capacity(frontier.len());
+                for index in neighbours.into_iter().flatten() {
+                    if !visited[index] {
+                        visited[index] = true;
+                        next_frontier.push(index);
+                    }
+                }
+                frontier = next_frontier;
+            }
+        }
+
+        fn frontier_neighbours(&self, frontier: &[usize]) -> Vec<usize> {
+            let mut ret = Vec::with_capacity(frontier.len());
+            for index in frontier {
+                if let Some(from_to) = self.get_edges(&IndexVertex(*index)) {
+                    ret.extend(from_to.iter().map(|(_weight, to_vertex)| to_vertex.0));
+                }
+            }
+            ret
+        }
         fn adjacency_vertexes(&self, index_vertex: &IndexVertex) -> Vec<&IndexVertex> {
             let mut ret = Vec::with_capacity(1);
             if let Some(from_to) = self.get_edges(&index_vertex) {
@@ -347,63 +461,50 @@ mod sdws_graph {
                 return None;
             }
             let index_from_vertex = self.find_vertex(from).unwrap();
-            let from_vertex = self.get_mut_vertex(&index_from_vertex);
-            if from_vertex.is_none() {
-                return None;
-            }
-            let from_vertex = from_vertex.unwrap();
+            let index_to_vertex = self.find_vertex(to).unwrap();
+            let from_vertex = self.get_mut_vertex(&index_from_vertex)?;
             from_vertex.visited = true;
 
-            let mut heap_queue_visit: BinaryHeap<MinWeight<W>> = BinaryHeap::with_capacity(64);
+            // каждая вершина попадает в очередь один раз, более короткий путь обновляет её вес через decrease_key
+            let mut heap_queue_visit: IndexedPriorityQueue<IndexVertex, W> =
+                IndexedPriorityQueue::with_capacity(64);
+            let mut handles: Vec<Option<Handle>> = vec![None; self.vertexes.len()];
 
-            if let Some(from_to) = self.get_edges(&index_from_vertex) {
-                for (weight, to_vertex) in from_to {
-                    heap_queue_visit.push(MinWeight::new(
-                        start_weight + *weight,
-                        *to_vertex,
-                        index_from_vertex,
-                    ));
+            let mut next = Some((index_from_vertex, start_weight));
+            while let Some((index_vertex, sum_weight)) = next {
+                if index_vertex == index_to_vertex {
+                    break;
                 }
-            }
-            let index_to_vertex = self.find_vertex(to).unwrap();
-            while let Some(MinWeight(mut sum_weight, next_vertex, previous_vertex)) =
-                heap_queue_visit.pop()
-            {
-                if let Some(from_vertex) = self.get_mut_vertex(&next_vertex) {
-                    if from_vertex.visited {
-                        continue;
-                    }
-                    if let Some(ref mut sw) = &mut from_vertex.sum_weight {
-                        if sw > &mut sum_weight {
-                            *sw = sum_weight;
-                            (*from_vertex).previous_vertex = Some(previous_vertex);
-                            (*from_vertex).visited = false;
-                        }
-                    } else {
-                        (*from_vertex).sum_weight = Some(sum_weight);
-                        (*from_vertex).previous_vertex = Some(previous_vertex);
-                    }
-                    if index_to_vertex == next_vertex {
-                        break;
-                    }
-                    if !from_vertex.visited {
-                        (*from_vertex).visited = true;
-                        if let Some(from_to) = self.get_edges(&next_vertex) {
-                            for (weight, to_vertex) in from_to {
-                                /*if let Some(v) = self.get_vertex(&to_vertex){
-                                    if v.visited{
-                                        continue;
-                                    }
-                                }*/
-                                heap_queue_visit.push(MinWeight::new(
-                                    sum_weight + *weight,
-                                    *to_vertex,
-                                    next_vertex,
-                                ));
+                if let Some(Some(from_to)) = self.edges.get(index_vertex.0) {
+                    for (weight, to_vertex) in from_to.iter() {
+                        let vertex = match self.vertexes.get_mut(to_vertex.0) {
+                            Some(Some(vertex)) if !vertex.visited => vertex,
+                            _ => continue,
+                        };
+                        let new_weight = sum_weight + *weight;
+                        let improved = match handles[to_vertex.0] {
+                            Some(handle) => {
+                                vertex.sum_weight.is_some_and(|sw| new_weight < sw)
+                                    && heap_queue_visit.decrease_key(handle, new_weight)
+                            }
+                            None => {
+                                handles[to_vertex.0] =
+                                    Some(heap_queue_visit.push(*to_vertex, new_weight));
+                                true
                             }
+                        };
+                        if improved {
+                            vertex.sum_weight = Some(new_weight);
+                            vertex.previous_vertex = Some(index_vertex);
                         }
                     }
                 }
+                next = heap_queue_visit.pop();
+                if let Some((index_vertex, _)) = next {
+                    if let Some(vertex) = self.get_mut_vertex(&index_vertex) {
+                        vertex.visited = true;
+                    }
+                }
             }
             let vertex = self.get_vertex(&index_to_vertex).unwrap();
             if vertex.sum_weight.is_none() {
@@ -416,54 +517,6 @@ mod sdws_graph {
         }
     }
 
-    #[derive(Debug)]
-    struct MinWeight<W>(W, IndexVertex, IndexVertex);
-
-    impl<W> MinWeight<W> {
-        fn new(sum_weight: W, next_vertex: IndexVertex, previous_vertex: IndexVertex) -> Self {
-            Self(sum_weight, next_vertex, previous_vertex)
-        }
-    }
-
-    impl<W: PartialOrd> PartialEq for MinWeight<W> {
-        #[inline]
-        fn eq(&self, other: &MinWeight<W>) -> bool {
-            self.cmp(other) == Ordering::Equal
-        }
-    }
-
-    impl<W: PartialOrd> Eq for MinWeight<W> {}
-
-    impl<W: PartialOrd> PartialOrd for MinWeight<W> {
-        #[inline]
-        fn partial_cmp(&self, other: &MinWeight<W>) -> Option<Ordering> {
-            Some(self.cmp(other))
-        }
-    }
-
-    impl<W: PartialOrd> Ord for MinWeight<W> {
-        #[inline]
-        fn cmp(&self, other: &MinWeight<W>) -> Ordering {
-            let a = &self.0;
-            let b = &other.0;
-            if a == b {
-                Ordering::Equal
-            } else if a < b {
-                Ordering::Greater
-            } else if a > b {
-                Ordering::Less
-            } else if a.ne(a) && b.ne(b) {
-                // these are the NaN cases
-                Ordering::Equal
-            } else if a.ne(a) {
-                // Order NaN less, so that it is last in the MinScore order
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
-        }
-    }
-
     /// Vertex
     impl<T: PartialEq, W> Vertex<T, W> {
         fn new(payload: T) -> Self {
@@ -515,6 +568,39 @@ mod tests {
         }
     }
 
+    // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_dijkstras_known_graph -- --nocapture
+    #[test]
+    fn test_dijkstras_known_graph() {
+        let input: Vec<PrepareInput<&str, u32>> = vec![
+            PrepareInput::new("A", Some(("B", 10))),
+            PrepareInput::new("A", Some(("C", 1))),
+            PrepareInput::new("C", Some(("B", 2))),
+            PrepareInput::new("B", Some(("D", 1))),
+            PrepareInput::new("C", Some(("D", 7))),
+            PrepareInput::new("E", Some(("A", 1))),
+        ];
+        let mut graph: Graph<&str, u32> = Graph::new_with_prepare_input(input);
+
+        // B сначала попадает в очередь с весом 10, через C находится путь веса 3 (decrease_key)
+        assert_eq!(
+            graph.dijkstras_algorithm(&"A", &"B"),
+            Some((3, vec![&"A", &"C", &"B"]))
+        );
+        assert_eq!(
+            graph.dijkstras_algorithm(&"A", &"D"),
+            Some((4, vec![&"A", &"C", &"B", &"D"]))
+        );
+        assert_eq!(
+            graph.dijkstras_algorithm(&"E", &"D"),
+            Some((5, vec![&"E", &"A", &"C", &"B", &"D"]))
+        );
+
+        // в E рёбра не входят, неизвестная вершина - тоже None
+        assert_eq!(graph.dijkstras_algorithm(&"A", &"E"), None);
+        assert_eq!(graph.dijkstras_algorithm(&"D", &"A"), None);
+        assert_eq!(graph.dijkstras_algorithm(&"A", &"Z"), None);
+    }
+
     // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_bfs_success -- --nocapture
     #[test]
     fn test_bfs_success() {
@@ -540,6 +626,135 @@ mod tests {
         }
     }
 
+    // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_bfs_parallel_success -- --nocapture
+    #[test]
+    fn test_bfs_parallel_success() {
+        let input: Vec<PrepareInput<&str, u8>> = vec![
+            PrepareInput::new("A", Some(("B", 4))),
+            PrepareInput::new("B", Some(("D", 10))),
+            PrepareInput::new("D", Some(("F", 11))),
+            PrepareInput::new("A", Some(("C", 2))),
+            PrepareInput::new("B", Some(("C", 5))),
+            PrepareInput::new("C", Some(("E", 3))),
+            PrepareInput::new("E", Some(("D", 4))),
+            PrepareInput::new("F", Some(("A", 1))),
+        ];
+        let graph: Graph<&str, u8> = Graph::new_with_prepare_input(input);
+        for threads in 1..=4 {
+            let mut vertexes = vec![];
+            graph.breadth_first_search_parallel("A", threads, &mut vertexes);
+            assert_eq!(vertexes, vec![&"A", &"B", &"C", &"D", &"E", &"F"]);
+        }
+
+        let mut vertexes = vec![];
+        graph.breadth_first_search_parallel("Z", 4, &mut vertexes);
+        assert!(vertexes.is_empty());
+    }
+
+    // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_bfs_parallel_deterministic -- --nocapture
+    #[test]
+    fn test_bfs_parallel_deterministic() {
+        let input = gen_input();
+        let from = input[0].from;
+        let graph: Graph<i32, i32> = Graph::new_with_prepare_input(input);
+
+        let mut expected = vec![];
+        graph.breadth_first_search_parallel(from, 1, &mut expected);
+        for threads in [2, 3, 8, 64] {
+            let mut vertexes = vec![];
+            graph.breadth_first_search_parallel(from, threads, &mut vertexes);
+            assert_eq!(vertexes, expected);
+        }
+    }
+
+    // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_bfs_parallel_matches_sequential -- --nocapture
+    #[test]
+    fn test_bfs_parallel_matches_sequential() {
+        use rand::{thread_rng, Rng};
+        // два случайных дерева: от 0 достижимы вершины 0..reachable, второе дерево недостижимо.
+        // Обход с деком не помнит посещённые вершины, поэтому граф без циклов и без общих потомков
+        let (reachable, vertexes) = (3_000u32, 5_000u32);
+        let mut rng = thread_rng();
+        let mut depth = vec![0usize; vertexes as usize];
+        let mut input = vec![];
+        for to in 1..vertexes {
+            if to == reachable {
+                continue;
+            }
+            let from = if to < reachable {
+                rng.gen_range(0..to)
+            } else {
+                rng.gen_range(reachable..to)
+            };
+            depth[to as usize] = depth[from as usize] + 1;
+            input.push(PrepareInput::new(from, Some((to, rng.gen_range(1..10)))));
+        }
+        let graph: Graph<u32, u32> = Graph::new_with_prepare_input(input);
+
+        let levels = |vertexes: &[&u32]| -> Vec<Vec<u32>> {
+            let mut levels: Vec<Vec<u32>> = vec![];
+            for &&vertex in vertexes {
+                let level = depth[vertex as usize];
+                assert!(level + 1 >= levels.len(), "уровни обхода идут не по порядку");
+                levels.resize(level + 1, vec![]);
+                levels[level].push(vertex);
+            }
+            for level in levels.iter_mut() {
+                level.sort_unstable();
+            }
+            levels
+        };
+
+        let mut sequential = vec![];
+        graph.breadth_first_search_with_deque(0, &mut sequential);
+        let expected = levels(&sequential);
+        let mut reachable_set: Vec<u32> = sequential.iter().map(|&&vertex| vertex).collect();
+        reachable_set.sort_unstable();
+        assert_eq!(reachable_set, (0..reachable).collect::<Vec<_>>());
+
+        for threads in [1, 2, 4, 16] {
+            let mut parallel = vec![];
+            graph.breadth_first_search_parallel(0, threads, &mut parallel);
+            assert_eq!(levels(&parallel), expected);
+            let mut parallel_set: Vec<u32> = parallel.iter().map(|&&vertex| vertex).collect();
+            parallel_set.sort_unstable();
+            assert_eq!(parallel_set, reachable_set);
+        }
+    }
+
+    // $ cargo test graph::simple_directed_weighted_sparse_graph::tests::test_dijkstras_multi_source_success -- --nocapture
+    #[test]
+    fn test_dijkstras_multi_source_success() {
+        let input: Vec<PrepareInput<&str, u32>> = vec![
+            PrepareInput::new("gw1", Some(("d1", 1))),
+            PrepareInput::new("d1", Some(("d2", 5))),
+            PrepareInput::new("gw2", Some(("d2", 2))),
+            PrepareInput::new("d2", Some(("d3", 1))),
+            PrepareInput::new("gw1", Some(("d3", 3))),
+            PrepareInput::new("d4", Some(("d1", 1))),
+        ];
+        let graph: Graph<&str, u32> = Graph::new_with_prepare_input(input);
+
+        let nearest = graph.dijkstras_multi_source(&["gw1", "gw2"]);
+        // вершины отсортированы: d1, d2, d3, d4, gw1, gw2; d4 недостижима
+        assert_eq!(
+            nearest,
+            vec![
+                (&"d1", 1, &"gw1"),
+                (&"d2", 2, &"gw2"),
+                (&"d3", 3, &"gw1"),
+                (&"gw1", 0, &"gw1"),
+                (&"gw2", 0, &"gw2"),
+            ]
+        );
+
+        // при равном весе d3 достаётся стартовой вершине, указанной первой
+        let nearest = graph.dijkstras_multi_source(&["gw2", "gw1"]);
+        assert!(nearest.contains(&(&"d3", 3, &"gw2")));
+
+        assert!(graph.dijkstras_multi_source(&["unknown"]).is_empty());
+    }
+
     fn gen_input() -> Vec<PrepareInput<i32, i32>> {
         use rand::{thread_rng, Rng};
         let indexes = 50; //16_777_216; // 2^26 67_108_864
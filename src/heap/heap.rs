@@ -1,7 +1,8 @@
 #![allow(dead_code)]
 // https://github.com/PacktPublishing/Hands-On-Data-Structures-and-Algorithms-with-Rust/blob/master/Chapter05/src/heap.rs
-use std::boxed::Box;
+use std::cmp::Ordering;
 use std::mem;
+use std::ops::{Deref, DerefMut};
 
 #[derive(Clone, Debug)]
 pub struct IoTDevice {
@@ -47,69 +48,366 @@ impl PartialEq for MessageNotification {
     }
 }
 
+/// Сравнение элементов кучи: на вершине находится наибольший по `compare` элемент.
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// Наибольший элемент на вершине (max-heap)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaxComparator;
+
+/// Наименьший элемент на вершине (min-heap)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinComparator;
+
+impl<T: Ord> Compare<T> for MaxComparator {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: Ord> Compare<T> for MinComparator {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// Общий интерфейс куч модуля, позволяет подменять реализацию
+pub trait Heap<T> {
+    fn push(&mut self, item: T);
+    fn pop(&mut self) -> Option<T>;
+    fn peek(&self) -> Option<&T>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Двоичная куча на `Vec` с произвольным компаратором
+pub struct BinaryHeap<T, C = MaxComparator> {
+    data: Vec<T>,
+    cmp: C,
+}
+
+impl<T, C: Compare<T> + Default> Default for BinaryHeap<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Compare<T> + Default> BinaryHeap<T, C> {
+    pub fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    /// Построение кучи из вектора за O(n)
+    pub fn from_vec(data: Vec<T>) -> Self {
+        Self::from_vec_with_comparator(data, C::default())
+    }
+}
+
+impl<T, C: Compare<T>> BinaryHeap<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BinaryHeap { data: vec![], cmp }
+    }
+
+    pub fn from_vec_with_comparator(data: Vec<T>, cmp: C) -> Self {
+        let mut heap = BinaryHeap { data, cmp };
+        for i in (0..heap.data.len() / 2).rev() {
+            heap.sift_down(i, heap.data.len());
+        }
+        heap
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        self.sift_up(self.data.len() - 1);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.data.pop()?;
+        if self.data.is_empty() {
+            return Some(last);
+        }
+        let top = mem::replace(&mut self.data[0], last);
+        self.sift_down(0, self.data.len());
+        Some(top)
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Изменяемая ссылка на вершину, порядок восстанавливается при освобождении `PeekMut`
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(PeekMut { heap: self })
+        }
+    }
+
+    /// Элементы в произвольном порядке
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    /// Извлекает все элементы в произвольном порядке, куча остаётся пустой
+    pub fn drain(&mut self) -> std::vec::Drain<'_, T> {
+        self.data.drain(..)
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    /// Пирамидальная сортировка: элементы по возрастанию согласно компаратору
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            self.sift_down(0, end);
+        }
+        self.data
+    }
+
+    fn greater(&self, a: usize, b: usize) -> bool {
+        self.cmp.compare(&self.data[a], &self.data[b]) == Ordering::Greater
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !self.greater(i, parent) {
+                break;
+            }
+            self.data.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize, end: usize) {
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut top = i;
+            if left < end && self.greater(left, top) {
+                top = left;
+            }
+            if right < end && self.greater(right, top) {
+                top = right;
+            }
+            if top == i {
+                break;
+            }
+            self.data.swap(i, top);
+            i = top;
+        }
+    }
+}
+
+impl<T, C: Compare<T>> Heap<T> for BinaryHeap<T, C> {
+    fn push(&mut self, item: T) {
+        BinaryHeap::push(self, item)
+    }
+    fn pop(&mut self) -> Option<T> {
+        BinaryHeap::pop(self)
+    }
+    fn peek(&self) -> Option<&T> {
+        BinaryHeap::peek(self)
+    }
+    fn len(&self) -> usize {
+        BinaryHeap::len(self)
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for BinaryHeap<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for BinaryHeap<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_vec(iter.into_iter().collect())
+    }
+}
+
+pub struct PeekMut<'a, T, C: Compare<T>> {
+    heap: &'a mut BinaryHeap<T, C>,
+}
+
+impl<T, C: Compare<T>> PeekMut<'_, T, C> {
+    pub fn pop(this: Self) -> T {
+        let item = this
+            .heap
+            .pop()
+            .expect("PeekMut exists only for a non-empty heap");
+        mem::forget(this);
+        item
+    }
+}
+
+impl<T, C: Compare<T>> Deref for PeekMut<'_, T, C> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.heap.data[0]
+    }
+}
+
+impl<T, C: Compare<T>> DerefMut for PeekMut<'_, T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.heap.data[0]
+    }
+}
+
+impl<T, C: Compare<T>> Drop for PeekMut<'_, T, C> {
+    fn drop(&mut self) {
+        let end = self.heap.data.len();
+        self.heap.sift_down(0, end);
+    }
+}
+
+/// Больше сообщений - выше в куче
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoreMessages;
+
+impl Compare<MessageNotification> for MoreMessages {
+    fn compare(&self, a: &MessageNotification, b: &MessageNotification) -> Ordering {
+        a.no_messages.cmp(&b.no_messages)
+    }
+}
+
 pub struct MessageChecker {
     pub length: usize,
-    heap: Vec<Box<MessageNotification>>,
+    heap: BinaryHeap<MessageNotification, MoreMessages>,
 }
 
 impl MessageChecker {
     pub fn new_empty() -> MessageChecker {
         MessageChecker {
             length: 0,
-            heap: vec![],
+            heap: BinaryHeap::new(),
         }
     }
 
-    fn swap(&mut self, pos1: usize, pos2: usize) {
-        let m2 = self.heap[pos1 - 1].clone();
-        self.heap[pos1 - 1] = mem::replace(&mut self.heap[pos2 - 1], m2);
+    pub fn add(&mut self, notification: MessageNotification) {
+        self.heap.push(notification);
+        self.length = self.heap.len();
     }
 
-    fn has_more_messages(&self, pos1: usize, pos2: usize) -> bool {
-        let a = &self.heap[pos1 - 1];
-        let b = &self.heap[pos2 - 1];
-        a.no_messages >= b.no_messages
+    pub fn pop(&mut self) -> Option<MessageNotification> {
+        let elem = self.heap.pop();
+        self.length = self.heap.len();
+        elem
     }
+}
 
-    pub fn add(&mut self, notification: MessageNotification) {
-        self.heap.push(Box::new(notification));
-        self.length = self.heap.len();
+/// $ cargo +nightly miri test heap
+/// $ cargo test heap::heap
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if self.length > 1 {
-            let mut i = self.length;
-            while i / 2 > 0 && self.has_more_messages(i, i / 2) {
-                self.swap(i, i / 2);
-                i /= 2;
-            }
+    #[test]
+    fn test_max_heap() {
+        let mut heap: BinaryHeap<i32> = BinaryHeap::new();
+        for i in [5, 1, 8, 3, 9, 2, 8] {
+            heap.push(i);
         }
+        assert_eq!(heap.len(), 7);
+        assert_eq!(heap.peek(), Some(&9));
+        let order: Vec<i32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(order, vec![9, 8, 8, 5, 3, 2, 1]);
+        assert!(heap.is_empty());
     }
 
-    pub fn pop(&mut self) -> Option<MessageNotification> {
-        if self.length > 0 {
-            let elem = self.heap.swap_remove(0);
-            self.length = self.heap.len();
-            let mut i = 1;
-            while i * 2 < self.length {
-                let children = (i * 2, i * 2 + 1);
-                i = if self.has_more_messages(children.0, children.1) {
-                    if self.has_more_messages(children.0, i) {
-                        self.swap(i, children.0);
-                        children.0
-                    } else {
-                        break;
-                    }
-                } else {
-                    if self.has_more_messages(children.1, i) {
-                        self.swap(i, children.1);
-                        children.1
-                    } else {
-                        break;
-                    }
-                }
-            }
-            Some(*elem)
-        } else {
-            None
+    #[test]
+    fn test_min_and_custom_heap() {
+        let mut heap: BinaryHeap<i32, MinComparator> = BinaryHeap::from_vec(vec![5, 1, 8, 3]);
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(3));
+
+        let by_len = |a: &&str, b: &&str| a.len().cmp(&b.len());
+        let mut heap = BinaryHeap::from_vec_with_comparator(vec!["ab", "a", "abcd", "abc"], by_len);
+        assert_eq!(heap.pop(), Some("abcd"));
+        assert_eq!(heap.into_sorted_vec(), vec!["a", "ab", "abc"]);
+    }
+
+    #[test]
+    fn test_from_vec_into_sorted_vec() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let data: Vec<u32> = (0..500).map(|_| rng.gen_range(0..100)).collect();
+        let mut expected = data.clone();
+        expected.sort();
+
+        let heap: BinaryHeap<u32> = BinaryHeap::from_vec(data.clone());
+        assert_eq!(heap.into_sorted_vec(), expected);
+
+        let heap: BinaryHeap<u32, MinComparator> = data.into_iter().collect();
+        expected.reverse();
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        let mut heap: BinaryHeap<i32> = BinaryHeap::from_vec(vec![10, 7, 3]);
+        if let Some(mut top) = heap.peek_mut() {
+            *top = 1;
+        }
+        assert_eq!(heap.peek(), Some(&7));
+        if let Some(top) = heap.peek_mut() {
+            assert_eq!(PeekMut::pop(top), 7);
+        }
+        assert_eq!(heap.into_sorted_vec(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut heap: BinaryHeap<i32> = BinaryHeap::from_vec(vec![4, 2, 6]);
+        let mut drained: Vec<i32> = heap.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![2, 4, 6]);
+        assert!(heap.is_empty());
+        heap.extend([1, 5]);
+        assert_eq!(heap.pop(), Some(5));
+    }
+
+    #[test]
+    fn test_message_checker() {
+        let mut checker = MessageChecker::new_empty();
+        for (id, messages) in [(1, 3), (2, 10), (3, 1), (4, 7), (5, 7)] {
+            let device = IoTDevice::new(id, format!("10.0.0.{}", id), "/home");
+            checker.add(MessageNotification::new(device, messages));
         }
+        assert_eq!(checker.length, 5);
+        let order: Vec<u64> = std::iter::from_fn(|| checker.pop())
+            .map(|n| n.no_messages)
+            .collect();
+        assert_eq!(order, vec![10, 7, 7, 3, 1]);
+        assert_eq!(checker.length, 0);
     }
 }
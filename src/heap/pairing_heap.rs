@@ -0,0 +1,437 @@
+#![allow(dead_code)]
+
+//! Парная куча (pairing heap).
+//! `push` и `meld` за O(1), `pop` за амортизированное O(log n),
+//! `decrease_key` по дескриптору за амортизированное o(log n).
+//! Интерфейс совпадает с `heap::BinaryHeap` (трейт `Heap`), на вершине наибольший по компаратору элемент.
+pub use ds_pairing_heap::{Handle, PairingHeap};
+mod ds_pairing_heap {
+    use super::super::heap::{Compare, Heap, MaxComparator};
+    use std::cell::{Cell, RefCell};
+    use std::cmp::Ordering;
+    use std::marker::PhantomData;
+    use std::ptr::NonNull;
+    use std::rc::Rc;
+
+    type Link<T> = Option<NonNull<Node<T>>>;
+
+    struct Node<T> {
+        item: T,
+        child: Link<T>,
+        sibling: Link<T>,
+        // родитель для первого ребёнка, иначе левый брат
+        prev: Link<T>,
+        alive: Option<Rc<Cell<bool>>>,
+    }
+
+    /// Владелец узлов. При слиянии поглощённая куча ссылается на поглотившую,
+    /// так дескрипторы её узлов остаются действительными для новой кучи.
+    #[derive(Default)]
+    struct Owner {
+        parent: RefCell<Option<Rc<Owner>>>,
+    }
+
+    fn root_owner(owner: &Rc<Owner>) -> Rc<Owner> {
+        let mut current = owner.clone();
+        loop {
+            let parent = current.parent.borrow().clone();
+            match parent {
+                Some(parent) => current = parent,
+                None => return current,
+            }
+        }
+    }
+
+    /// Дескриптор элемента для `decrease_key`.
+    /// Становится недействительным после извлечения элемента или удаления кучи.
+    #[derive(Clone)]
+    pub struct Handle<T> {
+        node: NonNull<Node<T>>,
+        alive: Rc<Cell<bool>>,
+        owner: Rc<Owner>,
+    }
+
+    impl<T> Handle<T> {
+        /// Элемент всё ещё находится в куче
+        pub fn is_alive(&self) -> bool {
+            self.alive.get()
+        }
+    }
+
+    pub struct PairingHeap<T, C = MaxComparator> {
+        root: Link<T>,
+        len: usize,
+        cmp: C,
+        owner: Rc<Owner>,
+        _boo: PhantomData<T>,
+    }
+
+    impl<T, C: Compare<T> + Default> Default for PairingHeap<T, C> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, C: Compare<T> + Default> PairingHeap<T, C> {
+        pub fn new() -> Self {
+            Self::with_comparator(C::default())
+        }
+    }
+
+    impl<T, C: Compare<T>> PairingHeap<T, C> {
+        pub fn with_comparator(cmp: C) -> Self {
+            Self {
+                root: None,
+                len: 0,
+                cmp,
+                owner: Rc::new(Owner::default()),
+                _boo: PhantomData,
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        pub fn push(&mut self, item: T) {
+            self.push_node(item, None);
+        }
+
+        /// Добавление с дескриптором для последующего `decrease_key`
+        pub fn push_with_handle(&mut self, item: T) -> Handle<T> {
+            let alive = Rc::new(Cell::new(true));
+            let node = self.push_node(item, Some(alive.clone()));
+            Handle {
+                node,
+                alive,
+                owner: self.owner.clone(),
+            }
+        }
+
+        pub fn peek(&self) -> Option<&T> {
+            // SAFETY: корень принадлежит куче и жив, пока жива куча
+            self.root.map(|root| unsafe { &(*root.as_ptr()).item })
+        }
+
+        pub fn pop(&mut self) -> Option<T> {
+            let root = self.root?;
+            // SAFETY: корень создан через Box::into_raw и больше нигде не хранится
+            unsafe {
+                let boxed = Box::from_raw(root.as_ptr());
+                if let Some(alive) = &boxed.alive {
+                    alive.set(false);
+                }
+                self.root = self.merge_pairs(boxed.child);
+                self.len -= 1;
+                Some(boxed.item)
+            }
+        }
+
+        /// Слияние за O(1): все элементы `other` переходят в эту кучу,
+        /// дескрипторы `other` продолжают работать с этой кучей.
+        pub fn meld(&mut self, mut other: Self) {
+            *other.owner.parent.borrow_mut() = Some(self.owner.clone());
+            self.len += other.len;
+            other.len = 0;
+            self.root = match (self.root, other.root.take()) {
+                (Some(a), Some(b)) => Some(self.link(a, b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        /// Поднимает элемент к вершине, заменяя его значение на `item`.
+        /// Новое значение не должно быть ниже старого в порядке кучи
+        /// (для `MinComparator` - ключ уменьшается). Возвращает `false`,
+        /// если дескриптор недействителен, принадлежит другой куче или значение ниже старого.
+        pub fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> bool {
+            if !handle.alive.get() || !Rc::ptr_eq(&root_owner(&handle.owner), &self.owner) {
+                return false;
+            }
+            let node = handle.node;
+            // SAFETY: узел жив и принадлежит этой куче, проверено выше
+            unsafe {
+                if self.cmp.compare(&item, &(*node.as_ptr()).item) == Ordering::Less {
+                    return false;
+                }
+                (*node.as_ptr()).item = item;
+                if self.root == Some(node) {
+                    return true;
+                }
+                // вырезаем поддерево узла из списка братьев
+                let prev = (*node.as_ptr()).prev.expect("non-root node has prev");
+                let sibling = (*node.as_ptr()).sibling;
+                if (*prev.as_ptr()).child == Some(node) {
+                    (*prev.as_ptr()).child = sibling;
+                } else {
+                    (*prev.as_ptr()).sibling = sibling;
+                }
+                if let Some(sibling) = sibling {
+                    (*sibling.as_ptr()).prev = Some(prev);
+                }
+                (*node.as_ptr()).sibling = None;
+                (*node.as_ptr()).prev = None;
+                let root = self.root.expect("heap with a live node is not empty");
+                self.root = Some(self.link(root, node));
+            }
+            true
+        }
+
+        /// Элементы по возрастанию согласно компаратору
+        pub fn into_sorted_vec(mut self) -> Vec<T> {
+            let mut ret = Vec::with_capacity(self.len);
+            while let Some(item) = self.pop() {
+                ret.push(item);
+            }
+            ret.reverse();
+            ret
+        }
+
+        fn push_node(&mut self, item: T, alive: Option<Rc<Cell<bool>>>) -> NonNull<Node<T>> {
+            // SAFETY: Box::into_raw never returns null
+            let node = unsafe {
+                NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+                    item,
+                    child: None,
+                    sibling: None,
+                    prev: None,
+                    alive,
+                })))
+            };
+            self.root = match self.root {
+                Some(root) => Some(self.link(root, node)),
+                None => Some(node),
+            };
+            self.len += 1;
+            node
+        }
+
+        /// Связывает два корня: меньший становится первым ребёнком большего
+        fn link(&self, a: NonNull<Node<T>>, b: NonNull<Node<T>>) -> NonNull<Node<T>> {
+            // SAFETY: a и b - разные живые корни этой кучи
+            unsafe {
+                let (top, bottom) = if self.cmp.compare(&(*a.as_ptr()).item, &(*b.as_ptr()).item)
+                    == Ordering::Less
+                {
+                    (b, a)
+                } else {
+                    (a, b)
+                };
+                let child = (*top.as_ptr()).child;
+                (*bottom.as_ptr()).sibling = child;
+                if let Some(child) = child {
+                    (*child.as_ptr()).prev = Some(bottom);
+                }
+                (*bottom.as_ptr()).prev = Some(top);
+                (*top.as_ptr()).child = Some(bottom);
+                (*top.as_ptr()).sibling = None;
+                (*top.as_ptr()).prev = None;
+                top
+            }
+        }
+
+        /// Двухпроходное слияние детей удалённого корня
+        fn merge_pairs(&self, first: Link<T>) -> Link<T> {
+            let mut roots = Vec::new();
+            let mut current = first;
+            // SAFETY: обходим список братьев, узлы живы
+            unsafe {
+                while let Some(a) = current {
+                    let b = (*a.as_ptr()).sibling;
+                    (*a.as_ptr()).prev = None;
+                    (*a.as_ptr()).sibling = None;
+                    match b {
+                        Some(b) => {
+                            current = (*b.as_ptr()).sibling;
+                            (*b.as_ptr()).prev = None;
+                            (*b.as_ptr()).sibling = None;
+                            roots.push(self.link(a, b));
+                        }
+                        None => {
+                            current = None;
+                            roots.push(a);
+                        }
+                    }
+                }
+            }
+            let mut root = roots.pop()?;
+            while let Some(next) = roots.pop() {
+                root = self.link(next, root);
+            }
+            Some(root)
+        }
+    }
+
+    impl<T, C: Compare<T>> Heap<T> for PairingHeap<T, C> {
+        fn push(&mut self, item: T) {
+            PairingHeap::push(self, item)
+        }
+        fn pop(&mut self) -> Option<T> {
+            PairingHeap::pop(self)
+        }
+        fn peek(&self) -> Option<&T> {
+            PairingHeap::peek(self)
+        }
+        fn len(&self) -> usize {
+            PairingHeap::len(self)
+        }
+    }
+
+    impl<T, C: Compare<T>> Extend<T> for PairingHeap<T, C> {
+        fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            for item in iter {
+                self.push(item);
+            }
+        }
+    }
+
+    impl<T, C: Compare<T> + Default> FromIterator<T> for PairingHeap<T, C> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let mut heap = Self::new();
+            heap.extend(iter);
+            heap
+        }
+    }
+
+    impl<T, C> PairingHeap<T, C> {
+        pub fn clear(&mut self) {
+            let mut stack: Vec<NonNull<Node<T>>> = self.root.take().into_iter().collect();
+            // SAFETY: каждый узел достижим ровно одним путём и освобождается один раз
+            while let Some(node) = stack.pop() {
+                unsafe {
+                    let boxed = Box::from_raw(node.as_ptr());
+                    if let Some(alive) = &boxed.alive {
+                        alive.set(false);
+                    }
+                    stack.extend(boxed.child);
+                    stack.extend(boxed.sibling);
+                }
+            }
+            self.len = 0;
+        }
+    }
+
+    impl<T, C> Drop for PairingHeap<T, C> {
+        fn drop(&mut self) {
+            self.clear();
+        }
+    }
+}
+
+/// $ cargo +nightly miri test pairing_heap
+/// $ cargo test pairing_heap
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::heap::{BinaryHeap, Heap, MinComparator};
+
+    #[test]
+    fn test_success() {
+        let mut heap: PairingHeap<i32> = PairingHeap::new();
+        for i in [5, 1, 8, 3, 9, 2, 8] {
+            heap.push(i);
+        }
+        assert_eq!(heap.len(), 7);
+        assert_eq!(heap.peek(), Some(&9));
+        let order: Vec<i32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(order, vec![9, 8, 8, 5, 3, 2, 1]);
+        assert!(heap.is_empty());
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_meld() {
+        let mut a: PairingHeap<u32, MinComparator> = [7, 3, 11].into_iter().collect();
+        let mut b: PairingHeap<u32, MinComparator> = PairingHeap::new();
+        b.push(5);
+        let handle = b.push_with_handle(20);
+        b.push(1);
+
+        a.meld(b);
+        assert_eq!(a.len(), 6);
+        // дескриптор поглощённой кучи работает с новой
+        assert!(a.decrease_key(&handle, 0));
+        assert_eq!(a.into_sorted_vec(), vec![11, 7, 5, 3, 1, 0]);
+        assert!(!handle.is_alive());
+    }
+
+    #[test]
+    fn test_decrease_key() {
+        let mut heap: PairingHeap<(u32, char), MinComparator> = PairingHeap::new();
+        let a = heap.push_with_handle((10, 'a'));
+        let b = heap.push_with_handle((20, 'b'));
+        let c = heap.push_with_handle((30, 'c'));
+        heap.push((15, 'd'));
+        assert_eq!(heap.pop(), Some((10, 'a')));
+
+        assert!(!heap.decrease_key(&a, (0, 'a')));
+        assert!(!heap.decrease_key(&b, (25, 'b')));
+        assert!(heap.decrease_key(&c, (5, 'c')));
+        assert!(heap.decrease_key(&b, (5, 'b')));
+
+        let mut other: PairingHeap<(u32, char), MinComparator> = PairingHeap::new();
+        assert!(!other.decrease_key(&c, (1, 'c')));
+
+        let order: Vec<char> = std::iter::from_fn(|| heap.pop()).map(|(_, c)| c).collect();
+        assert_eq!(order, vec!['b', 'c', 'd']);
+    }
+
+    #[test]
+    fn test_same_as_binary_heap() {
+        use rand::Rng;
+        fn fill<H: Heap<u32>>(heap: &mut H, data: &[u32]) -> Vec<u32> {
+            let mut ret = vec![];
+            for (i, value) in data.iter().enumerate() {
+                heap.push(*value);
+                if i % 3 == 0 {
+                    ret.extend(heap.pop());
+                }
+            }
+            while let Some(value) = heap.pop() {
+                ret.push(value);
+            }
+            ret
+        }
+        let mut rng = rand::thread_rng();
+        let data: Vec<u32> = (0..1000).map(|_| rng.gen_range(0..200)).collect();
+        let mut binary: BinaryHeap<u32> = BinaryHeap::new();
+        let mut pairing: PairingHeap<u32> = PairingHeap::new();
+        assert_eq!(fill(&mut pairing, &data), fill(&mut binary, &data));
+    }
+
+    #[test]
+    fn test_random_decrease_key() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut heap: PairingHeap<u32, MinComparator> = PairingHeap::new();
+        let mut values = vec![];
+        let mut handles = vec![];
+        for _ in 0..500 {
+            let value = rng.gen_range(1000..2000);
+            handles.push(heap.push_with_handle(value));
+            values.push(value);
+        }
+        for _ in 0..300 {
+            let i = rng.gen_range(0..500);
+            let value = rng.gen_range(0..=values[i]);
+            assert!(heap.decrease_key(&handles[i], value));
+            values[i] = value;
+        }
+        values.sort();
+        let popped: Vec<u32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, values);
+    }
+
+    #[test]
+    fn test_drop_invalidates_handles() {
+        let handle = {
+            let mut heap: PairingHeap<String> = PairingHeap::new();
+            heap.push("a".to_string());
+            heap.push_with_handle("b".to_string())
+        };
+        assert!(!handle.is_alive());
+    }
+}
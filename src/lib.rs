@@ -1,10 +1,14 @@
 mod stack;
 pub use stack::{
-    stack_array, stack_linked_list, stack_linked_list_2, stack_linked_list_persistent, stack_vec,
+    stack_array, stack_linked_list, stack_linked_list_2, stack_linked_list_persistent,
+    stack_lock_free, stack_min_max, stack_vec,
 };
 
 mod queue;
-pub use queue::{queue_linked_list, queue_priority, queue_vec};
+pub use queue::{
+    queue_async, queue_linked_list, queue_lock_free, queue_min_max, queue_persistent,
+    queue_priority, queue_priority_indexed, queue_vec, ring_buffer, ring_deque,
+};
 
 mod linked_list;
 pub use linked_list::{
@@ -27,7 +31,7 @@ mod graph;
 pub use graph::*;
 mod heap;
 pub use heap::*;
-mod map;
+pub mod map;
 pub use map::*;
 mod red_black_tree;
 pub use red_black_tree::*;
@@ -35,3 +39,5 @@ mod set;
 pub use set::*;
 mod trie;
 pub use trie::*;
+mod reclaim;
+pub use reclaim::hazard_pointer;
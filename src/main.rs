@@ -5,12 +5,21 @@ use ds::{
     stack_linked_list, stack_vec, simple_directed_weighted_sparse_graph
 };
 
+use ds::hasher::{
+    distribution, Adler32BuildHasher, Fnv1aBuildHasher, HashcodeBuildHasher, SipBuildHasher,
+    XxBuildHasher,
+};
+
+use ds::map::map::HashMap;
+use std::hash::{BuildHasher, Hash};
 use std::time::Instant;
 
 fn main() {
      cmp_llrb_vs_vec();
 
     // cmp_llrb_ptr_vs_vec();
+
+    // cmp_hashers();
  
 }
 
@@ -126,5 +135,75 @@ fn cmp_llrb_ptr_vs_vec() {
  
 }
 
- 
- 
\ No newline at end of file
+/*
+$ cargo run --release
+
+hash: 1M коротких ключей / 1M u64 / 100K путей по 256 байт; map: вставка и поиск 50K коротких ключей в ds::map::map::HashMap
+distribution: 65536 коротких ключей по 4096 корзинам
+
+adler32   hash   28 /   11 /   91 millis, map    32 millis
+          keys 65536 buckets 4096 hash collisions 63103 bucket collisions 65404 max load 3668 chi2/df 2281.405
+hashcode  hash    6 /    0 /   10 millis, map    44 millis
+          keys 65536 buckets 4096 hash collisions 64700 bucket collisions 64765 max load 193 chi2/df 113.004
+fnv1a     hash    7 /    6 /   31 millis, map     9 millis
+          keys 65536 buckets 4096 hash collisions 0 bucket collisions 61440 max load 30 chi2/df 0.956
+siphash   hash   16 /   14 /   15 millis, map     9 millis
+          keys 65536 buckets 4096 hash collisions 0 bucket collisions 61440 max load 32 chi2/df 1.034
+xxh64     hash   25 /   10 /    4 millis, map     9 millis
+          keys 65536 buckets 4096 hash collisions 0 bucket collisions 61440 max load 31 chi2/df 0.964
+
+Короткие ключи - fnv1a, длинные - xxh64, ключи от внешних клиентов - siphash со случайным ключом.
+adler32 и hashcode годятся только как контрольные суммы.
+*/
+#[allow(dead_code)]
+fn cmp_hashers() {
+    let short: Vec<String> = (0..1_000_000).map(|i| format!("device-{}", i)).collect();
+    let numbers: Vec<u64> = (0..1_000_000).map(|_| rand::random::<u64>()).collect();
+    let long: Vec<String> = (0..100_000)
+        .map(|i| format!("/building/{:06}/", i).repeat(16)[..256].to_string())
+        .collect();
+
+    fn bench<B: BuildHasher>(
+        name: &str,
+        build: B,
+        short: &[String],
+        numbers: &[u64],
+        long: &[String],
+    ) {
+        fn hash_all<B: BuildHasher, T: Hash>(build: &B, keys: &[T]) -> u128 {
+            let now = Instant::now();
+            let mut sum = 0_u64;
+            for key in keys {
+                sum = sum.wrapping_add(build.hash_one(key));
+            }
+            assert_ne!(sum, 1);
+            now.elapsed().as_millis()
+        }
+        let short_millis = hash_all(&build, short);
+        let numbers_millis = hash_all(&build, numbers);
+        let long_millis = hash_all(&build, long);
+
+        let now = Instant::now();
+        let mut map = HashMap::with_hasher(build);
+        // на слабых хешах длинные цепочки коллизий, поэтому ключей в 20 раз меньше
+        for (i, key) in short[..50_000].iter().enumerate() {
+            map.insert(key.as_str(), i);
+        }
+        for key in short[..50_000].iter() {
+            assert!(map.contains_key(key.as_str()));
+        }
+        let map_millis = now.elapsed().as_millis();
+
+        let report = distribution(map.hasher(), &short[..65536], 4096);
+        println!(
+            "{:<9} hash {:>4} / {:>4} / {:>4} millis, map {:>5} millis\n          {}",
+            name, short_millis, numbers_millis, long_millis, map_millis, report
+        );
+    }
+
+    bench("adler32", Adler32BuildHasher::default(), &short, &numbers, &long);
+    bench("hashcode", HashcodeBuildHasher::default(), &short, &numbers, &long);
+    bench("fnv1a", Fnv1aBuildHasher::default(), &short, &numbers, &long);
+    bench("siphash", SipBuildHasher::default(), &short, &numbers, &long);
+    bench("xxh64", XxBuildHasher::default(), &short, &numbers, &long);
+}
@@ -0,0 +1,570 @@
+#![allow(dead_code)]
+
+//! Хеш-функции байтов и их `std::hash::Hasher` для `map::HashMap`.
+//!
+//! - `adler32`, `hashcode` - простые контрольные суммы, плохо перемешивают биты;
+//! - FNV-1a - побайтовый xor и умножение, быстрый на коротких ключах;
+//! - SipHash-2-4 с 128-битным ключом - устойчив к подбору коллизий, как `RandomState`;
+//! - XXH64 - полосы по 32 байта в четырёх независимых аккумуляторах, быстрый на длинных ключах.
+//!
+//! Все хешеры потоковые: `write` можно вызывать по частям, результат как от одного вызова.
+//! Хешеры без параметров строятся через `BuildHasherDefault`: `HashMap::with_hasher(Fnv1aBuildHasher::default())`.
+//! `distribution` показывает, как хешер раскладывает заданные ключи по корзинам.
+pub use ds_hasher::{
+    adler32, distribution, hashcode, Adler32BuildHasher, Adler32Hasher, Distribution,
+    Fnv1aBuildHasher, Fnv1aHasher, HashcodeBuildHasher, HashcodeHasher, SipBuildHasher,
+    SipHasher24, XxBuildHasher, XxHasher64,
+};
+mod ds_hasher {
+    use std::fmt;
+    use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+
+    const MOD_ADLER: u32 = 65521;
+
+    pub fn adler32(bytes: &[u8]) -> u32 {
+        let mut hasher = Adler32Hasher::default();
+        hasher.write(bytes);
+        hasher.finish() as u32
+    }
+
+    pub fn hashcode(bytes: &[u8]) -> u32 {
+        let mut hasher = HashcodeHasher::default();
+        hasher.write(bytes);
+        hasher.finish() as u32
+    }
+
+    pub struct Adler32Hasher {
+        a: u32,
+        b: u32,
+    }
+
+    impl Default for Adler32Hasher {
+        fn default() -> Self {
+            Adler32Hasher { a: 1, b: 0 }
+        }
+    }
+
+    impl Hasher for Adler32Hasher {
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.a = (self.a + *byte as u32) % MOD_ADLER;
+                self.b = (self.b + self.a) % MOD_ADLER;
+            }
+        }
+
+        fn finish(&self) -> u64 {
+            ((self.b << 16) | self.a) as u64
+        }
+    }
+
+    #[derive(Default)]
+    pub struct HashcodeHasher {
+        a: u32,
+        // число уже записанных байтов, от него зависит сдвиг
+        written: usize,
+    }
+
+    impl Hasher for HashcodeHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.a ^= *byte as u32;
+                self.a <<= self.written % 4;
+                self.written += 1;
+            }
+        }
+
+        fn finish(&self) -> u64 {
+            self.a as u64
+        }
+    }
+
+    pub type Adler32BuildHasher = BuildHasherDefault<Adler32Hasher>;
+    pub type HashcodeBuildHasher = BuildHasherDefault<HashcodeHasher>;
+
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub struct Fnv1aHasher(u64);
+
+    impl Default for Fnv1aHasher {
+        fn default() -> Self {
+            Fnv1aHasher(FNV_OFFSET)
+        }
+    }
+
+    impl Hasher for Fnv1aHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 ^= *byte as u64;
+                self.0 = self.0.wrapping_mul(FNV_PRIME);
+            }
+        }
+
+        fn finish(&self) -> u64 {
+            self.0
+        }
+    }
+
+    pub type Fnv1aBuildHasher = BuildHasherDefault<Fnv1aHasher>;
+
+    /// SipHash-2-4: два раунда на слово сообщения, четыре при завершении
+    #[derive(Clone)]
+    pub struct SipHasher24 {
+        v: [u64; 4],
+        // неполное слово из последних байтов и их число
+        tail: u64,
+        tail_len: usize,
+        length: usize,
+    }
+
+    impl SipHasher24 {
+        pub fn new(k0: u64, k1: u64) -> Self {
+            SipHasher24 {
+                v: [
+                    k0 ^ 0x736f_6d65_7073_6575,
+                    k1 ^ 0x646f_7261_6e64_6f6d,
+                    k0 ^ 0x6c79_6765_6e65_7261,
+                    k1 ^ 0x7465_6462_7974_6573,
+                ],
+                tail: 0,
+                tail_len: 0,
+                length: 0,
+            }
+        }
+
+        fn round(v: &mut [u64; 4]) {
+            v[0] = v[0].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(13) ^ v[0];
+            v[0] = v[0].rotate_left(32);
+            v[2] = v[2].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(16) ^ v[2];
+            v[0] = v[0].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(21) ^ v[0];
+            v[2] = v[2].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(17) ^ v[2];
+            v[2] = v[2].rotate_left(32);
+        }
+
+        fn compress(v: &mut [u64; 4], word: u64) {
+            v[3] ^= word;
+            Self::round(v);
+            Self::round(v);
+            v[0] ^= word;
+        }
+    }
+
+    impl Hasher for SipHasher24 {
+        fn write(&mut self, mut bytes: &[u8]) {
+            self.length += bytes.len();
+            while self.tail_len > 0 && !bytes.is_empty() {
+                self.tail |= (bytes[0] as u64) << (8 * self.tail_len);
+                self.tail_len = (self.tail_len + 1) % 8;
+                bytes = &bytes[1..];
+                if self.tail_len == 0 {
+                    Self::compress(&mut self.v, self.tail);
+                    self.tail = 0;
+                }
+            }
+            if self.tail_len > 0 {
+                // все байты ушли в недобранное слово
+                return;
+            }
+            let mut words = bytes.chunks_exact(8);
+            for word in &mut words {
+                Self::compress(&mut self.v, u64::from_le_bytes(word.try_into().unwrap()));
+            }
+            for (i, byte) in words.remainder().iter().enumerate() {
+                self.tail |= (*byte as u64) << (8 * i);
+            }
+            self.tail_len = words.remainder().len();
+        }
+
+        fn finish(&self) -> u64 {
+            let mut v = self.v;
+            Self::compress(&mut v, ((self.length as u64) << 56) | self.tail);
+            v[2] ^= 0xff;
+            for _ in 0..4 {
+                Self::round(&mut v);
+            }
+            v[0] ^ v[1] ^ v[2] ^ v[3]
+        }
+    }
+
+    /// Ключ SipHash; `Default` берёт случайный ключ, как `RandomState`
+    #[derive(Clone, Copy, Debug)]
+    pub struct SipBuildHasher {
+        k0: u64,
+        k1: u64,
+    }
+
+    impl SipBuildHasher {
+        pub fn new(k0: u64, k1: u64) -> Self {
+            SipBuildHasher { k0, k1 }
+        }
+    }
+
+    impl Default for SipBuildHasher {
+        fn default() -> Self {
+            Self::new(rand::random(), rand::random())
+        }
+    }
+
+    impl BuildHasher for SipBuildHasher {
+        type Hasher = SipHasher24;
+
+        fn build_hasher(&self) -> SipHasher24 {
+            SipHasher24::new(self.k0, self.k1)
+        }
+    }
+
+    const XX_PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+    const XX_PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+    const XX_PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+    const XX_PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+    const XX_PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+    /// XXH64: байты копятся в полосу из 32 байтов, полоса - четыре слова для четырёх аккумуляторов
+    #[derive(Clone)]
+    pub struct XxHasher64 {
+        seed: u64,
+        lanes: [u64; 4],
+        buffer: [u8; 32],
+        buffered: usize,
+        length: usize,
+    }
+
+    impl XxHasher64 {
+        pub fn new(seed: u64) -> Self {
+            XxHasher64 {
+                seed,
+                lanes: [
+                    seed.wrapping_add(XX_PRIME_1).wrapping_add(XX_PRIME_2),
+                    seed.wrapping_add(XX_PRIME_2),
+                    seed,
+                    seed.wrapping_sub(XX_PRIME_1),
+                ],
+                buffer: [0; 32],
+                buffered: 0,
+                length: 0,
+            }
+        }
+
+        fn round(lane: u64, word: u64) -> u64 {
+            lane.wrapping_add(word.wrapping_mul(XX_PRIME_2))
+                .rotate_left(31)
+                .wrapping_mul(XX_PRIME_1)
+        }
+
+        fn merge(hash: u64, lane: u64) -> u64 {
+            (hash ^ Self::round(0, lane))
+                .wrapping_mul(XX_PRIME_1)
+                .wrapping_add(XX_PRIME_4)
+        }
+
+        fn stripe(lanes: &mut [u64; 4], stripe: &[u8]) {
+            for (lane, word) in lanes.iter_mut().zip(stripe.chunks_exact(8)) {
+                *lane = Self::round(*lane, u64::from_le_bytes(word.try_into().unwrap()));
+            }
+        }
+    }
+
+    impl Default for XxHasher64 {
+        fn default() -> Self {
+            Self::new(0)
+        }
+    }
+
+    impl Hasher for XxHasher64 {
+        fn write(&mut self, mut bytes: &[u8]) {
+            self.length += bytes.len();
+            if self.buffered > 0 {
+                let take = bytes.len().min(32 - self.buffered);
+                self.buffer[self.buffered..self.buffered + take].copy_from_slice(&bytes[..take]);
+                self.buffered += take;
+                bytes = &bytes[take..];
+                if self.buffered < 32 {
+                    return;
+                }
+                let buffer = self.buffer;
+                Self::stripe(&mut self.lanes, &buffer);
+                self.buffered = 0;
+            }
+            let mut stripes = bytes.chunks_exact(32);
+            for stripe in &mut stripes {
+                Self::stripe(&mut self.lanes, stripe);
+            }
+            let rest = stripes.remainder();
+            self.buffer[..rest.len()].copy_from_slice(rest);
+            self.buffered = rest.len();
+        }
+
+        fn finish(&self) -> u64 {
+            let [v1, v2, v3, v4] = self.lanes;
+            let mut hash = if self.length >= 32 {
+                let hash = v1
+                    .rotate_left(1)
+                    .wrapping_add(v2.rotate_left(7))
+                    .wrapping_add(v3.rotate_left(12))
+                    .wrapping_add(v4.rotate_left(18));
+                self.lanes
+                    .iter()
+                    .fold(hash, |hash, lane| Self::merge(hash, *lane))
+            } else {
+                self.seed.wrapping_add(XX_PRIME_5)
+            };
+            hash = hash.wrapping_add(self.length as u64);
+
+            let mut rest = &self.buffer[..self.buffered];
+            while rest.len() >= 8 {
+                let word = u64::from_le_bytes(rest[..8].try_into().unwrap());
+                hash ^= Self::round(0, word);
+                hash = hash
+                    .rotate_left(27)
+                    .wrapping_mul(XX_PRIME_1)
+                    .wrapping_add(XX_PRIME_4);
+                rest = &rest[8..];
+            }
+            if rest.len() >= 4 {
+                let word = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+                hash ^= word.wrapping_mul(XX_PRIME_1);
+                hash = hash
+                    .rotate_left(23)
+                    .wrapping_mul(XX_PRIME_2)
+                    .wrapping_add(XX_PRIME_3);
+                rest = &rest[4..];
+            }
+            for byte in rest {
+                hash ^= (*byte as u64).wrapping_mul(XX_PRIME_5);
+                hash = hash.rotate_left(11).wrapping_mul(XX_PRIME_1);
+            }
+
+            // лавина: каждый бит входа влияет на все биты результата
+            hash ^= hash >> 33;
+            hash = hash.wrapping_mul(XX_PRIME_2);
+            hash ^= hash >> 29;
+            hash = hash.wrapping_mul(XX_PRIME_3);
+            hash ^ (hash >> 32)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct XxBuildHasher {
+        seed: u64,
+    }
+
+    impl XxBuildHasher {
+        pub fn new(seed: u64) -> Self {
+            XxBuildHasher { seed }
+        }
+    }
+
+    impl BuildHasher for XxBuildHasher {
+        type Hasher = XxHasher64;
+
+        fn build_hasher(&self) -> XxHasher64 {
+            XxHasher64::new(self.seed)
+        }
+    }
+
+    /// Раскладка ключей по корзинам `hash % buckets`
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Distribution {
+        pub keys: usize,
+        pub buckets: usize,
+        /// Пары ключей с одинаковым 64-битным хешем (ключи считаются различными)
+        pub hash_collisions: usize,
+        /// Ключи, попавшие в уже занятую корзину
+        pub bucket_collisions: usize,
+        pub max_load: usize,
+        /// Хи-квадрат, делённый на число степеней свободы: около 1 для равномерного хеша
+        pub chi_squared: f64,
+    }
+
+    impl fmt::Display for Distribution {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "keys {} buckets {} hash collisions {} bucket collisions {} max load {} chi2/df {:.3}",
+                self.keys,
+                self.buckets,
+                self.hash_collisions,
+                self.bucket_collisions,
+                self.max_load,
+                self.chi_squared
+            )
+        }
+    }
+
+    /// Раскладывает хеши ключей по `buckets` корзинам остатком от деления.
+    /// Паникует при нулевом числе корзин
+    pub fn distribution<B, T, I>(build: &B, keys: I, buckets: usize) -> Distribution
+    where
+        B: BuildHasher,
+        T: Hash,
+        I: IntoIterator<Item = T>,
+    {
+        assert!(buckets > 0, "distribution needs at least one bucket");
+        let mut hashes: Vec<u64> = keys.into_iter().map(|key| build.hash_one(key)).collect();
+        let mut loads = vec![0_usize; buckets];
+        for hash in &hashes {
+            loads[(*hash % buckets as u64) as usize] += 1;
+        }
+        hashes.sort_unstable();
+        let hash_collisions = hashes.windows(2).filter(|pair| pair[0] == pair[1]).count();
+
+        let keys = hashes.len();
+        let expected = keys as f64 / buckets as f64;
+        let chi_squared = loads
+            .iter()
+            .map(|load| (*load as f64 - expected).powi(2) / expected)
+            .sum::<f64>()
+            / (buckets.max(2) - 1) as f64;
+        Distribution {
+            keys,
+            buckets,
+            hash_collisions,
+            bucket_collisions: keys - loads.iter().filter(|load| **load > 0).count(),
+            max_load: loads.iter().copied().max().unwrap_or(0),
+            chi_squared,
+        }
+    }
+}
+
+/// $ cargo test hasher
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(hashcode(b"ab"), (97 ^ 98) << 1);
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut hasher = Adler32Hasher::default();
+        hasher.write(b"Wiki");
+        hasher.write(b"pedia");
+        assert_eq!(hasher.finish(), adler32(b"Wikipedia") as u64);
+
+        let mut hasher = HashcodeHasher::default();
+        hasher.write(b"dev");
+        hasher.write(b"ice-1");
+        assert_eq!(hasher.finish(), hashcode(b"device-1") as u64);
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        let mut hasher = Fnv1aHasher::default();
+        assert_eq!(hasher.finish(), 0xcbf29ce484222325);
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+        hasher.write(b"bc");
+        let mut whole = Fnv1aHasher::default();
+        whole.write(b"abc");
+        assert_eq!(hasher.finish(), whole.finish());
+    }
+
+    /// Эталон - `std::hash::SipHasher`, тот же SipHash-2-4
+    #[test]
+    #[allow(deprecated)]
+    fn test_siphash() {
+        let mut hasher = SipHasher24::new(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        assert_eq!(hasher.finish(), 0x726fdb47dd0e0e31);
+
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let (k0, k1) = (rng.gen(), rng.gen());
+            let bytes: Vec<u8> = (0..rng.gen_range(0..100)).map(|_| rng.gen()).collect();
+            // несколько кусков подряд, в том числе короткие внутри недобранного слова
+            let mut splits: Vec<usize> = (0..rng.gen_range(3..8))
+                .map(|_| rng.gen_range(0..=bytes.len()))
+                .collect();
+            splits.sort();
+            hasher = SipBuildHasher::new(k0, k1).build_hasher();
+            let mut from = 0;
+            for to in splits.into_iter().chain([bytes.len()]) {
+                hasher.write(&bytes[from..to]);
+                from = to;
+            }
+            let mut expected = std::hash::SipHasher::new_with_keys(k0, k1);
+            expected.write(&bytes);
+            assert_eq!(hasher.finish(), expected.finish());
+        }
+
+        // кортеж пишется по два байта за вызов
+        let mut expected = std::hash::SipHasher::new_with_keys(1, 2);
+        (1u16, 2u16, 3u16).hash(&mut expected);
+        assert_eq!(
+            SipBuildHasher::new(1, 2).hash_one((1u16, 2u16, 3u16)),
+            expected.finish()
+        );
+    }
+
+    #[test]
+    fn test_xxhash() {
+        let xxh64 = |bytes: &[u8], seed: u64| {
+            let mut hasher = XxHasher64::new(seed);
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(xxh64(b"", 0), 0xef46db3751d8e999);
+        assert_eq!(xxh64(b"a", 0), 0xd24ec4f1a98c6e5b);
+        assert_eq!(xxh64(b"abc", 0), 0x44bc2cf5ad770999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition", 0),
+            0xfbcea83c8a378bf1
+        );
+        assert_ne!(xxh64(b"abc", 1), xxh64(b"abc", 0));
+
+        let bytes: Vec<u8> = (0..200).map(|i| (i * 7) as u8).collect();
+        for split in [0, 1, 31, 32, 33, 64, 100, 199] {
+            let mut hasher = XxBuildHasher::new(9).build_hasher();
+            hasher.write(&bytes[..split]);
+            hasher.write(&bytes[split..]);
+            assert_eq!(hasher.finish(), xxh64(&bytes, 9));
+        }
+    }
+
+    #[test]
+    fn test_distribution() {
+        let keys = || (0..4096).map(|i| format!("device-{}", i));
+        let sip = distribution(&SipBuildHasher::default(), keys(), 1024);
+        let xx = distribution(&XxBuildHasher::default(), keys(), 1024);
+        let weak = distribution(&HashcodeBuildHasher::default(), keys(), 1024);
+        assert_eq!(sip.keys, 4096);
+        assert_eq!((sip.hash_collisions, xx.hash_collisions), (0, 0));
+        assert!(sip.chi_squared < 2.0 && xx.chi_squared < 2.0);
+        // младшие биты `hashcode` почти всегда нули
+        assert!(weak.chi_squared > 10.0 * sip.chi_squared);
+        assert!(weak.max_load > sip.max_load);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one bucket")]
+    fn test_distribution_no_buckets() {
+        distribution(&Fnv1aBuildHasher::default(), ["a"], 0);
+    }
+
+    #[test]
+    fn test_hash_map() {
+        use crate::map::map::HashMap;
+        let mut fnv = HashMap::with_hasher(Fnv1aBuildHasher::default());
+        let mut sip = HashMap::with_hasher(SipBuildHasher::default());
+        let mut xx = HashMap::with_hasher(XxBuildHasher::new(7));
+        for i in 0..500 {
+            fnv.insert(i, i);
+            sip.insert(i, i);
+            xx.insert(i, i);
+        }
+        assert_eq!(
+            (fnv.get(&42), sip.get(&42), xx.get(&42)),
+            (Some(&42), Some(&42), Some(&42))
+        );
+        assert_eq!(fnv.len() + sip.len() + xx.len(), 1500);
+    }
+}
@@ -0,0 +1,444 @@
+#![allow(dead_code)]
+
+//! Кеш с вытеснением давно неиспользуемых записей (LRU).
+//!
+//! Записи лежат в арене `Vec` и связаны индексами в двусвязный список от самой свежей к самой старой,
+//! `map::HashMap` отображает ключ в индекс записи. Поэтому `get`, `put` и вытеснение - O(1)
+//! без выделения памяти на каждую запись. Удалённая запись заменяется последней в арене,
+//! так что арена всегда плотная.
+//!
+//! Ключ хранится дважды: в `map` и в записи арены, откуда его берут `pop_lru`, вытеснение и `iter`.
+//! Поэтому нужен `K: Clone`, и новый ключ клонируется один раз при вставке. Для дорогих ключей
+//! вроде `String` лучше брать `Rc<str>`.
+//!
+//! - `get` поднимает запись в начало списка, `peek` - нет;
+//! - `put` в заполненный кеш вытесняет самую старую запись и передаёт её в `set_on_evict`;
+//! - `pop` удаляет запись явно, без обратного вызова.
+pub use ds_lru_cache::{Iter, LruCache};
+mod ds_lru_cache {
+    use crate::map::map::{self, HashMap};
+    use std::borrow::Borrow;
+    use std::collections::hash_map::RandomState;
+    use std::fmt;
+    use std::hash::{BuildHasher, Hash};
+    use std::mem;
+
+    struct Entry<K, V> {
+        key: K,
+        value: V,
+        // соседи по списку: prev - свежее, next - старее
+        prev: Option<usize>,
+        next: Option<usize>,
+    }
+
+    pub struct LruCache<K, V, S = RandomState> {
+        map: HashMap<K, usize, S>,
+        entries: Vec<Entry<K, V>>,
+        // самая свежая и самая старая записи
+        head: Option<usize>,
+        tail: Option<usize>,
+        capacity: usize,
+        on_evict: Option<Box<dyn FnMut(K, V)>>,
+    }
+
+    impl<K: Hash + Eq + Clone, V> LruCache<K, V, RandomState> {
+        pub fn new(capacity: usize) -> Self {
+            Self::with_hasher(capacity, RandomState::new())
+        }
+    }
+
+    impl<K: Hash + Eq + Clone, V, S: BuildHasher> LruCache<K, V, S> {
+        /// Паникует при нулевой ёмкости
+        pub fn with_hasher(capacity: usize, hash_builder: S) -> Self {
+            assert!(capacity > 0, "LruCache capacity must be positive");
+            LruCache {
+                map: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+                entries: Vec::with_capacity(capacity),
+                head: None,
+                tail: None,
+                capacity,
+                on_evict: None,
+            }
+        }
+
+        /// Вызывается для каждой записи, вытесненной из-за нехватки места
+        pub fn set_on_evict(&mut self, callback: impl FnMut(K, V) + 'static) {
+            self.on_evict = Some(Box::new(callback));
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.capacity
+        }
+
+        /// Значение по ключу; запись становится самой свежей
+        pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            let index = *self.map.get(key)?;
+            self.promote(index);
+            Some(&self.entries[index].value)
+        }
+
+        pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            let index = *self.map.get(key)?;
+            self.promote(index);
+            Some(&mut self.entries[index].value)
+        }
+
+        /// Значение по ключу без изменения порядка
+        pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            self.map.get(key).map(|index| &self.entries[*index].value)
+        }
+
+        /// Самая старая запись, она будет вытеснена следующей
+        pub fn peek_lru(&self) -> Option<(&K, &V)> {
+            self.tail.map(|index| {
+                let entry = &self.entries[index];
+                (&entry.key, &entry.value)
+            })
+        }
+
+        pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            self.map.contains_key(key)
+        }
+
+        /// Вставляет или заменяет значение и делает запись самой свежей.
+        /// Возвращает прежнее значение ключа. Если места нет, вытесняет самую старую запись
+        pub fn put(&mut self, key: K, value: V) -> Option<V> {
+            // ключ хешируется один раз: при нехватке места новая запись занимает место самой старой
+            let full = self.entries.len() == self.capacity;
+            let (index, key) = match self.map.entry(key) {
+                map::Entry::Occupied(occupied) => {
+                    let index = *occupied.get();
+                    self.promote(index);
+                    return Some(mem::replace(&mut self.entries[index].value, value));
+                }
+                map::Entry::Vacant(vacant) => {
+                    let index = match self.tail {
+                        Some(tail) if full => tail,
+                        _ => self.entries.len(),
+                    };
+                    let key = vacant.key().clone();
+                    vacant.insert(index);
+                    (index, key)
+                }
+            };
+            let entry = Entry {
+                key,
+                value,
+                prev: None,
+                next: None,
+            };
+            if full {
+                self.unlink(index);
+                let old = mem::replace(&mut self.entries[index], entry);
+                self.map.remove(&old.key);
+                if let Some(on_evict) = self.on_evict.as_mut() {
+                    on_evict(old.key, old.value);
+                }
+            } else {
+                self.entries.push(entry);
+            }
+            self.push_front(index);
+            None
+        }
+
+        /// Удаляет запись без вызова `on_evict`
+        pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+        {
+            let index = self.map.remove(key)?;
+            Some(self.remove_entry(index).1)
+        }
+
+        /// Удаляет самую старую запись без вызова `on_evict`
+        pub fn pop_lru(&mut self) -> Option<(K, V)> {
+            let index = self.tail?;
+            self.map.remove(&self.entries[index].key);
+            Some(self.remove_entry(index))
+        }
+
+        /// Меняет ёмкость, лишние старые записи вытесняются. Паникует при нулевой ёмкости
+        pub fn resize(&mut self, capacity: usize) {
+            assert!(capacity > 0, "LruCache capacity must be positive");
+            self.capacity = capacity;
+            while self.entries.len() > capacity {
+                self.evict();
+            }
+        }
+
+        pub fn clear(&mut self) {
+            self.map.clear();
+            self.entries.clear();
+            self.head = None;
+            self.tail = None;
+        }
+
+        /// Записи от самой свежей к самой старой
+        pub fn iter(&self) -> Iter<'_, K, V> {
+            Iter {
+                entries: &self.entries,
+                next: self.head,
+                remaining: self.entries.len(),
+            }
+        }
+
+        fn evict(&mut self) {
+            if let Some((key, value)) = self.pop_lru() {
+                if let Some(on_evict) = self.on_evict.as_mut() {
+                    on_evict(key, value);
+                }
+            }
+        }
+
+        fn promote(&mut self, index: usize) {
+            if self.head != Some(index) {
+                self.unlink(index);
+                self.push_front(index);
+            }
+        }
+
+        fn push_front(&mut self, index: usize) {
+            self.entries[index].prev = None;
+            self.entries[index].next = self.head;
+            match self.head {
+                Some(head) => self.entries[head].prev = Some(index),
+                None => self.tail = Some(index),
+            }
+            self.head = Some(index);
+        }
+
+        fn unlink(&mut self, index: usize) {
+            let (prev, next) = (self.entries[index].prev, self.entries[index].next);
+            match prev {
+                Some(prev) => self.entries[prev].next = next,
+                None => self.head = next,
+            }
+            match next {
+                Some(next) => self.entries[next].prev = prev,
+                None => self.tail = prev,
+            }
+        }
+
+        /// Вынимает запись из списка и арены; ключ уже удалён из `map`.
+        /// Последняя запись арены переезжает на освободившееся место
+        fn remove_entry(&mut self, index: usize) -> (K, V) {
+            self.unlink(index);
+            let last = self.entries.len() - 1;
+            if index != last {
+                let (prev, next) = (self.entries[last].prev, self.entries[last].next);
+                match prev {
+                    Some(prev) => self.entries[prev].next = Some(index),
+                    None => self.head = Some(index),
+                }
+                match next {
+                    Some(next) => self.entries[next].prev = Some(index),
+                    None => self.tail = Some(index),
+                }
+                if let Some(moved) = self.map.get_mut(&self.entries[last].key) {
+                    *moved = index;
+                }
+            }
+            let entry = self.entries.swap_remove(index);
+            (entry.key, entry.value)
+        }
+    }
+
+    pub struct Iter<'a, K, V> {
+        entries: &'a [Entry<K, V>],
+        next: Option<usize>,
+        remaining: usize,
+    }
+
+    impl<'a, K, V> Iterator for Iter<'a, K, V> {
+        type Item = (&'a K, &'a V);
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let entry = &self.entries[self.next?];
+            self.next = entry.next;
+            self.remaining -= 1;
+            Some((&entry.key, &entry.value))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.remaining, Some(self.remaining))
+        }
+    }
+
+    impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+    impl<K: fmt::Debug + Hash + Eq + Clone, V: fmt::Debug, S: BuildHasher> fmt::Debug
+        for LruCache<K, V, S>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_map().entries(self.iter()).finish()
+        }
+    }
+}
+
+/// $ cargo test lru_cache
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn keys(cache: &LruCache<&'static str, u32>) -> Vec<&'static str> {
+        cache.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn test_success() {
+        let mut cache = LruCache::new(3);
+        assert_eq!(cache.put("a", 1), None);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        assert_eq!(keys(&cache), vec!["c", "b", "a"]);
+
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(keys(&cache), vec!["a", "c", "b"]);
+        assert_eq!(cache.peek("b"), Some(&2));
+        assert_eq!(cache.peek_lru(), Some((&"b", &2)));
+
+        cache.put("d", 4);
+        assert!(!cache.contains_key("b"));
+        assert_eq!(keys(&cache), vec!["d", "a", "c"]);
+
+        assert_eq!(cache.put("c", 30), Some(3));
+        assert_eq!(keys(&cache), vec!["c", "d", "a"]);
+        if let Some(value) = cache.get_mut("a") {
+            *value += 10;
+        }
+        assert_eq!(cache.pop("a"), Some(11));
+        assert_eq!(cache.pop("a"), None);
+        assert_eq!(cache.pop_lru(), Some(("d", 4)));
+        assert_eq!(keys(&cache), vec!["c"]);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_on_evict() {
+        let evicted = Rc::new(RefCell::new(vec![]));
+        let mut cache = LruCache::new(2);
+        let log = Rc::clone(&evicted);
+        cache.set_on_evict(move |key, value| log.borrow_mut().push((key, value)));
+
+        for (i, key) in ["a", "b", "c", "a", "d"].iter().enumerate() {
+            cache.put(*key, i);
+        }
+        cache.pop("a");
+        assert_eq!(*evicted.borrow(), vec![("a", 0), ("b", 1), ("c", 2)]);
+
+        cache.put("e", 5);
+        cache.put("f", 6);
+        cache.resize(1);
+        assert_eq!(evicted.borrow()[3..], [("d", 4), ("e", 5)]);
+        assert_eq!(cache.iter().collect::<Vec<_>>(), vec![(&"f", &6)]);
+    }
+
+    thread_local! {
+        static HASHED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        static CLONED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// Ключ, считающий свои хеши и клоны
+    #[derive(PartialEq, Eq, Debug)]
+    struct Counted(u32);
+
+    impl std::hash::Hash for Counted {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            HASHED.with(|hashed| hashed.set(hashed.get() + 1));
+            self.0.hash(state);
+        }
+    }
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONED.with(|cloned| cloned.set(cloned.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    /// `put` ищет новый ключ в таблице один раз и клонирует его только при вставке
+    #[test]
+    fn test_put_hashes_once() {
+        let counts = || (HASHED.with(|c| c.get()), CLONED.with(|c| c.get()));
+        let mut cache = LruCache::new(2);
+        let before = counts();
+        cache.put(Counted(1), "a");
+        cache.put(Counted(2), "b");
+        assert_eq!(counts(), (before.0 + 2, before.1 + 2));
+
+        // замена значения не клонирует ключ
+        assert_eq!(cache.put(Counted(1), "aa"), Some("a"));
+        assert_eq!(counts(), (before.0 + 3, before.1 + 2));
+
+        // вытеснение хеширует ещё и старый ключ, чтобы убрать его из таблицы
+        cache.put(Counted(3), "c");
+        assert_eq!(counts(), (before.0 + 5, before.1 + 3));
+        let keys: Vec<u32> = cache.iter().map(|(key, _)| key.0).collect();
+        assert_eq!(keys, vec![3, 1]);
+        assert_eq!(cache.peek(&Counted(2)), None);
+    }
+
+    /// Сверка с моделью: вектор ключей от свежего к старому
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut cache = LruCache::new(8);
+        let mut model: Vec<(u8, u32)> = vec![];
+        for step in 0..5000 {
+            let key = rng.gen_range(0..16);
+            let position = model.iter().position(|(k, _)| *k == key);
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let old = position.map(|i| model.remove(i).1);
+                    if old.is_none() && model.len() == 8 {
+                        model.pop();
+                    }
+                    model.insert(0, (key, step));
+                    assert_eq!(cache.put(key, step), old);
+                }
+                2 => {
+                    let expected = position.map(|i| {
+                        let entry = model.remove(i);
+                        model.insert(0, entry);
+                        entry.1
+                    });
+                    assert_eq!(cache.get(&key).copied(), expected);
+                }
+                _ => {
+                    let expected = position.map(|i| model.remove(i).1);
+                    assert_eq!(cache.pop(&key), expected);
+                }
+            }
+            let entries: Vec<(u8, u32)> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+            assert_eq!(entries, model);
+        }
+    }
+}
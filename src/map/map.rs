@@ -1,6 +1,29 @@
 #![allow(dead_code)]
 // https://github.com/PacktPublishing/Hands-On-Data-Structures-and-Algorithms-with-Rust/blob/master/Chapter06/src/map.rs
 
+//! Хеш-таблица с открытой адресацией по схеме Robin Hood.
+//!
+//! Элементы лежат прямо в массиве слотов, коллизии решаются линейным пробированием.
+//! При вставке элемент, ушедший от своего идеального слота дальше, чем текущий жилец,
+//! занимает его место, а жилец продолжает поиск. Так расстояния пробирования выравниваются,
+//! и поиск можно прекратить, встретив элемент ближе к своему слоту, чем искомый.
+//! Удаление сдвигает следующие элементы назад, без надгробий.
+//!
+//! Хеш считает `BuildHasher` (по умолчанию `RandomState`), слот выбирается умножением
+//! Фибоначчи, поэтому подходят и слабые хеши вроде `hasher::Adler32BuildHasher`.
+//! Таблица удваивается при заполнении больше чем на 7/8.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use super::lru_cache::LruCache;
+
+// хеш-функции жили здесь до переезда в `hasher`, старые пути остаются рабочими
+pub use super::hasher::{adler32, hashcode};
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct LocationInformation {
     name: String,
@@ -8,86 +31,643 @@ pub struct LocationInformation {
     address: String,
     security_group_name: String,
 }
-const MOD_ADLER: u32 = 65521;
 
-pub fn adler32(bytes: &[u8]) -> u32 {
-    let mut a = 1_u32;
-    let mut b = 0_u32;
+/// Ограниченный по размеру кеш: при заполнении вытесняются давно запрошенные адреса
+pub type LocationCache = LruCache<String, LocationInformation>;
 
-    for byte in bytes {
-        a = (a + *byte as u32) % MOD_ADLER;
-        b = (b + a) % MOD_ADLER;
-    }
+const MIN_CAPACITY: usize = 8;
 
-    (b << 16) | a
+#[derive(Clone)]
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
 }
 
-pub fn hashcode(bytes: &[u8]) -> u32 {
-    let mut a = 0_u32;
-    for (i, b) in bytes.iter().enumerate() {
-        a ^= *b as u32;
-        a <<= i % 4;
-    }
-    a
+#[derive(Clone)]
+pub struct HashMap<K, V, S = RandomState> {
+    hash_builder: S,
+    // число слотов - степень двойки или 0
+    slots: Box<[Option<Slot<K, V>>]>,
+    length: usize,
 }
 
-type Entry<K, V> = Vec<(K, V)>;
+impl<K, V> HashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
 
-pub type LocationCache = HashMap<String, LocationInformation>;
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
 
-pub struct HashMap<K, V>
-where
-    K: PartialEq + Clone,
-    V: Clone,
-{
-    hash_fn: Box<dyn (Fn(&K) -> usize)>,
-    store: Box<[Entry<K, V>]>,
-    pub length: usize,
+impl<K, V, S: Default> Default for HashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
 }
 
-impl<K, V> HashMap<K, V>
-where
-    K: PartialEq + Clone,
-    V: Clone,
-{
-    pub fn new(hash_fn: Box<dyn (Fn(&K) -> usize)>, length: usize) -> HashMap<K, V> {
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
         HashMap {
-            hash_fn: hash_fn,
+            hash_builder,
+            slots: Box::new([]),
             length: 0,
-            store: vec![vec![]; length].into_boxed_slice(),
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<V> {
-        let h = (self.hash_fn)(key);
-        let idx = h & (self.store.len() - 1);
-        self.store[idx]
-            .iter()
-            .find(|e| e.0 == *key)
-            .map(|e| e.1.clone())
+    /// Таблица вмещает `capacity` элементов без перестройки
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let mut map = Self::with_hasher(hash_builder);
+        if capacity > 0 {
+            map.slots = empty_slots(slots_for(capacity));
+        }
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Сколько элементов поместится до следующего удвоения
+    pub fn capacity(&self) -> usize {
+        self.slots.len() / 8 * 7
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.length = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.slots.iter(),
+            remaining: self.length,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            slots: self.slots.iter_mut(),
+            remaining: self.length,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    /// Идеальный слот хеша: старшие биты произведения на 2^64 / φ
+    fn ideal(&self, hash: u64) -> usize {
+        let bits = self.slots.len().trailing_zeros();
+        (hash.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - bits)) as usize
+    }
+
+    /// Насколько элемент в слоте `index` ушёл от своего идеального слота
+    fn distance(&self, hash: u64, index: usize) -> usize {
+        index.wrapping_sub(self.ideal(hash)) & (self.slots.len() - 1)
+    }
+
+    /// Вставка ключа, которого точно нет, в таблицу со свободным местом.
+    /// Возвращает слот, в который попал новый элемент
+    fn insert_new(&mut self, mut slot: Slot<K, V>) -> usize {
+        let mask = self.slots.len() - 1;
+        let mut index = self.ideal(slot.hash);
+        let mut distance = 0;
+        let mut placed = None;
+        loop {
+            match &self.slots[index] {
+                None => {
+                    self.slots[index] = Some(slot);
+                    self.length += 1;
+                    return placed.unwrap_or(index);
+                }
+                Some(resident) => {
+                    let resident_distance = self.distance(resident.hash, index);
+                    if resident_distance < distance {
+                        // богатый жилец уступает место и ищет слот дальше
+                        let resident = self.slots[index].replace(slot).unwrap();
+                        slot = resident;
+                        placed.get_or_insert(index);
+                        distance = resident_distance;
+                    }
+                }
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+
+    fn grow(&mut self) {
+        let capacity = (self.slots.len() * 2).max(MIN_CAPACITY);
+        let slots = mem::replace(&mut self.slots, empty_slots(capacity));
+        self.length = 0;
+        for slot in Vec::from(slots).into_iter().flatten() {
+            self.insert_new(slot);
+        }
     }
 
-    pub fn remove(&mut self, key: K) -> Option<V> {
-        let h = (self.hash_fn)(&key);
-        let idx = h & (self.store.len() - 1);
-        match self.store[idx].iter().position(|e| e.0 == key) {
-            Some(pos) => {
-                self.length -= 1;
-                Some(self.store[idx].remove(pos).1)
+    /// Освобождает слот и сдвигает назад следующие элементы, стоящие не на своём месте
+    fn remove_at(&mut self, index: usize) -> Slot<K, V> {
+        let mask = self.slots.len() - 1;
+        let removed = self.slots[index].take().unwrap();
+        self.length -= 1;
+        let mut hole = index;
+        loop {
+            let next = (hole + 1) & mask;
+            match &self.slots[next] {
+                Some(slot) if self.distance(slot.hash, next) > 0 => {
+                    self.slots[hole] = self.slots[next].take();
+                    hole = next;
+                }
+                _ => return removed,
             }
-            _ => None,
         }
     }
+}
 
-    pub fn insert(&mut self, key: K, value: V) {
-        let h = (self.hash_fn)(&key);
-        let idx = h & (self.store.len() - 1);
-        match self.store[idx].iter().position(|e| e.0 == key) {
-            Some(pos) => self.store[idx][pos] = (key, value),
+impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
+    /// Возвращает прежнее значение ключа
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        self.slots[index].as_ref().map(|slot| &slot.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        self.slots[index].as_mut().map(|slot| &mut slot.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        Some(self.remove_at(index).value)
+    }
+
+    /// Место ключа в таблице для чтения или вставки без повторного поиска
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        let hash = self.hash_builder.hash_one(&key);
+        match self.find_hashed(hash, &key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
             None => {
-                self.store[idx].push((key, value));
-                self.length += 1
+                // место резервируется заранее, чтобы вставка не двигала слоты повторно
+                if self.length >= self.capacity() {
+                    self.grow();
+                }
+                Entry::Vacant(VacantEntry {
+                    map: self,
+                    hash,
+                    key,
+                })
+            }
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.length == 0 {
+            return None;
+        }
+        self.find_hashed(self.hash_builder.hash_one(key), key)
+    }
+
+    fn find_hashed<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let mask = self.slots.len() - 1;
+        let mut index = self.ideal(hash);
+        let mut distance = 0;
+        loop {
+            let slot = self.slots[index].as_ref()?;
+            if self.distance(slot.hash, index) < distance {
+                // искомый ключ занял бы этот слот раньше
+                return None;
+            }
+            if slot.hash == hash && slot.key.borrow() == key {
+                return Some(index);
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+}
+
+fn slots_for(capacity: usize) -> usize {
+    (capacity * 8 / 7 + 1).next_power_of_two().max(MIN_CAPACITY)
+}
+
+fn empty_slots<K, V>(capacity: usize) -> Box<[Option<Slot<K, V>>]> {
+    (0..capacity).map(|_| None).collect()
+}
+
+pub enum Entry<'a, K, V, S> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+pub struct OccupiedEntry<'a, K, V, S> {
+    map: &'a mut HashMap<K, V, S>,
+    index: usize,
+}
+
+pub struct VacantEntry<'a, K, V, S> {
+    map: &'a mut HashMap<K, V, S>,
+    hash: u64,
+    key: K,
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S> {
+    fn slot(&self) -> &Slot<K, V> {
+        self.map.slots[self.index].as_ref().unwrap()
+    }
+
+    pub fn key(&self) -> &K {
+        &self.slot().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.slot().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.slots[self.index].as_mut().unwrap().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.slots[self.index].as_mut().unwrap().value
+    }
+
+    /// Заменяет значение и возвращает прежнее
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.map.remove_at(self.index).value
+    }
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let index = self.map.insert_new(Slot {
+            hash: self.hash,
+            key: self.key,
+            value,
+        });
+        &mut self.map.slots[index].as_mut().unwrap().value
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    slots: std::slice::Iter<'a, Option<Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&slot.key, &slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    slots: std::slice::IterMut<'a, Option<Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&slot.key, &mut slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+pub struct IntoIter<K, V> {
+    slots: std::vec::IntoIter<Option<Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((slot.key, slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V, S> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter {
+            slots: Vec::from(self.slots).into_iter(),
+            remaining: self.length,
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut HashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Extend<(K, V)> for HashMap<K, V, S> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> FromIterator<(K, V)> for HashMap<K, V, S> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = HashMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HashMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// $ cargo test map::map
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::{Adler32BuildHasher, HashcodeBuildHasher};
+
+    #[test]
+    fn test_success() {
+        let mut map = HashMap::new();
+        assert_eq!(map.get("gateway"), None);
+        assert_eq!(map.insert("gateway".to_string(), 1), None);
+        assert_eq!(map.insert("sensor".to_string(), 2), None);
+        assert_eq!(map.insert("gateway".to_string(), 3), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("gateway"), Some(&3));
+
+        if let Some(value) = map.get_mut("sensor") {
+            *value += 10;
+        }
+        assert_eq!(map.get("sensor"), Some(&12));
+        assert_eq!(map.remove("sensor"), Some(12));
+        assert_eq!(map.remove("sensor"), None);
+        assert!(!map.contains_key("sensor"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for c in "hello world".chars() {
+            *counts.entry(c).or_default() += 1;
+        }
+        assert_eq!(counts.get(&'l'), Some(&3));
+        assert_eq!(counts.get(&'o'), Some(&2));
+
+        counts
+            .entry('h')
+            .and_modify(|count| *count = 10)
+            .or_insert(0);
+        counts
+            .entry('z')
+            .and_modify(|count| *count = 10)
+            .or_insert(0);
+        assert_eq!((counts.get(&'h'), counts.get(&'z')), (Some(&10), Some(&0)));
+
+        match counts.entry('w') {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 1),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(!counts.contains_key(&'w'));
+    }
+
+    #[test]
+    fn test_iterators_and_growth() {
+        let mut map: HashMap<u32, u32> = (0..1000).map(|i| (i, i * 2)).collect();
+        assert_eq!(map.len(), 1000);
+        assert!(map.capacity() >= 1000);
+        for (_, value) in map.iter_mut() {
+            *value += 1;
+        }
+        let mut pairs: Vec<(u32, u32)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        pairs.sort();
+        assert_eq!(pairs, (0..1000).map(|i| (i, i * 2 + 1)).collect::<Vec<_>>());
+        assert_eq!(map.keys().count(), 1000);
+        assert_eq!(map.values().map(|v| *v as u64).sum::<u64>(), 1000 * 1000);
+
+        let mut owned: Vec<(u32, u32)> = map.clone().into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, pairs);
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.iter().next(), None);
+    }
+
+    #[test]
+    fn test_build_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let mut map: HashMap<String, u32, BuildHasherDefault<DefaultHasher>> =
+            HashMap::with_capacity_and_hasher(16, BuildHasherDefault::default());
+        for i in 0..200 {
+            map.insert(format!("device-{}", i), i);
+        }
+        assert_eq!(map.get("device-42"), Some(&42));
+        assert_eq!(map.remove("device-0"), Some(0));
+        assert_eq!(map.len(), 199);
+    }
+
+    #[test]
+    fn test_hasher_options() {
+        // старые пути к хеш-функциям
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(hashcode(b"device-1"), crate::hasher::hashcode(b"device-1"));
+        let mut adler: HashMap<String, u32, Adler32BuildHasher> = HashMap::default();
+        let mut hashcode = HashMap::with_capacity_and_hasher(16, HashcodeBuildHasher::default());
+        for i in 0..200 {
+            adler.insert(format!("device-{}", i), i);
+            hashcode.insert(format!("device-{}", i), i);
+        }
+        assert_eq!(adler.get("device-42"), Some(&42));
+        assert_eq!(hashcode.get("device-199"), Some(&199));
+        assert_eq!(hashcode.remove("device-0"), Some(0));
+        assert_eq!(hashcode.len(), 199);
+    }
+
+    /// Случайные вставки и удаления, сверка с `std::collections::HashMap`
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        // слабый хеш: много коллизий и длинные цепочки пробирования
+        let mut map = HashMap::with_hasher(HashcodeBuildHasher::default());
+        let mut model = std::collections::HashMap::new();
+        for _ in 0..20000 {
+            let key: u16 = rng.gen_range(0..500);
+            if rng.gen_bool(0.6) {
+                assert_eq!(map.insert(key, key), model.insert(key, key));
+            } else {
+                assert_eq!(map.remove(&key), model.remove(&key));
             }
+            assert_eq!(map.len(), model.len());
+        }
+        for key in 0..500 {
+            assert_eq!(map.get(&key), model.get(&key));
         }
     }
+
+    #[test]
+    fn test_location_cache_bounded() {
+        let location = |name: &str| LocationInformation {
+            name: name.to_string(),
+            opened: "2021".to_string(),
+            address: format!("{} street", name),
+            security_group_name: "staff".to_string(),
+        };
+        let mut cache = LocationCache::new(2);
+        cache.put("hq".to_string(), location("hq"));
+        cache.put("lab".to_string(), location("lab"));
+        cache.get("hq");
+        cache.put("depot".to_string(), location("depot"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.peek("lab"), None);
+        assert_eq!(cache.peek("hq"), Some(&location("hq")));
+    }
 }
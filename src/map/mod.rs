@@ -1 +1,3 @@
+pub mod hasher;
+pub mod lru_cache;
 pub mod map;
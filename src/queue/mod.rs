@@ -1,4 +1,11 @@
 pub mod deque_std;
+pub mod queue_async;
 pub mod queue_linked_list;
+pub mod queue_lock_free;
+pub mod queue_min_max;
+pub mod queue_persistent;
 pub mod queue_priority;
+pub mod queue_priority_indexed;
 pub mod queue_vec;
+pub mod ring_buffer;
+pub mod ring_deque;
@@ -0,0 +1,426 @@
+#![allow(dead_code)]
+
+//! Асинхронная ограниченная очередь FIFO в духе канала.
+//!
+//! Операции те же, что у `queue_vec::Queue` (`enqueue`, `dequeue`, `peek`, `length`, `is_empty`),
+//! но `enqueue().await` ждёт свободного места, а `dequeue().await` - появления элемента.
+//! Элементы хранятся в `ring_deque::RingDeque`, поэтому обе операции O(1).
+//!
+//! `AsyncQueue` - разделяемый дескриптор: клоны работают с одной очередью из разных задач и потоков.
+//! Реализует `Stream` (поток заканчивается после `close` и опустошения очереди) и `Sink`.
+//! Внешний runtime не нужен, достаточно `futures::executor`.
+pub use ds_queue_async::{AsyncQueue, Dequeue, Enqueue, QueueClosed, TryEnqueueError};
+mod ds_queue_async {
+    use crate::ring_deque::RingDeque;
+    use futures::{Sink, Stream};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::task::{Context, Poll, Waker};
+
+    /// Очередь закрыта методом `close`
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct QueueClosed;
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum TryEnqueueError<T> {
+        Full(T),
+        Closed(T),
+    }
+
+    struct State<T> {
+        queue: RingDeque<T>,
+        closed: bool,
+        // задачи, ждущие свободного места
+        producers: Vec<Waker>,
+        // задачи, ждущие элемента
+        consumers: Vec<Waker>,
+    }
+
+    struct Shared<T> {
+        state: Mutex<State<T>>,
+        capacity: usize,
+    }
+
+    fn register(wakers: &mut Vec<Waker>, waker: &Waker) {
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn wake_all(wakers: &mut Vec<Waker>) {
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    impl<T> Shared<T> {
+        fn lock(&self) -> MutexGuard<'_, State<T>> {
+            self.state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+
+        fn poll_enqueue(&self, item: T, cx: &mut Context<'_>) -> Result<(), TryEnqueueError<T>> {
+            let mut state = self.lock();
+            let result = Self::push(&mut state, self.capacity, item);
+            if let Err(TryEnqueueError::Full(_)) = result {
+                register(&mut state.producers, cx.waker());
+            }
+            result
+        }
+
+        fn push(state: &mut State<T>, capacity: usize, item: T) -> Result<(), TryEnqueueError<T>> {
+            if state.closed {
+                return Err(TryEnqueueError::Closed(item));
+            }
+            if state.queue.length() >= capacity {
+                return Err(TryEnqueueError::Full(item));
+            }
+            state.queue.enqueue(item);
+            wake_all(&mut state.consumers);
+            Ok(())
+        }
+
+        fn poll_dequeue(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            let mut state = self.lock();
+            match state.queue.dequeue() {
+                Some(item) => {
+                    wake_all(&mut state.producers);
+                    Poll::Ready(Some(item))
+                }
+                None if state.closed => Poll::Ready(None),
+                None => {
+                    register(&mut state.consumers, cx.waker());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    pub struct AsyncQueue<T> {
+        shared: Arc<Shared<T>>,
+        // элемент, принятый `Sink::start_send` и ещё не помещённый в очередь
+        pending: Option<T>,
+    }
+
+    // поля никогда не закрепляются (pin projection не используется)
+    impl<T> Unpin for AsyncQueue<T> {}
+
+    impl<T> Clone for AsyncQueue<T> {
+        fn clone(&self) -> Self {
+            Self {
+                shared: self.shared.clone(),
+                pending: None,
+            }
+        }
+    }
+
+    impl<T> AsyncQueue<T> {
+        pub fn new(capacity: usize) -> Self {
+            assert!(capacity > 0, "AsyncQueue capacity must be positive");
+            Self {
+                shared: Arc::new(Shared {
+                    state: Mutex::new(State {
+                        queue: RingDeque::with_capacity(capacity),
+                        closed: false,
+                        producers: vec![],
+                        consumers: vec![],
+                    }),
+                    capacity,
+                }),
+                pending: None,
+            }
+        }
+
+        /// Ждёт свободного места. Возвращает элемент обратно, если очередь закрыта
+        pub fn enqueue(&self, item: T) -> Enqueue<'_, T> {
+            Enqueue {
+                shared: &self.shared,
+                item: Some(item),
+            }
+        }
+
+        /// Ждёт элемента. `None` - очередь закрыта и пуста
+        pub fn dequeue(&self) -> Dequeue<'_, T> {
+            Dequeue {
+                shared: &self.shared,
+            }
+        }
+
+        pub fn try_enqueue(&self, item: T) -> Result<(), TryEnqueueError<T>> {
+            Shared::push(&mut self.shared.lock(), self.shared.capacity, item)
+        }
+
+        pub fn try_dequeue(&self) -> Option<T> {
+            let mut state = self.shared.lock();
+            let item = state.queue.dequeue();
+            if item.is_some() {
+                wake_all(&mut state.producers);
+            }
+            item
+        }
+
+        /// Копия первого элемента: ссылку отдать нельзя, очередь разделяется между задачами
+        pub fn peek(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.shared.lock().queue.peek().cloned()
+        }
+
+        pub fn length(&self) -> usize {
+            self.shared.lock().queue.length()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.length() == 0
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.shared.capacity
+        }
+
+        /// Запрещает новые элементы. Оставшиеся элементы по-прежнему можно извлечь
+        pub fn close(&self) {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            wake_all(&mut state.producers);
+            wake_all(&mut state.consumers);
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.shared.lock().closed
+        }
+
+        fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), QueueClosed>> {
+            let Some(item) = self.pending.take() else {
+                return Poll::Ready(Ok(()));
+            };
+            match self.shared.poll_enqueue(item, cx) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TryEnqueueError::Full(item)) => {
+                    self.pending = Some(item);
+                    Poll::Pending
+                }
+                Err(TryEnqueueError::Closed(_)) => Poll::Ready(Err(QueueClosed)),
+            }
+        }
+    }
+
+    pub struct Enqueue<'a, T> {
+        shared: &'a Shared<T>,
+        item: Option<T>,
+    }
+
+    impl<T> Unpin for Enqueue<'_, T> {}
+
+    impl<T> Future for Enqueue<'_, T> {
+        type Output = Result<(), T>;
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let item = self.item.take().expect("Enqueue polled after completion");
+            match self.shared.poll_enqueue(item, cx) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TryEnqueueError::Closed(item)) => Poll::Ready(Err(item)),
+                Err(TryEnqueueError::Full(item)) => {
+                    self.item = Some(item);
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    pub struct Dequeue<'a, T> {
+        shared: &'a Shared<T>,
+    }
+
+    impl<T> Future for Dequeue<'_, T> {
+        type Output = Option<T>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            self.shared.poll_dequeue(cx)
+        }
+    }
+
+    impl<T> Stream for AsyncQueue<T> {
+        type Item = T;
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.shared.poll_dequeue(cx)
+        }
+    }
+
+    /// Принятый `start_send` элемент попадает в очередь при следующем `poll_ready` или `poll_flush`
+    impl<T> Sink<T> for AsyncQueue<T> {
+        type Error = QueueClosed;
+
+        fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.get_mut().poll_pending(cx)
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+            let this = self.get_mut();
+            if this.shared.lock().closed {
+                return Err(QueueClosed);
+            }
+            assert!(
+                this.pending.is_none(),
+                "start_send called without poll_ready"
+            );
+            this.pending = Some(item);
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.get_mut().poll_pending(cx)
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            let this = self.get_mut();
+            // закрываем только после того, как принятый элемент попал в очередь
+            match this.poll_pending(cx) {
+                Poll::Ready(Ok(())) => {
+                    this.close();
+                    Poll::Ready(Ok(()))
+                }
+                Poll::Ready(Err(closed)) => Poll::Ready(Err(closed)),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+}
+
+/// $ cargo test queue_async
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::{block_on, LocalPool};
+    use futures::task::LocalSpawnExt;
+    use futures::{SinkExt, StreamExt};
+
+    #[test]
+    fn test_success() {
+        let queue: AsyncQueue<i32> = AsyncQueue::new(3);
+        block_on(async {
+            queue.enqueue(1).await.unwrap();
+            queue.enqueue(2).await.unwrap();
+            assert_eq!(queue.peek(), Some(1));
+            assert_eq!(queue.length(), 2);
+            assert_eq!(queue.dequeue().await, Some(1));
+            assert_eq!(queue.dequeue().await, Some(2));
+        });
+        assert!(queue.is_empty());
+        assert_eq!(queue.try_dequeue(), None);
+    }
+
+    #[test]
+    fn test_try_enqueue_and_close() {
+        let queue: AsyncQueue<i32> = AsyncQueue::new(1);
+        assert_eq!(queue.try_enqueue(1), Ok(()));
+        assert_eq!(queue.try_enqueue(2), Err(TryEnqueueError::Full(2)));
+
+        queue.close();
+        assert!(queue.is_closed());
+        assert_eq!(queue.try_enqueue(3), Err(TryEnqueueError::Closed(3)));
+        block_on(async {
+            assert_eq!(queue.enqueue(4).await, Err(4));
+            assert_eq!(queue.dequeue().await, Some(1));
+            assert_eq!(queue.dequeue().await, None);
+        });
+    }
+
+    #[test]
+    fn test_producer_waits_for_space() {
+        let queue: AsyncQueue<usize> = AsyncQueue::new(2);
+        let mut pool = LocalPool::new();
+        let spawner = pool.spawner();
+
+        let producer = queue.clone();
+        spawner
+            .spawn_local(async move {
+                for i in 0..100 {
+                    producer.enqueue(i).await.unwrap();
+                    assert!(producer.length() <= producer.capacity());
+                }
+                producer.close();
+            })
+            .unwrap();
+        let consumer = queue.clone();
+        let received = spawner
+            .spawn_local_with_handle(async move {
+                let mut received = vec![];
+                while let Some(item) = consumer.dequeue().await {
+                    received.push(item);
+                }
+                received
+            })
+            .unwrap();
+
+        assert_eq!(pool.run_until(received), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stream_and_sink() {
+        let queue: AsyncQueue<i32> = AsyncQueue::new(4);
+        let mut sink = queue.clone();
+        let stream = queue.clone();
+        let (sent, received) = block_on(futures::future::join(
+            async {
+                let sent = sink
+                    .send_all(&mut futures::stream::iter(0..50).map(Ok))
+                    .await;
+                (sent, SinkExt::close(&mut sink).await)
+            },
+            stream.collect::<Vec<_>>(),
+        ));
+        assert_eq!(sent, (Ok(()), Ok(())));
+        assert_eq!(received, (0..50).collect::<Vec<_>>());
+
+        let mut sink = queue;
+        assert_eq!(block_on(sink.send(1)), Err(QueueClosed));
+
+        // очередь закрыли, пока принятый элемент ждал места: close сообщает о потере
+        let queue: AsyncQueue<i32> = AsyncQueue::new(1);
+        let mut sink = queue.clone();
+        queue.try_enqueue(0).unwrap();
+        assert_eq!(block_on(sink.feed(1)), Ok(()));
+        queue.close();
+        assert_eq!(block_on(SinkExt::close(&mut sink)), Err(QueueClosed));
+    }
+
+    #[test]
+    fn test_threads() {
+        let queue: AsyncQueue<usize> = AsyncQueue::new(8);
+        let received: Vec<usize> = std::thread::scope(|scope| {
+            for producer in 0..4 {
+                let queue = queue.clone();
+                scope.spawn(move || {
+                    block_on(async {
+                        for i in 0..1000 {
+                            queue.enqueue(producer * 1000 + i).await.unwrap();
+                        }
+                    })
+                });
+            }
+            let consumers: Vec<_> = (0..2)
+                .map(|_| {
+                    let queue = queue.clone();
+                    scope.spawn(move || {
+                        block_on(async {
+                            let mut received = vec![];
+                            for _ in 0..2000 {
+                                received.push(queue.dequeue().await.unwrap());
+                            }
+                            received
+                        })
+                    })
+                })
+                .collect();
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        let mut received = received;
+        received.sort();
+        assert_eq!(received, (0..4000).collect::<Vec<_>>());
+    }
+}
@@ -1,103 +1,265 @@
-pub use ds_queue_rc::{Node, Queue};
-mod ds_queue_rc {
-    use std::fmt::Debug;
-    use std::rc::Rc;
+//! Очередь FIFO на односвязном списке, владеющем своими узлами.
+//!
+//! Хранит указатели на голову и хвост, поэтому `enqueue`, `dequeue`, `len` и `append` работают за O(1).
+//! Все связи - сырые указатели из `Box::into_raw`: смешивание `Box` с указателем на хвост
+//! нарушает правила заимствования (проверяется `miri`).
+pub use ds_queue_linked_list::{IntoIter, Iter, IterMut, Queue};
+mod ds_queue_linked_list {
+    use std::fmt::{self, Debug};
+    use std::marker::PhantomData;
+    use std::ptr;
 
-    #[derive(Debug)]
-    pub struct Node<T> {
+    type Link<T> = *mut Node<T>;
+
+    struct Node<T> {
         data: T,
-        next: Option<Rc<Node<T>>>,
+        next: Link<T>,
     }
 
-    impl<T> Node<T>
-    where
-        T: Copy + Debug,
-    {
-        pub fn new(data: T) -> Self {
-            Node { data, next: None }
-        }
-        pub fn set_next(&mut self, next: Option<Rc<Node<T>>>) -> bool {
-            if self.next.is_none() {
-                self.next = next;
-                return true;
-            } else {
-                if let Some(e) = &mut self.next {
-                    (*Rc::<Node<T>>::get_mut(e).unwrap()).set_next(next);
-                    return true;
-                }
-            }
-            return false;
+    pub struct Queue<T> {
+        head: Link<T>,
+        tail: Link<T>,
+        len: usize,
+        // очередь владеет значениями T
+        marker: PhantomData<Box<Node<T>>>,
+    }
+
+    // SAFETY: очередь единолично владеет узлами, как Box
+    unsafe impl<T: Send> Send for Queue<T> {}
+    unsafe impl<T: Sync> Sync for Queue<T> {}
+
+    impl<T> Default for Queue<T> {
+        fn default() -> Self {
+            Self::new()
         }
-        pub fn peek_all<'b>(&'b self, buf: &mut Vec<&'b T>) {
-            buf.push(&self.data);
-            if self.next.is_some() {
-                self.next.as_ref().unwrap().peek_all(buf);
+    }
+
+    impl<T> Queue<T> {
+        pub fn new() -> Self {
+            Queue {
+                head: ptr::null_mut(),
+                tail: ptr::null_mut(),
+                len: 0,
+                marker: PhantomData,
             }
         }
-    }
 
-    #[derive(Debug)]
-    pub struct Queue<T> {
-        head: Option<Rc<Node<T>>>,
-        count: usize,
-    }
-    impl<T> Queue<T>
-    where
-        T: Copy + Debug,
-    {
-        pub fn new(head: Option<Rc<Node<T>>>) -> Self {
-            Self { head, count: 1 }
-        }
-        pub fn enqueue(&mut self, next: Option<Rc<Node<T>>>) {
-            if self.head.is_some() {
-                if let Some(e) = Rc::<Node<T>>::get_mut(&mut self.head.as_mut().unwrap()) {
-                    e.set_next(next);
-                    self.count += 1;
-                }
+        pub fn enqueue(&mut self, data: T) {
+            let node = Box::into_raw(Box::new(Node {
+                data,
+                next: ptr::null_mut(),
+            }));
+            if self.tail.is_null() {
+                self.head = node;
+            } else {
+                // SAFETY: tail указывает на последний узел, которым владеет очередь
+                unsafe { (*self.tail).next = node };
             }
+            self.tail = node;
+            self.len += 1;
         }
+
         pub fn dequeue(&mut self) -> Option<T> {
-            if self.head.is_some() {
-                let head = self.head.as_mut().unwrap();
-                let data = head.data.clone();
-                if let Some(e) = &head.next {
-                    self.head = Some(Rc::clone(&e));
-                    self.count -= 1;
-                } else {
-                    self.head = None;
-                    self.count = 0;
-                }
-                return Some(data);
+            if self.head.is_null() {
+                return None;
             }
-            return None;
+            // SAFETY: head получен из Box::into_raw и снимается из очереди один раз
+            let head = unsafe { Box::from_raw(self.head) };
+            self.head = head.next;
+            if self.head.is_null() {
+                self.tail = ptr::null_mut();
+            }
+            self.len -= 1;
+            Some(head.data)
         }
+
         pub fn peek(&self) -> Option<&T> {
-            if self.head.is_some() {
-                return Some(&self.head.as_ref().unwrap().data);
-            }
-            return None;
+            // SAFETY: узлы живут, пока живёт очередь
+            unsafe { self.head.as_ref() }.map(|node| &node.data)
+        }
+
+        pub fn peek_mut(&mut self) -> Option<&mut T> {
+            // SAFETY: &mut self даёт единственный доступ к узлам
+            unsafe { self.head.as_mut() }.map(|node| &mut node.data)
         }
+
         pub fn peek_all<'b>(&'b self, buf: &mut Vec<&'b T>) {
-            if self.head.is_some() {
-                self.head.as_ref().unwrap().peek_all(buf);
-            }
+            buf.extend(self.iter());
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
         }
+
+        /// То же, что `len`, имя совпадает с остальными очередями крейта
         pub fn length(&self) -> usize {
-            self.count
+            self.len
         }
+
         pub fn is_empty(&self) -> bool {
-            self.count == 0
+            self.len == 0
+        }
+
+        /// Переносит все элементы `other` в конец очереди за O(1), `other` становится пустой
+        pub fn append(&mut self, other: &mut Queue<T>) {
+            if other.head.is_null() {
+                return;
+            }
+            if self.tail.is_null() {
+                self.head = other.head;
+            } else {
+                // SAFETY: tail указывает на последний узел, которым владеет очередь
+                unsafe { (*self.tail).next = other.head };
+            }
+            self.tail = other.tail;
+            self.len += other.len;
+            other.head = ptr::null_mut();
+            other.tail = ptr::null_mut();
+            other.len = 0;
+        }
+
+        pub fn clear(&mut self) {
+            while self.dequeue().is_some() {}
+        }
+
+        pub fn iter(&self) -> Iter<'_, T> {
+            Iter {
+                next: self.head,
+                len: self.len,
+                marker: PhantomData,
+            }
+        }
+
+        pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+            IterMut {
+                next: self.head,
+                len: self.len,
+                marker: PhantomData,
+            }
+        }
+    }
+
+    impl<T> Drop for Queue<T> {
+        fn drop(&mut self) {
+            self.clear();
+        }
+    }
+
+    impl<T: Debug> Debug for Queue<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+
+    impl<T: Clone> Clone for Queue<T> {
+        fn clone(&self) -> Self {
+            self.iter().cloned().collect()
+        }
+    }
+
+    impl<T: PartialEq> PartialEq for Queue<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.len == other.len && self.iter().eq(other.iter())
         }
     }
 
-    impl<T> Iterator for Queue<T>
-    where
-        T: Copy + Debug,
-    {
+    impl<T> Extend<T> for Queue<T> {
+        fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            for data in iter {
+                self.enqueue(data);
+            }
+        }
+    }
+
+    impl<T> FromIterator<T> for Queue<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let mut queue = Queue::new();
+            queue.extend(iter);
+            queue
+        }
+    }
+
+    pub struct IntoIter<T>(Queue<T>);
+
+    impl<T> Iterator for IntoIter<T> {
+        type Item = T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.dequeue()
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.0.len, Some(self.0.len))
+        }
+    }
+
+    impl<T> ExactSizeIterator for IntoIter<T> {}
+
+    impl<T> IntoIterator for Queue<T> {
         type Item = T;
+        type IntoIter = IntoIter<T>;
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self)
+        }
+    }
 
-        fn next(&mut self) -> Option<T> {
-            self.dequeue()
+    pub struct Iter<'a, T> {
+        next: Link<T>,
+        len: usize,
+        marker: PhantomData<&'a T>,
+    }
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<Self::Item> {
+            // SAFETY: узлы живут, пока очередь заимствована итератором
+            unsafe { self.next.as_ref() }.map(|node| {
+                self.next = node.next;
+                self.len -= 1;
+                &node.data
+            })
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.len, Some(self.len))
+        }
+    }
+
+    impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+    impl<'a, T> IntoIterator for &'a Queue<T> {
+        type Item = &'a T;
+        type IntoIter = Iter<'a, T>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    pub struct IterMut<'a, T> {
+        next: Link<T>,
+        len: usize,
+        marker: PhantomData<&'a mut T>,
+    }
+
+    impl<'a, T> Iterator for IterMut<'a, T> {
+        type Item = &'a mut T;
+        fn next(&mut self) -> Option<Self::Item> {
+            // SAFETY: очередь заимствована мутабельно, каждый узел выдаётся один раз
+            unsafe { self.next.as_mut() }.map(|node| {
+                self.next = node.next;
+                self.len -= 1;
+                &mut node.data
+            })
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.len, Some(self.len))
+        }
+    }
+
+    impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+    impl<'a, T> IntoIterator for &'a mut Queue<T> {
+        type Item = &'a mut T;
+        type IntoIter = IterMut<'a, T>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
         }
     }
 }
@@ -107,20 +269,15 @@ mod ds_queue_rc {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::rc::Rc;
 
     #[test]
     fn test_success() {
-        let node1 = Node::<i32>::new(1);
-        let node2 = Node::<i32>::new(2);
-        let node3 = Node::<i32>::new(3);
-        let node4 = Node::<i32>::new(4);
-        let node5 = Node::<i32>::new(5);
-        let mut queue = Queue::new(Some(Rc::new(node1)));
-        queue.enqueue(Some(Rc::new(node2)));
-        queue.enqueue(Some(Rc::new(node3)));
-        queue.enqueue(Some(Rc::new(node4)));
-        queue.enqueue(Some(Rc::new(node5)));
+        let mut queue: Queue<i32> = Queue::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
+        queue.enqueue(3);
+        queue.enqueue(4);
+        queue.enqueue(5);
 
         assert_eq!(queue.dequeue(), Some(1));
         assert_eq!(queue.dequeue(), Some(2));
@@ -130,15 +287,17 @@ mod tests {
         assert_eq!(queue.peek(), Some(&5));
         assert_eq!(queue.dequeue(), Some(5));
         assert_eq!(queue.peek(), None);
+
+        // хвост сброшен, очередь снова пригодна
+        queue.enqueue(6);
+        assert_eq!(queue.dequeue(), Some(6));
     }
 
     #[test]
     fn test_enqueue() {
-        let node1 = Node::<i32>::new(1);
-        let mut queue = Queue::new(Some(Rc::new(node1)));
-
-        let node2 = Node::<i32>::new(2);
-        queue.enqueue(Some(Rc::new(node2)));
+        let mut queue = Queue::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
 
         assert_eq!(queue.peek(), Some(&1));
         assert_eq!(queue.length(), 2);
@@ -147,11 +306,9 @@ mod tests {
 
     #[test]
     fn test_dequeue() {
-        let node1 = Node::<i32>::new(1);
-        let mut queue = Queue::new(Some(Rc::new(node1)));
-
-        let node2 = Node::<i32>::new(2);
-        queue.enqueue(Some(Rc::new(node2)));
+        let mut queue = Queue::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
 
         let item = queue.dequeue();
         assert_eq!(item, Some(1));
@@ -168,16 +325,60 @@ mod tests {
 
     #[test]
     fn test_peek_all() {
-        let node1 = Node::<i32>::new(1);
-        let node2 = Node::<i32>::new(2);
-        let node3 = Node::<i32>::new(3);
-
-        let mut q = Queue::new(Some(Rc::new(node1)));
-        q.enqueue(Some(Rc::new(node2)));
-        q.enqueue(Some(Rc::new(node3)));
+        let q: Queue<i32> = (1..=3).collect();
 
         let mut buf: Vec<&i32> = vec![];
         let _ = &q.peek_all(&mut buf);
         assert_eq!(buf, vec![&1, &2, &3]);
     }
+
+    #[test]
+    fn test_iter() {
+        let mut queue: Queue<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        for item in queue.iter_mut() {
+            item.push('!');
+        }
+        if let Some(item) = queue.peek_mut() {
+            item.insert(0, '>');
+        }
+        assert_eq!(queue.iter().len(), 3);
+        assert_eq!(
+            queue.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec![">a!", "b!", "c!"]
+        );
+        assert_eq!(
+            queue.into_iter().collect::<Vec<_>>(),
+            vec![">a!".to_string(), "b!".to_string(), "c!".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let mut first: Queue<i32> = (1..=3).collect();
+        let mut second: Queue<i32> = (4..=5).collect();
+        first.append(&mut second);
+        assert!(second.is_empty());
+        assert_eq!(first.len(), 5);
+
+        first.enqueue(6);
+        second.append(&mut first);
+        assert!(first.is_empty());
+        assert_eq!(
+            second.iter().copied().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+
+        second.enqueue(7);
+        assert_eq!(second.len(), 7);
+        assert_eq!(second.dequeue(), Some(1));
+    }
+
+    #[test]
+    fn test_drop_long() {
+        let mut queue = Queue::new();
+        for i in 0..200_000 {
+            queue.enqueue(i);
+        }
+        drop(queue);
+    }
 }
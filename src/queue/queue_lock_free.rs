@@ -0,0 +1,564 @@
+#![allow(dead_code)]
+
+//! Lock-free очереди для нескольких производителей и потребителей (MPMC).
+//!
+//! `ArrayQueue` - ограниченная очередь Вьюкова на массиве: каждая ячейка хранит номер хода,
+//! по которому производители и потребители узнают, свободна ли ячейка, без блокировок.
+//! `LinkedQueue` - неограниченная очередь Майкла-Скотта на односвязном списке с фиктивным узлом,
+//! удалённые узлы освобождаются через указатели опасности (`hazard_pointer`).
+//!
+//! `try_*` методы не ждут. `enqueue`/`dequeue` ждут места или данных: сначала опрашивают очередь
+//! с нарастающей паузой, затем засыпают на `Condvar` до успешной операции встречной стороны.
+pub use ds_queue_lock_free::{ArrayQueue, LinkedQueue};
+mod ds_queue_lock_free {
+    use crate::hazard_pointer::{retire, HazardPointer};
+    use std::cell::UnsafeCell;
+    use std::hint;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+    use std::sync::atomic::{self, AtomicPtr, AtomicUsize, Ordering};
+    use std::sync::{Condvar, Mutex};
+    use std::thread;
+
+    /// Отдельная кэш-линия, чтобы счётчики производителей и потребителей не мешали друг другу
+    #[repr(align(64))]
+    struct CachePadded<T>(T);
+
+    /// Ожидание: сначала короткие циклы ожидания, затем уступаем процессор,
+    /// после `YIELD_LIMIT` попыток пора засыпать
+    struct Backoff(u32);
+
+    impl Backoff {
+        const SPIN_LIMIT: u32 = 6;
+        const YIELD_LIMIT: u32 = 10;
+
+        fn snooze(&mut self) {
+            if self.0 <= Self::SPIN_LIMIT {
+                for _ in 0..1 << self.0 {
+                    hint::spin_loop();
+                }
+            } else {
+                thread::yield_now();
+            }
+            if self.0 <= Self::YIELD_LIMIT {
+                self.0 += 1;
+            }
+        }
+
+        fn is_completed(&self) -> bool {
+            self.0 > Self::YIELD_LIMIT
+        }
+    }
+
+    /// Спящие в ожидании потоки одной стороны очереди
+    struct Waiters {
+        sleeping: AtomicUsize,
+        lock: Mutex<()>,
+        condvar: Condvar,
+    }
+
+    impl Waiters {
+        fn new() -> Self {
+            Self {
+                sleeping: AtomicUsize::new(0),
+                lock: Mutex::new(()),
+                condvar: Condvar::new(),
+            }
+        }
+
+        /// Засыпает, если `ready` всё ещё ложно. Счётчик спящих увеличивается до проверки, а `notify`
+        /// читает его после своей операции, поэтому либо проверка увидит операцию, либо `notify` - спящего.
+        /// Просыпание не гарантирует успеха, вызывающий повторяет попытку
+        fn park(&self, ready: impl FnOnce() -> bool) {
+            let guard = self.lock.lock().unwrap();
+            self.sleeping.fetch_add(1, Ordering::SeqCst);
+            atomic::fence(Ordering::SeqCst);
+            if !ready() {
+                drop(self.condvar.wait(guard).unwrap());
+            } else {
+                drop(guard);
+            }
+            self.sleeping.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        /// Будит спящих; без них стоит один барьер и одно чтение
+        fn notify(&self) {
+            atomic::fence(Ordering::SeqCst);
+            if self.sleeping.load(Ordering::Relaxed) > 0 {
+                // под мьютексом: спящий уже ждёт на condvar или ещё не сделал проверку
+                let _guard = self.lock.lock().unwrap();
+                self.condvar.notify_all();
+            }
+        }
+    }
+
+    struct Slot<T> {
+        // 2p - ячейка свободна для хода p, 2p + 1 - заполнена на ходе p.
+        // Множитель 2 различает эти состояния и при ёмкости 1, где p + 1 совпало бы с p + ёмкость
+        sequence: AtomicUsize,
+        data: UnsafeCell<MaybeUninit<T>>,
+    }
+
+    pub struct ArrayQueue<T> {
+        buffer: Box<[Slot<T>]>,
+        enqueue_pos: CachePadded<AtomicUsize>,
+        dequeue_pos: CachePadded<AtomicUsize>,
+        // ждущие места производители и ждущие данных потребители
+        not_full: Waiters,
+        not_empty: Waiters,
+    }
+
+    // SAFETY: доступ к данным ячейки получает только поток, выигравший CAS за её позицию
+    unsafe impl<T: Send> Send for ArrayQueue<T> {}
+    unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
+    impl<T> ArrayQueue<T> {
+        pub fn new(capacity: usize) -> Self {
+            assert!(capacity > 0, "ArrayQueue capacity must be positive");
+            let buffer = (0..capacity)
+                .map(|i| Slot {
+                    sequence: AtomicUsize::new(i.wrapping_mul(2)),
+                    data: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect();
+            Self {
+                buffer,
+                enqueue_pos: CachePadded(AtomicUsize::new(0)),
+                dequeue_pos: CachePadded(AtomicUsize::new(0)),
+                not_full: Waiters::new(),
+                not_empty: Waiters::new(),
+            }
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.buffer.len()
+        }
+
+        /// Приблизительная длина: при параллельной работе может устареть сразу после вызова
+        pub fn len(&self) -> usize {
+            let dequeue_pos = self.dequeue_pos.0.load(Ordering::SeqCst);
+            let enqueue_pos = self.enqueue_pos.0.load(Ordering::SeqCst);
+            enqueue_pos.saturating_sub(dequeue_pos).min(self.capacity())
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Возвращает элемент обратно, если очередь заполнена
+        pub fn try_enqueue(&self, item: T) -> Result<(), T> {
+            let mut pos = self.enqueue_pos.0.load(Ordering::Relaxed);
+            loop {
+                let slot = &self.buffer[pos % self.capacity()];
+                let sequence = slot.sequence.load(Ordering::Acquire);
+                let diff = sequence.wrapping_sub(pos.wrapping_mul(2)) as isize;
+                if diff == 0 {
+                    match self.enqueue_pos.0.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: ячейка свободна и принадлежит только этому потоку
+                            unsafe { (*slot.data.get()).write(item) };
+                            slot.sequence
+                                .store(pos.wrapping_mul(2).wrapping_add(1), Ordering::Release);
+                            self.not_empty.notify();
+                            return Ok(());
+                        }
+                        Err(actual) => pos = actual,
+                    }
+                } else if diff < 0 {
+                    return Err(item);
+                } else {
+                    pos = self.enqueue_pos.0.load(Ordering::Relaxed);
+                }
+            }
+        }
+
+        pub fn try_dequeue(&self) -> Option<T> {
+            let mut pos = self.dequeue_pos.0.load(Ordering::Relaxed);
+            loop {
+                let slot = &self.buffer[pos % self.capacity()];
+                let sequence = slot.sequence.load(Ordering::Acquire);
+                let diff = sequence.wrapping_sub(pos.wrapping_mul(2).wrapping_add(1)) as isize;
+                if diff == 0 {
+                    match self.dequeue_pos.0.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: ячейка заполнена и принадлежит только этому потоку
+                            let item = unsafe { (*slot.data.get()).assume_init_read() };
+                            let next = pos.wrapping_add(self.capacity()).wrapping_mul(2);
+                            slot.sequence.store(next, Ordering::Release);
+                            self.not_full.notify();
+                            return Some(item);
+                        }
+                        Err(actual) => pos = actual,
+                    }
+                } else if diff < 0 {
+                    return None;
+                } else {
+                    pos = self.dequeue_pos.0.load(Ordering::Relaxed);
+                }
+            }
+        }
+
+        /// Ждёт свободного места, долгое ожидание - во сне
+        pub fn enqueue(&self, mut item: T) {
+            let mut backoff = Backoff(0);
+            loop {
+                match self.try_enqueue(item) {
+                    Ok(()) => return,
+                    Err(rejected) => item = rejected,
+                }
+                if backoff.is_completed() {
+                    self.not_full.park(|| self.len() < self.capacity());
+                } else {
+                    backoff.snooze();
+                }
+            }
+        }
+
+        /// Ждёт появления элемента, долгое ожидание - во сне
+        pub fn dequeue(&self) -> T {
+            let mut backoff = Backoff(0);
+            loop {
+                if let Some(item) = self.try_dequeue() {
+                    return item;
+                }
+                if backoff.is_completed() {
+                    self.not_empty.park(|| !self.is_empty());
+                } else {
+                    backoff.snooze();
+                }
+            }
+        }
+    }
+
+    impl<T> Drop for ArrayQueue<T> {
+        fn drop(&mut self) {
+            while self.try_dequeue().is_some() {}
+        }
+    }
+
+    struct Node<T> {
+        data: MaybeUninit<T>,
+        next: AtomicPtr<Node<T>>,
+    }
+
+    impl<T> Node<T> {
+        fn new(data: MaybeUninit<T>) -> *mut Node<T> {
+            Box::into_raw(Box::new(Node {
+                data,
+                next: AtomicPtr::new(ptr::null_mut()),
+            }))
+        }
+    }
+
+    /// `head` всегда указывает на фиктивный узел, первый элемент лежит в `head.next`
+    pub struct LinkedQueue<T: Send> {
+        head: CachePadded<AtomicPtr<Node<T>>>,
+        tail: CachePadded<AtomicPtr<Node<T>>>,
+        not_empty: Waiters,
+    }
+
+    // SAFETY: узлы передаются между потоками только вместе с владением T: Send
+    unsafe impl<T: Send> Send for LinkedQueue<T> {}
+    unsafe impl<T: Send> Sync for LinkedQueue<T> {}
+
+    impl<T: Send> Default for LinkedQueue<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Send> LinkedQueue<T> {
+        pub fn new() -> Self {
+            let dummy = Node::new(MaybeUninit::uninit());
+            Self {
+                head: CachePadded(AtomicPtr::new(dummy)),
+                tail: CachePadded(AtomicPtr::new(dummy)),
+                not_empty: Waiters::new(),
+            }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            let hazard = HazardPointer::new();
+            let head = hazard.protect(&self.head.0);
+            // SAFETY: head защищён указателем опасности
+            unsafe { (*head).next.load(Ordering::Acquire).is_null() }
+        }
+
+        /// Никогда не ждёт: очередь неограниченна
+        pub fn enqueue(&self, item: T) {
+            let node = Node::new(MaybeUninit::new(item));
+            let hazard = HazardPointer::new();
+            loop {
+                let tail = hazard.protect(&self.tail.0);
+                // SAFETY: tail защищён указателем опасности
+                let next = unsafe { (*tail).next.load(Ordering::Acquire) };
+                if tail != self.tail.0.load(Ordering::Acquire) {
+                    continue;
+                }
+                if !next.is_null() {
+                    // хвост отстал, помогаем его сдвинуть
+                    let _ = self.tail.0.compare_exchange(
+                        tail,
+                        next,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    );
+                    continue;
+                }
+                // SAFETY: tail защищён указателем опасности
+                let linked = unsafe {
+                    (*tail).next.compare_exchange(
+                        ptr::null_mut(),
+                        node,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    )
+                };
+                if linked.is_ok() {
+                    let _ = self.tail.0.compare_exchange(
+                        tail,
+                        node,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    );
+                    self.not_empty.notify();
+                    return;
+                }
+            }
+        }
+
+        pub fn try_dequeue(&self) -> Option<T> {
+            let hazard_head = HazardPointer::new();
+            let hazard_next = HazardPointer::new();
+            loop {
+                let head = hazard_head.protect(&self.head.0);
+                // SAFETY: head защищён указателем опасности
+                let next = unsafe { (*head).next.load(Ordering::Acquire) };
+                hazard_next.set(next);
+                if head != self.head.0.load(Ordering::SeqCst) {
+                    continue;
+                }
+                if next.is_null() {
+                    return None;
+                }
+                let tail = self.tail.0.load(Ordering::Acquire);
+                if head == tail {
+                    let _ = self.tail.0.compare_exchange(
+                        tail,
+                        next,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    );
+                    continue;
+                }
+                if self
+                    .head
+                    .0
+                    .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    // SAFETY: next защищён, его данные забирает только поток, выигравший CAS;
+                    // next становится новым фиктивным узлом
+                    let item = unsafe { (*next).data.assume_init_read() };
+                    hazard_head.reset();
+                    hazard_next.reset();
+                    // SAFETY: старый фиктивный узел больше недостижим из очереди
+                    unsafe { retire(head) };
+                    return Some(item);
+                }
+            }
+        }
+
+        /// Ждёт появления элемента, долгое ожидание - во сне
+        pub fn dequeue(&self) -> T {
+            let mut backoff = Backoff(0);
+            loop {
+                if let Some(item) = self.try_dequeue() {
+                    return item;
+                }
+                if backoff.is_completed() {
+                    self.not_empty.park(|| !self.is_empty());
+                } else {
+                    backoff.snooze();
+                }
+            }
+        }
+    }
+
+    impl<T: Send> Drop for LinkedQueue<T> {
+        fn drop(&mut self) {
+            while self.try_dequeue().is_some() {}
+            let dummy = self.head.0.load(Ordering::Relaxed);
+            // SAFETY: &mut self - других потоков нет, у фиктивного узла данных нет
+            unsafe { drop(Box::from_raw(dummy)) };
+        }
+    }
+}
+
+/// $ cargo test queue_lock_free
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Нагрузочный тест: производители кладут уникальные значения, потребители забирают их.
+    /// Проверяет, что ни одно значение не потеряно и не получено дважды,
+    /// а значения одного производителя приходят каждому потребителю в порядке добавления.
+    fn stress<E, D>(producers: usize, consumers: usize, per_producer: usize, enqueue: E, dequeue: D)
+    where
+        E: Fn(usize) + Sync,
+        D: Fn() -> Option<usize> + Sync,
+    {
+        let total = producers * per_producer;
+        let consumed = AtomicUsize::new(0);
+        let received: Vec<Vec<usize>> = std::thread::scope(|scope| {
+            for producer in 0..producers {
+                let enqueue = &enqueue;
+                scope.spawn(move || {
+                    for i in 0..per_producer {
+                        enqueue(producer * per_producer + i);
+                    }
+                });
+            }
+            let handles: Vec<_> = (0..consumers)
+                .map(|_| {
+                    let (dequeue, consumed) = (&dequeue, &consumed);
+                    scope.spawn(move || {
+                        let mut received = vec![];
+                        while consumed.load(Ordering::SeqCst) < total {
+                            match dequeue() {
+                                Some(value) => {
+                                    consumed.fetch_add(1, Ordering::SeqCst);
+                                    received.push(value);
+                                }
+                                None => std::thread::yield_now(),
+                            }
+                        }
+                        received
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        for values in received.iter() {
+            let mut last = vec![None; producers];
+            for value in values {
+                let producer = value / per_producer;
+                assert!(last[producer] < Some(*value), "FIFO order is broken");
+                last[producer] = Some(*value);
+            }
+        }
+        let mut all: Vec<usize> = received.into_iter().flatten().collect();
+        all.sort();
+        assert_eq!(all, (0..total).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_array_queue_success() {
+        let queue: ArrayQueue<i32> = ArrayQueue::new(3);
+        assert_eq!(queue.try_dequeue(), None);
+        assert_eq!(queue.try_enqueue(1), Ok(()));
+        assert_eq!(queue.try_enqueue(2), Ok(()));
+        assert_eq!(queue.try_enqueue(3), Ok(()));
+        assert_eq!(queue.try_enqueue(4), Err(4));
+        assert_eq!(queue.len(), 3);
+
+        assert_eq!(queue.dequeue(), 1);
+        queue.enqueue(4);
+        assert_eq!(queue.try_dequeue(), Some(2));
+        assert_eq!(queue.try_dequeue(), Some(3));
+        assert_eq!(queue.try_dequeue(), Some(4));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_linked_queue_success() {
+        let queue: LinkedQueue<String> = LinkedQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.try_dequeue(), None);
+        for i in 0..100 {
+            queue.enqueue(i.to_string());
+        }
+        for i in 0..100 {
+            assert_eq!(queue.dequeue(), i.to_string());
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_drop_remaining() {
+        let counter = Arc::new(());
+        {
+            let array: ArrayQueue<Arc<()>> = ArrayQueue::new(8);
+            let linked: LinkedQueue<Arc<()>> = LinkedQueue::new();
+            for _ in 0..5 {
+                array.enqueue(counter.clone());
+                linked.enqueue(counter.clone());
+            }
+            drop(array.dequeue());
+            drop(linked.dequeue());
+            assert_eq!(Arc::strong_count(&counter), 9);
+        }
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn test_array_queue_stress() {
+        let queue: ArrayQueue<usize> = ArrayQueue::new(8);
+        stress(4, 4, 20_000, |v| queue.enqueue(v), || queue.try_dequeue());
+    }
+
+    #[test]
+    fn test_array_queue_blocking_stress() {
+        let queue: ArrayQueue<usize> = ArrayQueue::new(2);
+        // блокирующий dequeue вызывается только за заранее занятым элементом, иначе потребитель зависнет
+        let claimed = AtomicUsize::new(0);
+        stress(
+            3,
+            2,
+            10_000,
+            |v| queue.enqueue(v),
+            || (claimed.fetch_add(1, Ordering::SeqCst) < 30_000).then(|| queue.dequeue()),
+        );
+    }
+
+    /// Ожидающие потоки засыпают и просыпаются после операции встречной стороны
+    #[test]
+    fn test_blocking_wakeup() {
+        use std::time::Duration;
+        let array: ArrayQueue<usize> = ArrayQueue::new(1);
+        let linked: LinkedQueue<usize> = LinkedQueue::new();
+        std::thread::scope(|scope| {
+            let consumer = scope.spawn(|| (array.dequeue(), linked.dequeue()));
+            std::thread::sleep(Duration::from_millis(50));
+            array.enqueue(1);
+            linked.enqueue(2);
+            assert_eq!(consumer.join().unwrap(), (1, 2));
+
+            array.enqueue(3);
+            let producer = scope.spawn(|| array.enqueue(4));
+            std::thread::sleep(Duration::from_millis(50));
+            assert_eq!(array.dequeue(), 3);
+            producer.join().unwrap();
+            assert_eq!(array.dequeue(), 4);
+        });
+    }
+
+    #[test]
+    fn test_linked_queue_stress() {
+        let queue: LinkedQueue<usize> = LinkedQueue::new();
+        stress(4, 4, 20_000, |v| queue.enqueue(v), || queue.try_dequeue());
+    }
+}
@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+
+//! Очередь с минимумом и максимумом за O(1) из двух `stack_min_max::MinMaxStack`.
+//!
+//! `enqueue` кладёт элемент во входной стек. `dequeue` берёт из выходного стека,
+//! а если он пуст - сначала перекладывает в него весь входной, поэтому амортизированно O(1).
+//! Минимум и максимум очереди - лучшие из значений двух стеков.
+//! Подходит для скользящего окна: `enqueue` нового значения и `dequeue` самого старого.
+pub use ds_queue_min_max::MinMaxQueue;
+mod ds_queue_min_max {
+    use crate::stack_min_max::MinMaxStack;
+
+    #[derive(Debug)]
+    pub struct MinMaxQueue<T: Ord> {
+        // вершина - самый новый элемент
+        input: MinMaxStack<T>,
+        // вершина - самый старый элемент
+        output: MinMaxStack<T>,
+    }
+
+    impl<T: Ord> Default for MinMaxQueue<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Ord> MinMaxQueue<T> {
+        pub fn new() -> Self {
+            Self {
+                input: MinMaxStack::new(),
+                output: MinMaxStack::new(),
+            }
+        }
+
+        pub fn enqueue(&mut self, item: T) {
+            self.input.push(item);
+        }
+
+        pub fn dequeue(&mut self) -> Option<T> {
+            if self.output.is_empty() {
+                while let Some(item) = self.input.pop() {
+                    self.output.push(item);
+                }
+            }
+            self.output.pop()
+        }
+
+        /// Самый старый элемент: вершина выходного стека или дно входного
+        pub fn peek(&self) -> Option<&T> {
+            self.output.peek().or_else(|| self.input.get(0))
+        }
+
+        pub fn min(&self) -> Option<&T> {
+            match (self.input.min(), self.output.min()) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        pub fn max(&self) -> Option<&T> {
+            match (self.input.max(), self.output.max()) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        pub fn length(&self) -> usize {
+            self.input.len() + self.output.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.input.is_empty() && self.output.is_empty()
+        }
+    }
+}
+
+/// $ cargo test queue_min_max
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success() {
+        let mut queue = MinMaxQueue::new();
+        assert_eq!(queue.peek(), None);
+        assert_eq!(queue.min(), None);
+
+        queue.enqueue(4);
+        queue.enqueue(2);
+        queue.enqueue(8);
+        assert_eq!(queue.peek(), Some(&4));
+        assert_eq!((queue.min(), queue.max()), (Some(&2), Some(&8)));
+
+        assert_eq!(queue.dequeue(), Some(4));
+        queue.enqueue(1);
+        assert_eq!(queue.peek(), Some(&2));
+        assert_eq!((queue.min(), queue.max()), (Some(&1), Some(&8)));
+
+        assert_eq!(queue.dequeue(), Some(2));
+        assert_eq!(queue.dequeue(), Some(8));
+        assert_eq!((queue.min(), queue.max()), (Some(&1), Some(&1)));
+        assert_eq!(queue.length(), 1);
+        assert_eq!(queue.dequeue(), Some(1));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut queue = MinMaxQueue::new();
+        let mut values = vec![];
+        for _ in 0..1000 {
+            let value = rng.gen_range(0..50);
+            values.push(value);
+            queue.enqueue(value);
+            if queue.length() > 16 {
+                queue.dequeue();
+            }
+            let window = &values[values.len().saturating_sub(16)..];
+            assert_eq!(queue.peek(), window.first());
+            assert_eq!(queue.min(), window.iter().min());
+            assert_eq!(queue.max(), window.iter().max());
+        }
+    }
+}
@@ -0,0 +1,918 @@
+#![allow(dead_code)]
+
+//! Неизменяемые (persistent) очередь на основе `stack_linked_list_persistent::List` и дек на ленивых списках.
+//!
+//! Каждая операция возвращает новую версию и не меняет старую, версии разделяют общие узлы.
+//! Старые версии остаются корректными, поэтому их можно хранить как снимки состояния.
+//!
+//! `PersistentQueue` - очередь физика (Окасаки): элементы берутся из `front`, добавляются в `rear`.
+//! При `|rear| > |front|` создаётся отложенный список `front ++ reverse(rear)`, а вычисляется он,
+//! только когда кончится уже вычисленное начало `front`. Результат запоминается и общий для всех версий,
+//! которые его разделяют, поэтому `snoc`/`uncons` - амортизированное O(1) и при многократном
+//! продолжении одной и той же версии.
+//!
+//! `PersistentDeque` - дек реального времени (Окасаки): концы хранятся в ленивых списках, длины которых
+//! не отличаются больше чем в `C` раз. При нарушении элементы делятся пополам, но перенос откладывается
+//! и идёт по шагам: каждая операция вычисляет одну-две ячейки каждого конца по расписанию.
+//! Ячейки запоминаются и общие для версий, поэтому `push_*`/`pop_*` - O(1) в худшем случае
+//! для любой версии, сколько бы веток от неё ни продолжали.
+//!
+//! Перестроение копирует элементы, поэтому `T: Clone`; для тяжёлых значений храните `Rc<T>`.
+pub use ds_queue_persistent::{DequeIter, Iter, PersistentDeque, PersistentQueue};
+mod ds_queue_persistent {
+    use crate::stack_linked_list_persistent::{Iter as ListIter, List};
+    use std::cell::{Cell, OnceCell};
+    use std::fmt::{self, Debug};
+    use std::rc::Rc;
+
+    /// `front ++ reverse(rear)`
+    fn append_reversed<T: Clone>(front: &List<T>, rear: &List<T>) -> List<T> {
+        let reversed = rear
+            .iter()
+            .fold(List::new(), |list, item| list.prepend(item.clone()));
+        let front_items: Vec<&T> = front.iter().collect();
+        front_items
+            .into_iter()
+            .rev()
+            .fold(reversed, |list, item| list.prepend(item.clone()))
+    }
+
+    /// Элементы от начала к концу: `front` от головы, затем `rear` от хвоста
+    pub struct Iter<'a, T> {
+        front: ListIter<'a, T>,
+        rear: &'a List<T>,
+        // список связан от последнего элемента, поэтому, дойдя до `rear`, итератор
+        // один раз собирает ссылки на его элементы: O(|rear|) дополнительной памяти
+        reversed: Option<std::iter::Rev<std::vec::IntoIter<&'a T>>>,
+    }
+
+    impl<'a, T> Iter<'a, T> {
+        fn new(front: &'a List<T>, rear: &'a List<T>) -> Self {
+            Iter {
+                front: front.iter(),
+                rear,
+                reversed: None,
+            }
+        }
+    }
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<Self::Item> {
+            if let Some(item) = self.front.next() {
+                return Some(item);
+            }
+            let rear = self.rear;
+            self.reversed
+                .get_or_insert_with(|| rear.iter().collect::<Vec<_>>().into_iter().rev())
+                .next()
+        }
+    }
+
+    enum Pending<T> {
+        /// `front ++ reverse(rear)`
+        Rotate(List<T>, List<T>),
+        /// Список другой отложенной части без первого элемента
+        Tail(Rc<Lazy<T>>),
+    }
+
+    /// Отложенный список: вычисляется при первом обращении и запоминается
+    struct Lazy<T> {
+        value: OnceCell<List<T>>,
+        pending: Cell<Option<Pending<T>>>,
+    }
+
+    impl<T: Clone> Lazy<T> {
+        fn new(pending: Pending<T>) -> Rc<Self> {
+            Rc::new(Lazy {
+                value: OnceCell::new(),
+                pending: Cell::new(Some(pending)),
+            })
+        }
+
+        fn ready(list: List<T>) -> Rc<Self> {
+            Rc::new(Lazy {
+                value: OnceCell::from(list),
+                pending: Cell::new(None),
+            })
+        }
+
+        /// Цепочка невычисленных `Tail` проходится циклом, каждое звено запоминает свой список
+        fn force(&self) -> &List<T> {
+            if let Some(list) = self.value.get() {
+                return list;
+            }
+            // невычисленные звенья под `self`, последним - самое глубокое
+            let mut chain: Vec<Rc<Lazy<T>>> = vec![];
+            let mut pending = self.pending.take();
+            // список самого глубокого звена: последнего в `chain` или самого `self`
+            let mut list = loop {
+                match pending.take().expect("pending list of an unforced Lazy") {
+                    Pending::Rotate(front, rear) => break append_reversed(&front, &rear),
+                    Pending::Tail(previous) => {
+                        if let Some(list) = previous.value.get() {
+                            break list.tail();
+                        }
+                        pending = previous.pending.take();
+                        chain.push(previous);
+                    }
+                }
+            };
+            while let Some(link) = chain.pop() {
+                let above = list.tail();
+                let _ = link.value.set(list);
+                list = above;
+            }
+            self.value.get_or_init(|| list)
+        }
+    }
+
+    /// Длинная цепочка `Tail` освобождается циклом, без рекурсии
+    impl<T> Drop for Lazy<T> {
+        fn drop(&mut self) {
+            let mut pending = self.pending.take();
+            while let Some(Pending::Tail(previous)) = pending {
+                pending = match Rc::try_unwrap(previous) {
+                    Ok(previous) => previous.pending.take(),
+                    Err(_) => None,
+                };
+            }
+        }
+    }
+
+    pub struct PersistentQueue<T> {
+        // вычисленное начало `front`, голова - самый старый элемент; пусто только у пустой очереди
+        prefix: List<T>,
+        // весь `front`, вычисляется не раньше, чем кончится `prefix`
+        front: Rc<Lazy<T>>,
+        front_len: usize,
+        // голова - самый новый элемент
+        rear: List<T>,
+        rear_len: usize,
+    }
+
+    impl<T> Clone for PersistentQueue<T> {
+        fn clone(&self) -> Self {
+            PersistentQueue {
+                prefix: self.prefix.clone(),
+                front: self.front.clone(),
+                front_len: self.front_len,
+                rear: self.rear.clone(),
+                rear_len: self.rear_len,
+            }
+        }
+    }
+
+    impl<T: Clone> Default for PersistentQueue<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Clone> PersistentQueue<T> {
+        pub fn new() -> Self {
+            PersistentQueue {
+                prefix: List::new(),
+                front: Lazy::ready(List::new()),
+                front_len: 0,
+                rear: List::new(),
+                rear_len: 0,
+            }
+        }
+
+        /// Новая версия с элементом в конце
+        pub fn snoc(&self, elem: T) -> Self {
+            Self::check(
+                self.prefix.clone(),
+                self.front.clone(),
+                self.front_len,
+                self.rear.prepend(elem),
+                self.rear_len + 1,
+            )
+        }
+
+        /// Первый элемент и версия без него
+        pub fn uncons(&self) -> Option<(&T, Self)> {
+            let head = self.prefix.head()?;
+            let rest = Self::check(
+                self.prefix.tail(),
+                Lazy::new(Pending::Tail(self.front.clone())),
+                self.front_len - 1,
+                self.rear.clone(),
+                self.rear_len,
+            );
+            Some((head, rest))
+        }
+
+        pub fn peek(&self) -> Option<&T> {
+            self.prefix.head()
+        }
+
+        pub fn len(&self) -> usize {
+            self.front_len + self.rear_len
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Вычисляет `front`, если он ещё отложен
+        pub fn iter(&self) -> Iter<'_, T> {
+            Iter::new(self.front.force(), &self.rear)
+        }
+
+        /// Восстанавливает инвариант `rear_len <= front_len`. Разворот `rear` откладывается:
+        /// новым `prefix` становится уже вычисленный `front` длины не меньше `rear_len - 1`,
+        /// и пока он не кончится, отложенный список не нужен
+        fn check(
+            prefix: List<T>,
+            front: Rc<Lazy<T>>,
+            front_len: usize,
+            rear: List<T>,
+            rear_len: usize,
+        ) -> Self {
+            if rear_len <= front_len {
+                return Self::check_prefix(prefix, front, front_len, rear, rear_len);
+            }
+            let forced = front.force().clone();
+            let front = Lazy::new(Pending::Rotate(forced.clone(), rear));
+            Self::check_prefix(forced, front, front_len + rear_len, List::new(), 0)
+        }
+
+        /// Восстанавливает инвариант: `prefix` пуст, только если пуст `front`
+        fn check_prefix(
+            prefix: List<T>,
+            front: Rc<Lazy<T>>,
+            front_len: usize,
+            rear: List<T>,
+            rear_len: usize,
+        ) -> Self {
+            let prefix = match prefix.head() {
+                Some(_) => prefix,
+                None => front.force().clone(),
+            };
+            PersistentQueue {
+                prefix,
+                front,
+                front_len,
+                rear,
+                rear_len,
+            }
+        }
+    }
+
+    impl<T: Clone> FromIterator<T> for PersistentQueue<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            iter.into_iter()
+                .fold(PersistentQueue::new(), |queue, elem| queue.snoc(elem))
+        }
+    }
+
+    impl<T: Clone + Debug> Debug for PersistentQueue<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+
+    impl<T: Clone + PartialEq> PartialEq for PersistentQueue<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.len() == other.len() && self.iter().eq(other.iter())
+        }
+    }
+
+    /// Допустимое отношение длин концов дека; при `C` = 2 или 3 расписания успевают
+    /// вычислить отложенные концы до следующего перестроения
+    const C: usize = 3;
+
+    /// Ленивый список: ячейка вычисляется при первом обращении и запоминается,
+    /// так что её вычисляет только первая из версий, которые её разделяют
+    struct Stream<T>(Rc<StreamCell<T>>);
+
+    struct StreamCell<T> {
+        value: OnceCell<Option<(T, Stream<T>)>>,
+        suspended: Cell<Option<Suspended<T>>>,
+    }
+
+    /// Отложенные шаги перестроения дека, каждый делает O(C) работы
+    enum Suspended<T> {
+        /// Первые `n` элементов списка
+        Take(usize, Stream<T>),
+        /// `front ++ reverse(rear) ++ acc`, за шаг переносит в `acc` по `C` элементов `rear`
+        RotateRev(Stream<T>, Stream<T>, Stream<T>),
+        /// `front ++ reverse(drop(j, rear))`, за шаг отбрасывает по `C` элементов `rear`
+        RotateDrop(Stream<T>, usize, Stream<T>),
+    }
+
+    impl<T> Clone for Stream<T> {
+        fn clone(&self) -> Self {
+            Stream(self.0.clone())
+        }
+    }
+
+    impl<T> Stream<T> {
+        fn ready(value: Option<(T, Stream<T>)>) -> Self {
+            Stream(Rc::new(StreamCell {
+                value: OnceCell::from(value),
+                suspended: Cell::new(None),
+            }))
+        }
+
+        fn empty() -> Self {
+            Self::ready(None)
+        }
+
+        fn suspend(suspended: Suspended<T>) -> Self {
+            Stream(Rc::new(StreamCell {
+                value: OnceCell::new(),
+                suspended: Cell::new(Some(suspended)),
+            }))
+        }
+    }
+
+    impl<T: Clone> Stream<T> {
+        /// Первый элемент и остаток, отложенная ячейка вычисляется
+        fn force(&self) -> Option<&(T, Stream<T>)> {
+            self.0
+                .value
+                .get_or_init(|| {
+                    let suspended = self.0.suspended.take();
+                    suspended.expect("suspension of an unforced cell").eval()
+                })
+                .as_ref()
+        }
+
+        fn head(&self) -> Option<&T> {
+            self.force().map(|(elem, _)| elem)
+        }
+
+        /// Шаг расписания: вычисляет первую ячейку и возвращает остаток
+        fn exec(&self) -> Stream<T> {
+            match self.force() {
+                Some((_, rest)) => rest.clone(),
+                None => self.clone(),
+            }
+        }
+
+        /// Список без первых `n` элементов
+        fn skip(&self, n: usize) -> Stream<T> {
+            let mut rest = self.clone();
+            for _ in 0..n {
+                let next = match rest.force() {
+                    Some((_, tail)) => tail.clone(),
+                    None => break,
+                };
+                rest = next;
+            }
+            rest
+        }
+
+        /// `reverse(take(n, self)) ++ acc`, вычисляется сразу
+        fn prepend_reversed(&self, n: usize, mut acc: Stream<T>) -> Stream<T> {
+            let mut rest = self;
+            for _ in 0..n {
+                let Some((elem, tail)) = rest.force() else {
+                    break;
+                };
+                acc = Stream::ready(Some((elem.clone(), acc)));
+                rest = tail;
+            }
+            acc
+        }
+    }
+
+    impl<T: Clone> Suspended<T> {
+        fn eval(self) -> Option<(T, Stream<T>)> {
+            match self {
+                Suspended::Take(0, _) => None,
+                Suspended::Take(n, stream) => stream.force().map(|(elem, rest)| {
+                    let rest = Stream::suspend(Suspended::Take(n - 1, rest.clone()));
+                    (elem.clone(), rest)
+                }),
+                Suspended::RotateRev(front, rear, acc) => match front.force() {
+                    // здесь в `rear` не больше `C` элементов
+                    None => rear.prepend_reversed(usize::MAX, acc).force().cloned(),
+                    Some((elem, rest)) => {
+                        let acc = rear.prepend_reversed(C, acc);
+                        let rest = Suspended::RotateRev(rest.clone(), rear.skip(C), acc);
+                        Some((elem.clone(), Stream::suspend(rest)))
+                    }
+                },
+                Suspended::RotateDrop(front, j, rear) if j < C => {
+                    Suspended::RotateRev(front, rear.skip(j), Stream::empty()).eval()
+                }
+                Suspended::RotateDrop(front, j, rear) => {
+                    let (elem, rest) = front.force().expect("short side outlasts the drop");
+                    let rest = Suspended::RotateDrop(rest.clone(), j - C, rear.skip(C));
+                    Some((elem.clone(), Stream::suspend(rest)))
+                }
+            }
+        }
+    }
+
+    /// Длинный список освобождается циклом, без рекурсии
+    impl<T> Drop for StreamCell<T> {
+        fn drop(&mut self) {
+            let mut next = self.value.take().flatten().map(|(_, rest)| rest);
+            while let Some(Stream(cell)) = next {
+                next = match Rc::try_unwrap(cell) {
+                    Ok(mut cell) => cell.value.take().flatten().map(|(_, rest)| rest),
+                    Err(_) => None,
+                };
+            }
+        }
+    }
+
+    /// Элементы дека от начала к концу
+    pub struct DequeIter<'a, T> {
+        front: &'a Stream<T>,
+        rear: &'a Stream<T>,
+        // как в `Iter`, ссылки на элементы `rear` собираются один раз
+        reversed: Option<std::iter::Rev<std::vec::IntoIter<&'a T>>>,
+    }
+
+    impl<'a, T: Clone> Iterator for DequeIter<'a, T> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<Self::Item> {
+            if let Some((elem, rest)) = self.front.force() {
+                self.front = rest;
+                return Some(elem);
+            }
+            let rear = self.rear;
+            self.reversed
+                .get_or_insert_with(|| {
+                    let mut items = vec![];
+                    let mut rest = rear;
+                    while let Some((elem, tail)) = rest.force() {
+                        items.push(elem);
+                        rest = tail;
+                    }
+                    items.into_iter().rev()
+                })
+                .next()
+        }
+    }
+
+    pub struct PersistentDeque<T> {
+        // голова - первый элемент дека
+        front: Stream<T>,
+        front_len: usize,
+        // ещё не вычисленная часть `front`
+        front_schedule: Stream<T>,
+        // голова - последний элемент дека
+        rear: Stream<T>,
+        rear_len: usize,
+        rear_schedule: Stream<T>,
+    }
+
+    impl<T> Clone for PersistentDeque<T> {
+        fn clone(&self) -> Self {
+            PersistentDeque {
+                front: self.front.clone(),
+                front_len: self.front_len,
+                front_schedule: self.front_schedule.clone(),
+                rear: self.rear.clone(),
+                rear_len: self.rear_len,
+                rear_schedule: self.rear_schedule.clone(),
+            }
+        }
+    }
+
+    impl<T: Clone> Default for PersistentDeque<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Clone> PersistentDeque<T> {
+        pub fn new() -> Self {
+            PersistentDeque {
+                front: Stream::empty(),
+                front_len: 0,
+                front_schedule: Stream::empty(),
+                rear: Stream::empty(),
+                rear_len: 0,
+                rear_schedule: Stream::empty(),
+            }
+        }
+
+        pub fn push_front(&self, elem: T) -> Self {
+            Self::check(
+                (
+                    Stream::ready(Some((elem, self.front.clone()))),
+                    self.front_len + 1,
+                    self.front_schedule.exec(),
+                ),
+                (self.rear.clone(), self.rear_len, self.rear_schedule.exec()),
+            )
+        }
+
+        pub fn push_back(&self, elem: T) -> Self {
+            Self::check(
+                (
+                    self.front.clone(),
+                    self.front_len,
+                    self.front_schedule.exec(),
+                ),
+                (
+                    Stream::ready(Some((elem, self.rear.clone()))),
+                    self.rear_len + 1,
+                    self.rear_schedule.exec(),
+                ),
+            )
+        }
+
+        /// Первый элемент и версия без него
+        pub fn pop_front(&self) -> Option<(&T, Self)> {
+            if self.front_len == 0 {
+                // по инварианту в rear не больше одного элемента
+                return self.rear.head().map(|elem| (elem, Self::new()));
+            }
+            let (elem, rest) = self.front.force()?;
+            let rest = Self::check(
+                (
+                    rest.clone(),
+                    self.front_len - 1,
+                    self.front_schedule.exec().exec(),
+                ),
+                (
+                    self.rear.clone(),
+                    self.rear_len,
+                    self.rear_schedule.exec().exec(),
+                ),
+            );
+            Some((elem, rest))
+        }
+
+        /// Последний элемент и версия без него
+        pub fn pop_back(&self) -> Option<(&T, Self)> {
+            if self.rear_len == 0 {
+                return self.front.head().map(|elem| (elem, Self::new()));
+            }
+            let (elem, rest) = self.rear.force()?;
+            let rest = Self::check(
+                (
+                    self.front.clone(),
+                    self.front_len,
+                    self.front_schedule.exec().exec(),
+                ),
+                (
+                    rest.clone(),
+                    self.rear_len - 1,
+                    self.rear_schedule.exec().exec(),
+                ),
+            );
+            Some((elem, rest))
+        }
+
+        pub fn front(&self) -> Option<&T> {
+            self.front.head().or_else(|| self.rear.head())
+        }
+
+        pub fn back(&self) -> Option<&T> {
+            self.rear.head().or_else(|| self.front.head())
+        }
+
+        pub fn len(&self) -> usize {
+            self.front_len + self.rear_len
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        pub fn iter(&self) -> DequeIter<'_, T> {
+            DequeIter {
+                front: &self.front,
+                rear: &self.rear,
+                reversed: None,
+            }
+        }
+
+        /// Восстанавливает инвариант `front_len <= C * rear_len + 1` и `rear_len <= C * front_len + 1`.
+        /// Концы - тройки (список, длина, расписание)
+        fn check(
+            (front, front_len, front_schedule): (Stream<T>, usize, Stream<T>),
+            (rear, rear_len, rear_schedule): (Stream<T>, usize, Stream<T>),
+        ) -> Self {
+            let len = front_len + rear_len;
+            let (front, front_len, rear, rear_len) = if front_len > C * rear_len + 1 {
+                let keep = len / 2;
+                let rear = Suspended::RotateDrop(rear, keep, front.clone());
+                let front = Suspended::Take(keep, front);
+                (front, keep, rear, len - keep)
+            } else if rear_len > C * front_len + 1 {
+                let keep = len / 2;
+                let front = Suspended::RotateDrop(front, keep, rear.clone());
+                let rear = Suspended::Take(keep, rear);
+                (front, len - keep, rear, keep)
+            } else {
+                return PersistentDeque {
+                    front,
+                    front_len,
+                    front_schedule,
+                    rear,
+                    rear_len,
+                    rear_schedule,
+                };
+            };
+            let (front, rear) = (Stream::suspend(front), Stream::suspend(rear));
+            PersistentDeque {
+                front: front.clone(),
+                front_len,
+                front_schedule: front,
+                rear: rear.clone(),
+                rear_len,
+                rear_schedule: rear,
+            }
+        }
+    }
+
+    impl<T: Clone> FromIterator<T> for PersistentDeque<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            iter.into_iter()
+                .fold(PersistentDeque::new(), |deque, elem| deque.push_back(elem))
+        }
+    }
+
+    impl<T: Clone + Debug> Debug for PersistentDeque<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+
+    impl<T: Clone + PartialEq> PartialEq for PersistentDeque<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.len() == other.len() && self.iter().eq(other.iter())
+        }
+    }
+}
+
+/// $ cargo test queue_persistent
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_queue_success() {
+        let queue = PersistentQueue::new().snoc(1).snoc(2).snoc(3);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek(), Some(&1));
+
+        let (first, rest) = queue.uncons().unwrap();
+        assert_eq!(first, &1);
+        let (second, rest) = rest.uncons().unwrap();
+        assert_eq!(second, &2);
+        let rest = rest.snoc(4);
+        assert_eq!(rest.iter().copied().collect::<Vec<_>>(), vec![3, 4]);
+
+        let (_, rest) = rest.uncons().unwrap();
+        let (_, rest) = rest.uncons().unwrap();
+        assert!(rest.is_empty());
+        assert!(rest.uncons().is_none());
+    }
+
+    #[test]
+    fn test_queue_snapshots() {
+        let mut snapshots = vec![PersistentQueue::new()];
+        for event in 0..20 {
+            let next = snapshots.last().unwrap().snoc(event);
+            snapshots.push(next);
+        }
+        // ветка от старой версии не влияет на другие версии
+        let (_, branch) = snapshots[10].uncons().unwrap();
+        let branch = branch.snoc(100);
+
+        for (len, snapshot) in snapshots.iter().enumerate() {
+            assert_eq!(
+                snapshot.iter().copied().collect::<Vec<_>>(),
+                (0..len as i32).collect::<Vec<_>>()
+            );
+        }
+        let mut expected: Vec<i32> = (1..10).collect();
+        expected.push(100);
+        assert_eq!(branch.iter().copied().collect::<Vec<_>>(), expected);
+    }
+
+    /// Ветки от одной версии не повторяют O(n) перестроение: отложенный разворот вычисляется
+    /// один раз на все версии, которые его разделяют
+    #[test]
+    fn test_queue_branches_share_rebuild() {
+        use std::cell::Cell;
+        thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Debug, PartialEq)]
+        struct Counted(usize);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Counted(self.0)
+            }
+        }
+
+        for len in 0..200 {
+            let queue: PersistentQueue<Counted> = (0..len).map(Counted).collect();
+            let before = CLONES.with(Cell::get);
+            for branch in 0..50 {
+                let version = queue.snoc(Counted(len + branch));
+                let (first, rest) = version.uncons().unwrap();
+                assert_eq!(first, &Counted(if len == 0 { branch } else { 0 }));
+                assert_eq!(rest.len(), len);
+            }
+            let clones = CLONES.with(Cell::get) - before;
+            assert!(clones <= 2 * len + 4 * 50, "len {len}: {clones} clones");
+        }
+    }
+
+    /// Длинные цепочки отложенных хвостов вычисляются и освобождаются без рекурсии
+    #[test]
+    fn test_queue_long_lazy_chain() {
+        let len = 300_000;
+        let queue: PersistentQueue<usize> = (0..len).collect();
+        let drain = |count: usize| {
+            let mut rest = queue.clone();
+            for _ in 0..count {
+                rest = rest.uncons().unwrap().1;
+            }
+            rest
+        };
+        // цепочка не вычислялась
+        drop(drain(len / 4));
+
+        let rest = drain(len / 4);
+        assert_eq!(rest.iter().next(), Some(&(len / 4)));
+        let branch = rest.snoc(len);
+        drop(rest);
+        assert_eq!(branch.len(), len - len / 4 + 1);
+        assert_eq!(branch.iter().last(), Some(&len));
+    }
+
+    #[test]
+    fn test_deque_success() {
+        let deque = PersistentDeque::new()
+            .push_back(2)
+            .push_front(1)
+            .push_back(3);
+        assert_eq!(deque.front(), Some(&1));
+        assert_eq!(deque.back(), Some(&3));
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let (back, rest) = deque.pop_back().unwrap();
+        assert_eq!(back, &3);
+        let (front, rest) = rest.pop_front().unwrap();
+        assert_eq!(front, &1);
+        let (last, rest) = rest.pop_back().unwrap();
+        assert_eq!(last, &2);
+        assert!(rest.is_empty());
+        assert!(rest.pop_front().is_none());
+
+        // старая версия не изменилась
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    /// Ветки от версии на границе перестроения стоят O(1): перенос элементов отложен,
+    /// и каждая операция вычисляет только несколько ячеек
+    #[test]
+    fn test_deque_branches_share_rebuild() {
+        use std::cell::Cell;
+        thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Debug, PartialEq)]
+        struct Counted(usize);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Counted(self.0)
+            }
+        }
+
+        for len in 0..200 {
+            let deque: PersistentDeque<Counted> = (0..len).map(Counted).collect();
+            let before = CLONES.with(Cell::get);
+            for branch in 0..50 {
+                // `collect` растит rear, поэтому многие версии стоят на границе перестроения
+                let version = deque.push_back(Counted(len + branch));
+                let (first, rest) = version.pop_front().unwrap();
+                assert_eq!(first, &Counted(if len == 0 { branch } else { 0 }));
+                assert_eq!(rest.len(), len);
+            }
+            let clones = CLONES.with(Cell::get) - before;
+            assert!(clones <= 8 * 50, "len {len}: {clones} clones");
+        }
+    }
+
+    /// Длинные ленивые концы вычисляются и освобождаются без рекурсии
+    #[test]
+    fn test_deque_long() {
+        let len = 300_000;
+        let deque: PersistentDeque<usize> = (0..len).collect();
+        let mut rest = deque.clone();
+        for i in 0..len / 2 {
+            let (elem, next) = rest.pop_front().unwrap();
+            assert_eq!(*elem, i);
+            rest = next;
+        }
+        assert_eq!(rest.back(), Some(&(len - 1)));
+        assert!(deque.iter().copied().eq(0..len));
+        drop(deque);
+        assert!(rest.iter().copied().eq(len / 2..len));
+    }
+
+    /// Операции над случайными старыми версиями, сверка со снимками `VecDeque`
+    #[test]
+    fn test_deque_random_versions() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut versions = vec![(PersistentDeque::new(), VecDeque::new())];
+        for i in 0..3000 {
+            let (deque, model) = &versions[rng.gen_range(0..versions.len())];
+            let (mut deque, mut model) = (deque.clone(), model.clone());
+            for _ in 0..rng.gen_range(1..20) {
+                deque = match rng.gen_range(0..5) {
+                    0 | 1 => {
+                        model.push_front(i);
+                        deque.push_front(i)
+                    }
+                    2 => {
+                        model.push_back(i);
+                        deque.push_back(i)
+                    }
+                    3 => match deque.pop_front() {
+                        Some((elem, rest)) => {
+                            assert_eq!(Some(*elem), model.pop_front());
+                            rest
+                        }
+                        None => deque,
+                    },
+                    _ => match deque.pop_back() {
+                        Some((elem, rest)) => {
+                            assert_eq!(Some(*elem), model.pop_back());
+                            rest
+                        }
+                        None => deque,
+                    },
+                };
+                assert_eq!(deque.len(), model.len());
+                assert_eq!((deque.front(), deque.back()), (model.front(), model.back()));
+            }
+            versions.push((deque, model));
+        }
+        for (deque, model) in &versions {
+            assert!(deque.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn test_random_against_vec_deque() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut queue = PersistentQueue::new();
+        let mut deque = PersistentDeque::new();
+        let mut queue_model = VecDeque::new();
+        let mut deque_model = VecDeque::new();
+        for i in 0..2000 {
+            if rng.gen_bool(0.6) {
+                queue = queue.snoc(i);
+                queue_model.push_back(i);
+            } else if let Some((elem, rest)) = queue.uncons() {
+                assert_eq!(Some(*elem), queue_model.pop_front());
+                queue = rest;
+            }
+
+            deque = match rng.gen_range(0..4) {
+                0 => {
+                    deque_model.push_front(i);
+                    deque.push_front(i)
+                }
+                1 => {
+                    deque_model.push_back(i);
+                    deque.push_back(i)
+                }
+                2 => match deque.pop_front() {
+                    Some((elem, rest)) => {
+                        assert_eq!(Some(*elem), deque_model.pop_front());
+                        rest
+                    }
+                    None => deque,
+                },
+                _ => match deque.pop_back() {
+                    Some((elem, rest)) => {
+                        assert_eq!(Some(*elem), deque_model.pop_back());
+                        rest
+                    }
+                    None => deque,
+                },
+            };
+
+            assert_eq!(queue.len(), queue_model.len());
+            assert_eq!(queue.peek(), queue_model.front());
+            assert_eq!(deque.len(), deque_model.len());
+            assert_eq!(deque.front(), deque_model.front());
+            assert_eq!(deque.back(), deque_model.back());
+        }
+        assert!(queue.iter().eq(queue_model.iter()));
+        assert!(deque.iter().eq(deque_model.iter()));
+    }
+}
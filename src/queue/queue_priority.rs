@@ -4,99 +4,296 @@
 ///! со значением приоритета.
 ///! И элементы обслуживаются на основе их приоритета.
 ///! То есть первыми обслуживаются элементы с более высоким приоритетом.
-pub use ds_queue_priority::{Priority, QueuePriority};
+pub use ds_queue_priority::{Priority, PriorityQueue, QueuePriority};
 mod ds_queue_priority {
+    use std::cmp::Ordering;
+    use std::collections::BinaryHeap;
 
-    #[derive(PartialEq, PartialOrd, Debug)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
     pub enum Priority {
         LOW,
         MIDDLE,
         HIGH,
     }
 
+    /// Очередь с произвольным упорядоченным приоритетом на двоичной куче.
+    /// `enqueue`/`dequeue` за O(log n), элементы с равным приоритетом выходят в порядке добавления (FIFO).
     #[derive(Debug)]
-    pub struct QueuePriority<T> {
-        queue_high: Vec<T>,
-        queue_middle: Vec<T>,
-        queue_low: Vec<T>,
+    pub struct PriorityQueue<T, P: Ord> {
+        heap: Vec<Entry<T, P>>,
+        order: u64,
     }
 
-    impl<T: std::cmp::PartialEq> QueuePriority<T> {
+    #[derive(Debug)]
+    struct Entry<T, P> {
+        priority: P,
+        order: u64,
+        item: T,
+    }
+
+    impl<T, P: Ord> Entry<T, P> {
+        /// Элемент обслуживается раньше: больший приоритет, при равенстве - добавлен раньше
+        fn outranks(&self, other: &Entry<T, P>) -> bool {
+            match self.priority.cmp(&other.priority) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => self.order < other.order,
+            }
+        }
+    }
+
+    impl<T, P: Ord> Default for PriorityQueue<T, P> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, P: Ord> PriorityQueue<T, P> {
         pub fn new() -> Self {
             Self {
-                queue_low: Vec::new(),
-                queue_middle: Vec::new(),
-                queue_high: Vec::new(),
+                heap: Vec::new(),
+                order: 0,
             }
         }
-        pub fn enqueue(&mut self, item: T, priority: Priority) {
-            match priority {
-                Priority::HIGH => {
-                    self.queue_high.push(item);
-                }
-                Priority::MIDDLE => {
-                    self.queue_middle.push(item);
-                }
-                Priority::LOW => {
-                    self.queue_low.push(item);
-                }
+
+        pub fn with_capacity(capacity: usize) -> Self {
+            Self {
+                heap: Vec::with_capacity(capacity),
+                order: 0,
             }
         }
 
+        pub fn enqueue(&mut self, item: T, priority: P) {
+            self.heap.push(Entry {
+                priority,
+                order: self.order,
+                item,
+            });
+            self.order += 1;
+            self.sift_up(self.heap.len() - 1);
+        }
+
         pub fn dequeue(&mut self) -> Option<T> {
-            if !self.is_empty() {
-                if !self.queue_high.is_empty() {
-                    return Some(self.queue_high.remove(0));
-                }
-                if !self.queue_middle.is_empty() {
-                    return Some(self.queue_middle.remove(0));
-                }
-                if !self.queue_low.is_empty() {
-                    return Some(self.queue_low.remove(0));
-                }
+            self.dequeue_with_priority().map(|(item, _priority)| item)
+        }
+
+        pub fn dequeue_with_priority(&mut self) -> Option<(T, P)> {
+            if self.is_empty() {
+                return None;
             }
-            None
+            let entry = self.remove_at(0);
+            Some((entry.item, entry.priority))
         }
+
         pub fn peek(&self) -> Option<&T> {
-            if !self.is_empty() {
-                if !self.queue_high.is_empty() {
-                    return self.queue_high.first();
+            self.heap.first().map(|entry| &entry.item)
+        }
+
+        pub fn peek_priority(&self) -> Option<&P> {
+            self.heap.first().map(|entry| &entry.priority)
+        }
+
+        /// Изменение элемента не влияет на порядок, он определяется только приоритетом
+        pub fn peek_mut(&mut self) -> Option<&mut T> {
+            self.heap.first_mut().map(|entry| &mut entry.item)
+        }
+
+        pub fn len(&self) -> usize {
+            self.heap.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.heap.is_empty()
+        }
+
+        pub fn clear(&mut self) {
+            self.heap.clear();
+        }
+
+        /// Обход в порядке обслуживания без извлечения элементов, O(k log k) для первых k элементов
+        pub fn iter(&self) -> Iter<'_, T, P> {
+            Iter::new(self)
+        }
+
+        pub fn search(&mut self, item: &T) -> Option<&mut T>
+        where
+            T: PartialEq,
+        {
+            let index = self.position(|el, _priority| el == item)?;
+            Some(&mut self.heap[index].item)
+        }
+
+        /// Элемент переносится в конец очереди с новым приоритетом
+        pub fn change_priority(&mut self, find_item: &T, new_priority: P) -> bool
+        where
+            T: PartialEq,
+        {
+            match self.position(|el, _priority| el == find_item) {
+                Some(index) => {
+                    let entry = self.remove_at(index);
+                    self.enqueue(entry.item, new_priority);
+                    true
                 }
-                if !self.queue_middle.is_empty() {
-                    return self.queue_middle.first();
+                None => false,
+            }
+        }
+
+        /// Индекс первого в порядке обслуживания элемента, удовлетворяющего условию
+        fn position<F: Fn(&T, &P) -> bool>(&self, predicate: F) -> Option<usize> {
+            let mut found: Option<usize> = None;
+            for (index, entry) in self.heap.iter().enumerate() {
+                if predicate(&entry.item, &entry.priority) {
+                    found = match found {
+                        Some(best) if self.heap[best].outranks(entry) => Some(best),
+                        _ => Some(index),
+                    };
                 }
-                if !self.queue_low.is_empty() {
-                    return self.queue_low.first();
+            }
+            found
+        }
+
+        fn remove_at(&mut self, index: usize) -> Entry<T, P> {
+            let entry = self.heap.swap_remove(index);
+            if index < self.heap.len() {
+                self.sift_down(index);
+                self.sift_up(index);
+            }
+            entry
+        }
+
+        fn sift_up(&mut self, mut index: usize) {
+            while index > 0 {
+                let parent = (index - 1) / 2;
+                if !self.heap[index].outranks(&self.heap[parent]) {
+                    break;
                 }
+                self.heap.swap(index, parent);
+                index = parent;
             }
-            None
         }
 
-        pub fn search(&mut self, item: T) -> Option<&mut T> {
-            if !self.is_empty() {
-                if !self.queue_high.is_empty() {
-                    for el in self.queue_high.iter_mut() {
-                        if el == &item {
-                            return Some(el);
-                        }
-                    }
+        fn sift_down(&mut self, mut index: usize) {
+            let len = self.heap.len();
+            loop {
+                let left = 2 * index + 1;
+                let right = left + 1;
+                let mut top = index;
+                if left < len && self.heap[left].outranks(&self.heap[top]) {
+                    top = left;
+                }
+                if right < len && self.heap[right].outranks(&self.heap[top]) {
+                    top = right;
                 }
-                if !self.queue_middle.is_empty() {
-                    for el in self.queue_middle.iter_mut() {
-                        if el == &item {
-                            return Some(el);
-                        }
-                    }
+                if top == index {
+                    break;
                 }
-                if !self.queue_low.is_empty() {
-                    for el in self.queue_low.iter_mut() {
-                        if el == &item {
-                            return Some(el);
-                        }
-                    }
+                self.heap.swap(index, top);
+                index = top;
+            }
+        }
+    }
+
+    pub struct Iter<'a, T, P: Ord> {
+        queue: &'a PriorityQueue<T, P>,
+        frontier: BinaryHeap<Candidate<'a, T, P>>,
+    }
+
+    struct Candidate<'a, T, P>(&'a Entry<T, P>, usize);
+
+    impl<T, P: Ord> PartialEq for Candidate<'_, T, P> {
+        fn eq(&self, other: &Self) -> bool {
+            self.1 == other.1
+        }
+    }
+
+    impl<T, P: Ord> Eq for Candidate<'_, T, P> {}
+
+    impl<T, P: Ord> PartialOrd for Candidate<'_, T, P> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<T, P: Ord> Ord for Candidate<'_, T, P> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            if self.1 == other.1 {
+                Ordering::Equal
+            } else if self.0.outranks(other.0) {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        }
+    }
+
+    impl<'a, T, P: Ord> Iter<'a, T, P> {
+        fn new(queue: &'a PriorityQueue<T, P>) -> Self {
+            let mut frontier = BinaryHeap::new();
+            if let Some(root) = queue.heap.first() {
+                frontier.push(Candidate(root, 0));
+            }
+            Self { queue, frontier }
+        }
+    }
+
+    impl<'a, T, P: Ord> Iterator for Iter<'a, T, P> {
+        type Item = (&'a T, &'a P);
+        fn next(&mut self) -> Option<Self::Item> {
+            let Candidate(entry, index) = self.frontier.pop()?;
+            for child in [2 * index + 1, 2 * index + 2] {
+                if let Some(child_entry) = self.queue.heap.get(child) {
+                    self.frontier.push(Candidate(child_entry, child));
                 }
             }
-            None
+            Some((&entry.item, &entry.priority))
+        }
+    }
+
+    pub struct IntoIter<T, P: Ord>(PriorityQueue<T, P>);
+
+    impl<T, P: Ord> Iterator for IntoIter<T, P> {
+        type Item = T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.dequeue()
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.0.len(), Some(self.0.len()))
+        }
+    }
+
+    impl<T, P: Ord> IntoIterator for PriorityQueue<T, P> {
+        type Item = T;
+        type IntoIter = IntoIter<T, P>;
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self)
+        }
+    }
+
+    /// Очередь с тремя уровнями приоритета, обёртка над `PriorityQueue`
+    #[derive(Debug)]
+    pub struct QueuePriority<T> {
+        queue: PriorityQueue<T, Priority>,
+    }
+
+    impl<T: std::cmp::PartialEq> QueuePriority<T> {
+        pub fn new() -> Self {
+            Self {
+                queue: PriorityQueue::new(),
+            }
+        }
+        pub fn enqueue(&mut self, item: T, priority: Priority) {
+            self.queue.enqueue(item, priority);
+        }
+
+        pub fn dequeue(&mut self) -> Option<T> {
+            self.queue.dequeue()
+        }
+        pub fn peek(&self) -> Option<&T> {
+            self.queue.peek()
+        }
+
+        pub fn search(&mut self, item: T) -> Option<&mut T> {
+            self.queue.search(&item)
         }
 
         pub fn change_priority(
@@ -105,118 +302,37 @@ mod ds_queue_priority {
             find_priority: Priority,
             new_priority: Priority,
         ) -> bool {
-            if !self.is_empty() {
-                let el = match find_priority {
-                    Priority::HIGH => {
-                        let mut el = None;
-                        if !self.queue_high.is_empty() {
-                            let mut find_index = None;
-                            for (index, el) in self.queue_high.iter_mut().enumerate() {
-                                if el == &find_item {
-                                    find_index = Some(index);
-                                }
-                            }
-                            if let Some(index) = find_index {
-                                el = Some(self.queue_high.remove(index));
-                            }
-                        }
-                        el
-                    }
-                    Priority::MIDDLE => {
-                        let mut el = None;
-                        if !self.queue_middle.is_empty() {
-                            let mut find_index = None;
-                            for (index, el) in self.queue_middle.iter_mut().enumerate() {
-                                if el == &find_item {
-                                    find_index = Some(index);
-                                }
-                            }
-                            if let Some(index) = find_index {
-                                el = Some(self.queue_middle.remove(index));
-                            }
-                        }
-                        el
-                    }
-                    Priority::LOW => {
-                        let mut el = None;
-                        if !self.queue_low.is_empty() {
-                            let mut find_index = None;
-                            for (index, el) in self.queue_low.iter_mut().enumerate() {
-                                if el == &find_item {
-                                    find_index = Some(index);
-                                }
-                            }
-                            if let Some(index) = find_index {
-                                el = Some(self.queue_low.remove(index));
-                            }
-                        }
-                        el
-                    }
-                };
-                if let Some(el) = el {
-                    match new_priority {
-                        Priority::HIGH => {
-                            self.queue_high.push(el);
-                        }
-                        Priority::MIDDLE => {
-                            self.queue_middle.push(el);
-                        }
-                        Priority::LOW => {
-                            self.queue_low.push(el);
-                        }
-                    }
-                    return true;
+            match self
+                .queue
+                .position(|el, priority| el == &find_item && priority == &find_priority)
+            {
+                Some(index) => {
+                    let entry = self.queue.remove_at(index);
+                    self.queue.enqueue(entry.item, new_priority);
+                    true
                 }
+                None => false,
             }
-            false
         }
 
         pub fn peek_mut(&mut self) -> Option<&mut T> {
-            if !self.is_empty() {
-                if !self.queue_high.is_empty() {
-                    return self.queue_high.first_mut();
-                }
-                if !self.queue_middle.is_empty() {
-                    return self.queue_middle.first_mut();
-                }
-                if !self.queue_low.is_empty() {
-                    return self.queue_low.first_mut();
-                }
-            }
-            None
+            self.queue.peek_mut()
         }
 
         pub fn is_empty(&self) -> bool {
-            self.queue_high.is_empty() && self.queue_middle.is_empty() && self.queue_low.is_empty()
+            self.queue.is_empty()
         }
 
         pub fn iter<'a>(&'a self) -> IterQueuePriority<'a, T> {
-            IterQueuePriority::new(self)
+            IterQueuePriority(self.queue.iter())
         }
     }
 
-    pub struct IterQueuePriority<'a, T>((usize, usize, usize), &'a QueuePriority<T>);
-    impl<'a, T> IterQueuePriority<'a, T> {
-        fn new(q: &'a QueuePriority<T>) -> Self {
-            Self((0, 0, 0), q)
-        }
-    }
+    pub struct IterQueuePriority<'a, T>(Iter<'a, T, Priority>);
     impl<'a, T> Iterator for IterQueuePriority<'a, T> {
         type Item = &'a T;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.0 .0 < self.1.queue_high.len() {
-                self.0 .0 += 1;
-                return self.1.queue_high.get(self.0 .0 - 1);
-            }
-            if self.0 .1 < self.1.queue_middle.len() {
-                self.0 .1 += 1;
-                return self.1.queue_middle.get(self.0 .1 - 1);
-            }
-            if self.0 .2 < self.1.queue_low.len() {
-                self.0 .2 += 1;
-                return self.1.queue_low.get(self.0 .2 - 1);
-            }
-            None
+            self.0.next().map(|(item, _priority)| item)
         }
     }
 }
@@ -304,4 +420,73 @@ mod tests {
         assert_eq!(queue.peek(), Some(&99));
         assert_eq!(queue.dequeue(), Some(99));
     }
+
+    // $ cargo test queue::queue_priority::tests::test_priority_queue_fifo -- --nocapture
+    #[test]
+    fn test_priority_queue_fifo() {
+        let mut queue: PriorityQueue<&str, u32> = PriorityQueue::new();
+        queue.enqueue("a", 1);
+        queue.enqueue("b", 5);
+        queue.enqueue("c", 1);
+        queue.enqueue("d", 5);
+        queue.enqueue("e", 3);
+        queue.enqueue("f", 1);
+
+        assert_eq!(queue.len(), 6);
+        assert_eq!(queue.peek_priority(), Some(&5));
+        let order: Vec<&str> = queue.into_iter().collect();
+        assert_eq!(order, vec!["b", "d", "e", "a", "c", "f"]);
+    }
+
+    #[test]
+    fn test_priority_queue_iter() {
+        let mut queue: PriorityQueue<i32, i32> = PriorityQueue::new();
+        for (i, priority) in [4, 9, 1, 9, 7, 4, 0].into_iter().enumerate() {
+            queue.enqueue(i as i32, priority);
+        }
+        let order: Vec<(i32, i32)> = queue.iter().map(|(i, p)| (*i, *p)).collect();
+        assert_eq!(
+            order,
+            vec![(1, 9), (3, 9), (4, 7), (0, 4), (5, 4), (2, 1), (6, 0)]
+        );
+        assert_eq!(queue.len(), 7);
+        assert_eq!(queue.dequeue_with_priority(), Some((1, 9)));
+    }
+
+    #[test]
+    fn test_priority_queue_change_priority() {
+        let mut queue: PriorityQueue<char, u8> = PriorityQueue::new();
+        queue.enqueue('x', 1);
+        queue.enqueue('y', 2);
+        queue.enqueue('z', 2);
+
+        assert!(queue.change_priority(&'x', 2));
+        assert!(!queue.change_priority(&'w', 2));
+        if let Some(item) = queue.search(&'z') {
+            *item = 'Z';
+        }
+        assert_eq!(queue.dequeue(), Some('y'));
+        assert_eq!(queue.dequeue(), Some('Z'));
+        assert_eq!(queue.dequeue(), Some('x'));
+        assert_eq!(queue.dequeue(), None);
+    }
+
+    #[test]
+    fn test_priority_queue_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut queue: PriorityQueue<usize, u8> = PriorityQueue::with_capacity(1000);
+        let mut expected = vec![];
+        for i in 0..1000 {
+            let priority = rng.gen_range(0..16);
+            queue.enqueue(i, priority);
+            expected.push((std::cmp::Reverse(priority), i));
+        }
+        expected.sort();
+        let expected: Vec<usize> = expected.into_iter().map(|(_, i)| i).collect();
+        let from_iter: Vec<usize> = queue.iter().map(|(i, _)| *i).collect();
+        assert_eq!(from_iter, expected);
+        let order: Vec<usize> = queue.into_iter().collect();
+        assert_eq!(order, expected);
+    }
 }
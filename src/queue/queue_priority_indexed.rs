@@ -0,0 +1,357 @@
+#![allow(dead_code)]
+
+//! Индексированная очередь с приоритетом.
+//! `push` возвращает дескриптор (`Handle`), по которому за O(log n) можно
+//! уменьшить или увеличить ключ элемента, а также удалить его из очереди.
+//! Первым обслуживается элемент с наименьшим ключом, как того требуют
+//! алгоритмы на графах (Дейкстра, Прим). Для обратного порядка используйте `std::cmp::Reverse`.
+pub use ds_queue_priority_indexed::{Handle, IndexedPriorityQueue};
+mod ds_queue_priority_indexed {
+
+    /// Дескриптор элемента в очереди.
+    /// После извлечения элемента дескриптор становится недействительным,
+    /// даже если его ячейка будет занята новым элементом.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+    pub struct Handle {
+        slot: usize,
+        generation: u64,
+    }
+
+    #[derive(Debug)]
+    struct Slot<T, P> {
+        generation: u64,
+        entry: Option<Entry<T, P>>,
+    }
+
+    #[derive(Debug)]
+    struct Entry<T, P> {
+        item: T,
+        key: P,
+        position: usize,
+    }
+
+    /// Двоичная куча из номеров ячеек, каждая ячейка помнит свою позицию в куче
+    #[derive(Debug)]
+    pub struct IndexedPriorityQueue<T, P: Ord> {
+        heap: Vec<usize>,
+        slots: Vec<Slot<T, P>>,
+        free: Vec<usize>,
+    }
+
+    impl<T, P: Ord> Default for IndexedPriorityQueue<T, P> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, P: Ord> IndexedPriorityQueue<T, P> {
+        pub fn new() -> Self {
+            Self {
+                heap: Vec::new(),
+                slots: Vec::new(),
+                free: Vec::new(),
+            }
+        }
+
+        pub fn with_capacity(capacity: usize) -> Self {
+            Self {
+                heap: Vec::with_capacity(capacity),
+                slots: Vec::with_capacity(capacity),
+                free: Vec::new(),
+            }
+        }
+
+        pub fn push(&mut self, item: T, key: P) -> Handle {
+            let position = self.heap.len();
+            let entry = Some(Entry {
+                item,
+                key,
+                position,
+            });
+            let slot = match self.free.pop() {
+                Some(slot) => {
+                    self.slots[slot].entry = entry;
+                    slot
+                }
+                None => {
+                    self.slots.push(Slot {
+                        generation: 0,
+                        entry,
+                    });
+                    self.slots.len() - 1
+                }
+            };
+            self.heap.push(slot);
+            self.sift_up(position);
+            Handle {
+                slot,
+                generation: self.slots[slot].generation,
+            }
+        }
+
+        pub fn pop(&mut self) -> Option<(T, P)> {
+            let slot = *self.heap.first()?;
+            self.take(slot)
+        }
+
+        pub fn peek(&self) -> Option<(&T, &P)> {
+            let slot = *self.heap.first()?;
+            self.entry(slot).map(|entry| (&entry.item, &entry.key))
+        }
+
+        pub fn len(&self) -> usize {
+            self.heap.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.heap.is_empty()
+        }
+
+        pub fn contains(&self, handle: Handle) -> bool {
+            self.resolve(handle).is_some()
+        }
+
+        pub fn get(&self, handle: Handle) -> Option<(&T, &P)> {
+            let slot = self.resolve(handle)?;
+            self.entry(slot).map(|entry| (&entry.item, &entry.key))
+        }
+
+        /// Изменение элемента не влияет на порядок, он определяется только ключом
+        pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+            let slot = self.resolve(handle)?;
+            self.slots[slot].entry.as_mut().map(|entry| &mut entry.item)
+        }
+
+        /// Уменьшает ключ элемента. Возвращает `false`, если дескриптор недействителен
+        /// или новый ключ больше текущего.
+        pub fn decrease_key(&mut self, handle: Handle, key: P) -> bool {
+            match self.resolve(handle) {
+                Some(slot) if self.entry(slot).is_some_and(|entry| key <= entry.key) => {
+                    let position = self.set_key(slot, key);
+                    self.sift_up(position);
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        /// Увеличивает ключ элемента. Возвращает `false`, если дескриптор недействителен
+        /// или новый ключ меньше текущего.
+        pub fn increase_key(&mut self, handle: Handle, key: P) -> bool {
+            match self.resolve(handle) {
+                Some(slot) if self.entry(slot).is_some_and(|entry| key >= entry.key) => {
+                    let position = self.set_key(slot, key);
+                    self.sift_down(position);
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        pub fn remove(&mut self, handle: Handle) -> Option<(T, P)> {
+            let slot = self.resolve(handle)?;
+            self.take(slot)
+        }
+
+        pub fn clear(&mut self) {
+            for slot in self.heap.drain(..) {
+                self.slots[slot].entry = None;
+                self.slots[slot].generation += 1;
+                self.free.push(slot);
+            }
+        }
+
+        fn resolve(&self, handle: Handle) -> Option<usize> {
+            match self.slots.get(handle.slot) {
+                Some(slot) if slot.generation == handle.generation && slot.entry.is_some() => {
+                    Some(handle.slot)
+                }
+                _ => None,
+            }
+        }
+
+        fn entry(&self, slot: usize) -> Option<&Entry<T, P>> {
+            self.slots[slot].entry.as_ref()
+        }
+
+        fn key(&self, position: usize) -> &P {
+            &self.slots[self.heap[position]]
+                .entry
+                .as_ref()
+                .expect("heap refers to an empty slot")
+                .key
+        }
+
+        fn set_key(&mut self, slot: usize, key: P) -> usize {
+            let entry = self.slots[slot]
+                .entry
+                .as_mut()
+                .expect("heap refers to an empty slot");
+            entry.key = key;
+            entry.position
+        }
+
+        fn take(&mut self, slot: usize) -> Option<(T, P)> {
+            let entry = self.slots[slot].entry.take()?;
+            self.slots[slot].generation += 1;
+            self.free.push(slot);
+
+            let position = entry.position;
+            let last = self.heap.len() - 1;
+            self.heap.swap(position, last);
+            self.heap.pop();
+            if position < self.heap.len() {
+                self.set_position(position);
+                self.sift_down(position);
+                self.sift_up(position);
+            }
+            Some((entry.item, entry.key))
+        }
+
+        fn swap(&mut self, a: usize, b: usize) {
+            self.heap.swap(a, b);
+            self.set_position(a);
+            self.set_position(b);
+        }
+
+        fn set_position(&mut self, position: usize) {
+            if let Some(entry) = self.slots[self.heap[position]].entry.as_mut() {
+                entry.position = position;
+            }
+        }
+
+        fn sift_up(&mut self, mut position: usize) {
+            while position > 0 {
+                let parent = (position - 1) / 2;
+                if self.key(position) >= self.key(parent) {
+                    break;
+                }
+                self.swap(position, parent);
+                position = parent;
+            }
+        }
+
+        fn sift_down(&mut self, mut position: usize) {
+            let len = self.heap.len();
+            loop {
+                let left = 2 * position + 1;
+                let right = left + 1;
+                let mut top = position;
+                if left < len && self.key(left) < self.key(top) {
+                    top = left;
+                }
+                if right < len && self.key(right) < self.key(top) {
+                    top = right;
+                }
+                if top == position {
+                    break;
+                }
+                self.swap(position, top);
+                position = top;
+            }
+        }
+    }
+}
+
+/// $ cargo +nightly miri test queue_priority_indexed
+/// $ cargo test queue_priority_indexed
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success() {
+        let mut queue: IndexedPriorityQueue<&str, u32> = IndexedPriorityQueue::new();
+        queue.push("c", 30);
+        queue.push("a", 10);
+        queue.push("b", 20);
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek(), Some((&"a", &10)));
+        assert_eq!(queue.pop(), Some(("a", 10)));
+        assert_eq!(queue.pop(), Some(("b", 20)));
+        assert_eq!(queue.pop(), Some(("c", 30)));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_decrease_increase_key() {
+        let mut queue: IndexedPriorityQueue<char, i32> = IndexedPriorityQueue::new();
+        let a = queue.push('a', 5);
+        let b = queue.push('b', 10);
+        let c = queue.push('c', 15);
+
+        assert!(queue.decrease_key(c, 1));
+        assert!(!queue.decrease_key(b, 20));
+        assert!(queue.increase_key(a, 12));
+        assert!(!queue.increase_key(a, 0));
+        assert_eq!(queue.get(a), Some((&'a', &12)));
+
+        assert_eq!(queue.pop(), Some(('c', 1)));
+        assert_eq!(queue.pop(), Some(('b', 10)));
+        assert_eq!(queue.pop(), Some(('a', 12)));
+    }
+
+    #[test]
+    fn test_remove_and_stale_handle() {
+        let mut queue: IndexedPriorityQueue<u32, u32> = IndexedPriorityQueue::new();
+        let handles: Vec<Handle> = (0..10).map(|i| queue.push(i, 10 - i)).collect();
+
+        assert_eq!(queue.remove(handles[4]), Some((4, 6)));
+        assert_eq!(queue.remove(handles[4]), None);
+        assert!(!queue.contains(handles[4]));
+
+        // ячейка переиспользуется, но старый дескриптор к ней не подходит
+        let reused = queue.push(100, 0);
+        assert!(!queue.decrease_key(handles[4], 0));
+        if let Some(item) = queue.get_mut(reused) {
+            *item = 101;
+        }
+        assert_eq!(queue.pop(), Some((101, 0)));
+
+        let order: Vec<u32> = std::iter::from_fn(|| queue.pop().map(|(i, _)| i)).collect();
+        assert_eq!(order, vec![9, 8, 7, 6, 5, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut queue: IndexedPriorityQueue<usize, u32> = IndexedPriorityQueue::with_capacity(500);
+        let mut keys = vec![];
+        let mut handles = vec![];
+        for i in 0..500 {
+            let key = rng.gen_range(100..1000);
+            handles.push(queue.push(i, key));
+            keys.push(Some(key));
+        }
+        for _ in 0..300 {
+            let i = rng.gen_range(0..500);
+            match rng.gen_range(0..3) {
+                0 => {
+                    let key = keys[i].map_or(0, |current| rng.gen_range(0..=current));
+                    assert_eq!(queue.decrease_key(handles[i], key), keys[i].is_some());
+                    if keys[i].is_some() {
+                        keys[i] = Some(key);
+                    }
+                }
+                1 => {
+                    let key = keys[i].map_or(0, |current| current + rng.gen_range(0..1000));
+                    assert_eq!(queue.increase_key(handles[i], key), keys[i].is_some());
+                    if keys[i].is_some() {
+                        keys[i] = Some(key);
+                    }
+                }
+                _ => {
+                    assert_eq!(queue.remove(handles[i]).map(|(_, k)| k), keys[i]);
+                    keys[i] = None;
+                }
+            }
+        }
+        let mut expected: Vec<u32> = keys.into_iter().flatten().collect();
+        expected.sort();
+        let popped: Vec<u32> = std::iter::from_fn(|| queue.pop().map(|(_, k)| k)).collect();
+        assert_eq!(popped, expected);
+    }
+}
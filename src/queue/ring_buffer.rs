@@ -0,0 +1,382 @@
+#![allow(dead_code)]
+
+//! Кольцевой буфер фиксированной ёмкости `N` для скользящих окон телеметрии.
+//! При заполнении либо отклоняет новый элемент (`OverflowMode::Reject`, как `stack_array::ArrayStack::try_push`),
+//! либо затирает самый старый (`OverflowMode::Overwrite`).
+//! Обход идёт от самого старого элемента к самому новому.
+pub use ds_ring_buffer::{Iter, OverflowMode, RingBuffer, SlidingWindow};
+mod ds_ring_buffer {
+    use crate::ring_deque::RingDeque;
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum OverflowMode {
+        /// Новый элемент не добавляется
+        Reject,
+        /// Самый старый элемент вытесняется
+        Overwrite,
+    }
+
+    #[derive(Debug)]
+    pub struct RingBuffer<T, const N: usize> {
+        data: [Option<T>; N],
+        // ячейка самого старого элемента
+        head: usize,
+        len: usize,
+        mode: OverflowMode,
+    }
+
+    impl<T, const N: usize> RingBuffer<T, N> {
+        pub fn new(mode: OverflowMode) -> Self {
+            Self {
+                data: std::array::from_fn(|_| None),
+                head: 0,
+                len: 0,
+                mode,
+            }
+        }
+
+        pub fn mode(&self) -> OverflowMode {
+            self.mode
+        }
+
+        pub fn capacity(&self) -> usize {
+            N
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        pub fn is_full(&self) -> bool {
+            self.len == N
+        }
+
+        /// `Ok(None)` - элемент добавлен, `Ok(Some(old))` - добавлен с вытеснением самого старого,
+        /// `Err(item)` - буфер заполнен в режиме `Reject` и элемент возвращается обратно
+        pub fn push(&mut self, item: T) -> Result<Option<T>, T> {
+            if N == 0 {
+                return Err(item);
+            }
+            if self.is_full() {
+                return match self.mode {
+                    OverflowMode::Reject => Err(item),
+                    OverflowMode::Overwrite => {
+                        let evicted = self.data[self.head].replace(item);
+                        self.head = (self.head + 1) % N;
+                        Ok(evicted)
+                    }
+                };
+            }
+            self.data[(self.head + self.len) % N] = Some(item);
+            self.len += 1;
+            Ok(None)
+        }
+
+        /// Извлекает самый старый элемент
+        pub fn pop(&mut self) -> Option<T> {
+            if self.is_empty() {
+                return None;
+            }
+            let item = self.data[self.head].take();
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            item
+        }
+
+        /// Элемент по порядку добавления, 0 - самый старый
+        pub fn get(&self, index: usize) -> Option<&T> {
+            if index >= self.len {
+                return None;
+            }
+            self.data[(self.head + index) % N].as_ref()
+        }
+
+        pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+            if index >= self.len {
+                return None;
+            }
+            self.data[(self.head + index) % N].as_mut()
+        }
+
+        pub fn oldest(&self) -> Option<&T> {
+            self.get(0)
+        }
+
+        pub fn newest(&self) -> Option<&T> {
+            self.len.checked_sub(1).and_then(|index| self.get(index))
+        }
+
+        pub fn clear(&mut self) {
+            while self.pop().is_some() {}
+            self.head = 0;
+        }
+
+        pub fn iter(&self) -> Iter<'_, T, N> {
+            Iter {
+                buffer: self,
+                front: 0,
+                back: self.len,
+            }
+        }
+    }
+
+    pub struct Iter<'a, T, const N: usize> {
+        buffer: &'a RingBuffer<T, N>,
+        front: usize,
+        back: usize,
+    }
+
+    impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.front == self.back {
+                return None;
+            }
+            self.front += 1;
+            self.buffer.get(self.front - 1)
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.back - self.front, Some(self.back - self.front))
+        }
+    }
+
+    impl<T, const N: usize> DoubleEndedIterator for Iter<'_, T, N> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            if self.front == self.back {
+                return None;
+            }
+            self.back -= 1;
+            self.buffer.get(self.back)
+        }
+    }
+
+    impl<T, const N: usize> ExactSizeIterator for Iter<'_, T, N> {}
+
+    impl<'a, T, const N: usize> IntoIterator for &'a RingBuffer<T, N> {
+        type Item = &'a T;
+        type IntoIter = Iter<'a, T, N>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    /// Скользящее окно из последних `N` значений с минимумом и максимумом за O(1).
+    /// Монотонные деки хранят порядковые номера кандидатов: в `mins` значения неубывают, в `maxs` невозрастают,
+    /// поэтому амортизированная стоимость `push` - O(1).
+    #[derive(Debug)]
+    pub struct SlidingWindow<T: Ord, const N: usize> {
+        buffer: RingBuffer<T, N>,
+        // порядковый номер самого старого элемента окна
+        start: u64,
+        mins: RingDeque<u64>,
+        maxs: RingDeque<u64>,
+    }
+
+    impl<T: Ord, const N: usize> Default for SlidingWindow<T, N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Ord, const N: usize> SlidingWindow<T, N> {
+        pub fn new() -> Self {
+            Self {
+                buffer: RingBuffer::new(OverflowMode::Overwrite),
+                start: 0,
+                mins: RingDeque::with_capacity(N),
+                maxs: RingDeque::with_capacity(N),
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.buffer.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.buffer.is_empty()
+        }
+
+        pub fn is_full(&self) -> bool {
+            self.buffer.is_full()
+        }
+
+        /// Добавляет значение, возвращает вытесненное из окна
+        pub fn push(&mut self, item: T) -> Option<T> {
+            if N == 0 {
+                return None;
+            }
+            let evicted = if self.buffer.is_full() {
+                self.pop()
+            } else {
+                None
+            };
+            let seq = self.start + self.buffer.len() as u64;
+            while self
+                .mins
+                .back()
+                .is_some_and(|back| self.value(*back) > &item)
+            {
+                self.mins.pop_back();
+            }
+            while self
+                .maxs
+                .back()
+                .is_some_and(|back| self.value(*back) < &item)
+            {
+                self.maxs.pop_back();
+            }
+            self.mins.push_back(seq);
+            self.maxs.push_back(seq);
+            let _ = self.buffer.push(item);
+            evicted
+        }
+
+        /// Извлекает самое старое значение окна
+        pub fn pop(&mut self) -> Option<T> {
+            let item = self.buffer.pop()?;
+            if self.mins.front() == Some(&self.start) {
+                self.mins.pop_front();
+            }
+            if self.maxs.front() == Some(&self.start) {
+                self.maxs.pop_front();
+            }
+            self.start += 1;
+            Some(item)
+        }
+
+        pub fn min(&self) -> Option<&T> {
+            self.mins.front().map(|seq| self.value(*seq))
+        }
+
+        pub fn max(&self) -> Option<&T> {
+            self.maxs.front().map(|seq| self.value(*seq))
+        }
+
+        pub fn oldest(&self) -> Option<&T> {
+            self.buffer.oldest()
+        }
+
+        pub fn newest(&self) -> Option<&T> {
+            self.buffer.newest()
+        }
+
+        pub fn iter(&self) -> Iter<'_, T, N> {
+            self.buffer.iter()
+        }
+
+        pub fn clear(&mut self) {
+            while self.pop().is_some() {}
+        }
+
+        fn value(&self, seq: u64) -> &T {
+            self.buffer
+                .get((seq - self.start) as usize)
+                .expect("monotonic deque refers to a value outside the window")
+        }
+    }
+}
+
+/// $ cargo +nightly miri test ring_buffer
+/// $ cargo test ring_buffer
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject() {
+        let mut buffer: RingBuffer<i32, 3> = RingBuffer::new(OverflowMode::Reject);
+        assert_eq!(buffer.push(1), Ok(None));
+        assert_eq!(buffer.push(2), Ok(None));
+        assert_eq!(buffer.push(3), Ok(None));
+        assert!(buffer.is_full());
+        assert_eq!(buffer.push(4), Err(4));
+
+        assert_eq!(buffer.pop(), Some(1));
+        assert_eq!(buffer.push(4), Ok(None));
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let mut buffer: RingBuffer<i32, 3> = RingBuffer::new(OverflowMode::Overwrite);
+        for i in 1..=3 {
+            assert_eq!(buffer.push(i), Ok(None));
+        }
+        assert_eq!(buffer.push(4), Ok(Some(1)));
+        assert_eq!(buffer.push(5), Ok(Some(2)));
+
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.oldest(), Some(&3));
+        assert_eq!(buffer.newest(), Some(&5));
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(
+            buffer.iter().rev().copied().collect::<Vec<_>>(),
+            vec![5, 4, 3]
+        );
+
+        if let Some(v) = buffer.get_mut(1) {
+            *v = 40;
+        }
+        assert_eq!(buffer.pop(), Some(3));
+        assert_eq!(buffer.pop(), Some(40));
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut buffer: RingBuffer<String, 0> = RingBuffer::new(OverflowMode::Overwrite);
+        assert_eq!(buffer.push("a".to_string()), Err("a".to_string()));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_sliding_window_min_max() {
+        let mut window: SlidingWindow<i32, 3> = SlidingWindow::new();
+        assert_eq!(window.min(), None);
+
+        let mut summary = vec![];
+        for value in [5, 1, 3, 4, 8, 2, 2, 9] {
+            window.push(value);
+            summary.push((*window.min().unwrap(), *window.max().unwrap()));
+        }
+        assert_eq!(
+            summary,
+            vec![
+                (5, 5),
+                (1, 5),
+                (1, 5),
+                (1, 4),
+                (3, 8),
+                (2, 8),
+                (2, 8),
+                (2, 9)
+            ]
+        );
+
+        assert_eq!(window.pop(), Some(2));
+        assert_eq!((window.min(), window.max()), (Some(&2), Some(&9)));
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![2, 9]);
+    }
+
+    #[test]
+    fn test_sliding_window_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut window: SlidingWindow<u32, 16> = SlidingWindow::new();
+        let mut values = vec![];
+        for _ in 0..1000 {
+            let value = rng.gen_range(0..50);
+            values.push(value);
+            window.push(value);
+            let last = &values[values.len().saturating_sub(16)..];
+            assert_eq!(window.min(), last.iter().min());
+            assert_eq!(window.max(), last.iter().max());
+        }
+    }
+}
@@ -0,0 +1,630 @@
+#![allow(dead_code)]
+
+//! Двусторонняя очередь на расширяемом кольцевом буфере.
+//! Добавление и удаление с обоих концов за амортизированное O(1), доступ по индексу за O(1).
+//! Кроме методов в духе `VecDeque` поддерживает интерфейс очереди `queue_vec::Queue`
+//! (`enqueue`/`dequeue`/`peek`), поэтому может заменить её без изменений в коде.
+pub use ds_ring_deque::{IntoIter, Iter, IterMut, RingDeque};
+mod ds_ring_deque {
+    use std::fmt::{self, Debug};
+    use std::mem::MaybeUninit;
+    use std::ops::{Index, IndexMut};
+    use std::slice;
+
+    const MIN_CAPACITY: usize = 4;
+
+    /// Элементы занимают ячейки `head..head + len` по модулю ёмкости буфера
+    pub struct RingDeque<T> {
+        buf: Vec<MaybeUninit<T>>,
+        head: usize,
+        len: usize,
+    }
+
+    impl<T> Default for RingDeque<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T> RingDeque<T> {
+        pub fn new() -> Self {
+            Self {
+                buf: Vec::new(),
+                head: 0,
+                len: 0,
+            }
+        }
+
+        pub fn with_capacity(capacity: usize) -> Self {
+            Self {
+                buf: Self::uninit_buf(capacity),
+                head: 0,
+                len: 0,
+            }
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.buf.len()
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        pub fn push_back(&mut self, item: T) {
+            if self.len == self.capacity() {
+                self.grow();
+            }
+            let index = self.physical(self.len);
+            self.buf[index] = MaybeUninit::new(item);
+            self.len += 1;
+        }
+
+        pub fn push_front(&mut self, item: T) {
+            if self.len == self.capacity() {
+                self.grow();
+            }
+            self.head = self.physical(self.capacity() - 1);
+            self.buf[self.head] = MaybeUninit::new(item);
+            self.len += 1;
+        }
+
+        pub fn pop_front(&mut self) -> Option<T> {
+            if self.is_empty() {
+                return None;
+            }
+            // SAFETY: ячейка head инициализирована, после чтения она исключается из диапазона
+            let item = unsafe { self.buf[self.head].assume_init_read() };
+            self.head = self.physical(1);
+            self.len -= 1;
+            Some(item)
+        }
+
+        pub fn pop_back(&mut self) -> Option<T> {
+            if self.is_empty() {
+                return None;
+            }
+            self.len -= 1;
+            let index = self.physical(self.len);
+            // SAFETY: последняя ячейка инициализирована, после чтения она исключается из диапазона
+            Some(unsafe { self.buf[index].assume_init_read() })
+        }
+
+        pub fn get(&self, index: usize) -> Option<&T> {
+            if index >= self.len {
+                return None;
+            }
+            // SAFETY: index < len, ячейка инициализирована
+            Some(unsafe { self.buf[self.physical(index)].assume_init_ref() })
+        }
+
+        pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+            if index >= self.len {
+                return None;
+            }
+            let index = self.physical(index);
+            // SAFETY: index < len, ячейка инициализирована
+            Some(unsafe { self.buf[index].assume_init_mut() })
+        }
+
+        pub fn front(&self) -> Option<&T> {
+            self.get(0)
+        }
+
+        pub fn front_mut(&mut self) -> Option<&mut T> {
+            self.get_mut(0)
+        }
+
+        pub fn back(&self) -> Option<&T> {
+            self.len.checked_sub(1).and_then(|index| self.get(index))
+        }
+
+        pub fn back_mut(&mut self) -> Option<&mut T> {
+            self.len
+                .checked_sub(1)
+                .and_then(|index| self.get_mut(index))
+        }
+
+        /// Содержимое в виде двух срезов: от головы до конца буфера и от начала буфера
+        pub fn as_slices(&self) -> (&[T], &[T]) {
+            let (first, second) = self.ranges();
+            // SAFETY: оба диапазона содержат только инициализированные ячейки
+            unsafe {
+                (
+                    slice_assume_init(&self.buf[first.0..first.1]),
+                    slice_assume_init(&self.buf[second.0..second.1]),
+                )
+            }
+        }
+
+        pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+            let (first, second) = self.ranges();
+            let (left, right) = self.buf.split_at_mut(first.0);
+            // SAFETY: диапазоны не пересекаются и содержат только инициализированные ячейки
+            unsafe {
+                (
+                    slice_assume_init_mut(&mut right[..first.1 - first.0]),
+                    slice_assume_init_mut(&mut left[second.0..second.1]),
+                )
+            }
+        }
+
+        /// Переносит элементы в начало буфера, после чего они лежат одним срезом
+        pub fn make_contiguous(&mut self) -> &mut [T] {
+            if self.head + self.len > self.capacity() {
+                self.buf.rotate_left(self.head);
+                self.head = 0;
+            }
+            self.as_mut_slices().0
+        }
+
+        /// Первые `n` элементов переходят в конец, O(min(n, len - n))
+        pub fn rotate_left(&mut self, n: usize) {
+            assert!(n <= self.len, "rotate_left: n > len");
+            if n <= self.len / 2 {
+                for _ in 0..n {
+                    let item = self.pop_front().unwrap();
+                    self.push_back(item);
+                }
+            } else {
+                self.rotate_right(self.len - n);
+            }
+        }
+
+        /// Последние `n` элементов переходят в начало, O(min(n, len - n))
+        pub fn rotate_right(&mut self, n: usize) {
+            assert!(n <= self.len, "rotate_right: n > len");
+            if n <= self.len / 2 {
+                for _ in 0..n {
+                    let item = self.pop_back().unwrap();
+                    self.push_front(item);
+                }
+            } else {
+                self.rotate_left(self.len - n);
+            }
+        }
+
+        pub fn clear(&mut self) {
+            while self.pop_back().is_some() {}
+            self.head = 0;
+        }
+
+        pub fn iter(&self) -> Iter<'_, T> {
+            let (first, second) = self.as_slices();
+            Iter {
+                first: first.iter(),
+                second: second.iter(),
+            }
+        }
+
+        pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+            let (first, second) = self.as_mut_slices();
+            IterMut {
+                first: first.iter_mut(),
+                second: second.iter_mut(),
+            }
+        }
+
+        // Интерфейс очереди queue_vec::Queue
+
+        pub fn enqueue(&mut self, item: T) {
+            self.push_back(item);
+        }
+
+        pub fn dequeue(&mut self) -> Option<T> {
+            self.pop_front()
+        }
+
+        pub fn peek(&self) -> Option<&T> {
+            self.front()
+        }
+
+        pub fn peek_mut(&mut self) -> Option<&mut T> {
+            self.front_mut()
+        }
+
+        pub fn length(&self) -> usize {
+            self.len
+        }
+
+        pub fn search(&mut self, item: T) -> Option<&mut T>
+        where
+            T: PartialEq,
+        {
+            self.iter_mut().find(|el| **el == item)
+        }
+
+        fn physical(&self, index: usize) -> usize {
+            let index = self.head + index;
+            if index >= self.capacity() {
+                index - self.capacity()
+            } else {
+                index
+            }
+        }
+
+        fn ranges(&self) -> ((usize, usize), (usize, usize)) {
+            let capacity = self.capacity();
+            if self.head + self.len <= capacity {
+                ((self.head, self.head + self.len), (0, 0))
+            } else {
+                ((self.head, capacity), (0, self.head + self.len - capacity))
+            }
+        }
+
+        fn grow(&mut self) {
+            let capacity = (self.capacity() * 2).max(MIN_CAPACITY);
+            let mut buf = Self::uninit_buf(capacity);
+            for (index, slot) in buf.iter_mut().enumerate().take(self.len) {
+                let from = self.physical(index);
+                // SAFETY: каждая инициализированная ячейка переносится ровно один раз,
+                // старый буфер из MaybeUninit не вызывает drop для элементов
+                *slot = MaybeUninit::new(unsafe { self.buf[from].assume_init_read() });
+            }
+            self.buf = buf;
+            self.head = 0;
+        }
+
+        fn uninit_buf(capacity: usize) -> Vec<MaybeUninit<T>> {
+            (0..capacity).map(|_| MaybeUninit::uninit()).collect()
+        }
+    }
+
+    unsafe fn slice_assume_init<T>(slice: &[MaybeUninit<T>]) -> &[T] {
+        &*(slice as *const [MaybeUninit<T>] as *const [T])
+    }
+
+    unsafe fn slice_assume_init_mut<T>(slice: &mut [MaybeUninit<T>]) -> &mut [T] {
+        &mut *(slice as *mut [MaybeUninit<T>] as *mut [T])
+    }
+
+    impl<T> Drop for RingDeque<T> {
+        fn drop(&mut self) {
+            self.clear();
+        }
+    }
+
+    impl<T> Index<usize> for RingDeque<T> {
+        type Output = T;
+        fn index(&self, index: usize) -> &T {
+            self.get(index).expect("RingDeque index out of bounds")
+        }
+    }
+
+    impl<T> IndexMut<usize> for RingDeque<T> {
+        fn index_mut(&mut self, index: usize) -> &mut T {
+            self.get_mut(index).expect("RingDeque index out of bounds")
+        }
+    }
+
+    impl<T: Clone> Clone for RingDeque<T> {
+        fn clone(&self) -> Self {
+            self.iter().cloned().collect()
+        }
+    }
+
+    impl<T: Debug> Debug for RingDeque<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+
+    impl<T: PartialEq> PartialEq for RingDeque<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.len == other.len && self.iter().eq(other.iter())
+        }
+    }
+
+    impl<T> Extend<T> for RingDeque<T> {
+        fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            for item in iter {
+                self.push_back(item);
+            }
+        }
+    }
+
+    impl<T> FromIterator<T> for RingDeque<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let mut deque = Self::new();
+            deque.extend(iter);
+            deque
+        }
+    }
+
+    pub struct Iter<'a, T> {
+        first: slice::Iter<'a, T>,
+        second: slice::Iter<'a, T>,
+    }
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.first.next().or_else(|| self.second.next())
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let len = self.first.len() + self.second.len();
+            (len, Some(len))
+        }
+    }
+
+    impl<T> DoubleEndedIterator for Iter<'_, T> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.second.next_back().or_else(|| self.first.next_back())
+        }
+    }
+
+    impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+    pub struct IterMut<'a, T> {
+        first: slice::IterMut<'a, T>,
+        second: slice::IterMut<'a, T>,
+    }
+
+    impl<'a, T> Iterator for IterMut<'a, T> {
+        type Item = &'a mut T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.first.next().or_else(|| self.second.next())
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let len = self.first.len() + self.second.len();
+            (len, Some(len))
+        }
+    }
+
+    impl<T> DoubleEndedIterator for IterMut<'_, T> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.second.next_back().or_else(|| self.first.next_back())
+        }
+    }
+
+    impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+    pub struct IntoIter<T>(RingDeque<T>);
+
+    impl<T> Iterator for IntoIter<T> {
+        type Item = T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.pop_front()
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.0.len, Some(self.0.len))
+        }
+    }
+
+    impl<T> DoubleEndedIterator for IntoIter<T> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.0.pop_back()
+        }
+    }
+
+    impl<T> ExactSizeIterator for IntoIter<T> {}
+
+    impl<T> IntoIterator for RingDeque<T> {
+        type Item = T;
+        type IntoIter = IntoIter<T>;
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self)
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a RingDeque<T> {
+        type Item = &'a T;
+        type IntoIter = Iter<'a, T>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a mut RingDeque<T> {
+        type Item = &'a mut T;
+        type IntoIter = IterMut<'a, T>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
+        }
+    }
+}
+
+/// $ cargo +nightly miri test ring_deque
+/// $ cargo test ring_deque
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // тесты queue_vec::Queue
+
+    #[test]
+    fn test_success() {
+        let mut queue: RingDeque<isize> = RingDeque::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
+        queue.enqueue(3);
+        queue.enqueue(4);
+        queue.enqueue(5);
+
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.dequeue(), Some(2));
+        assert_eq!(queue.dequeue(), Some(3));
+        assert_eq!(queue.dequeue(), Some(4));
+
+        assert_eq!(queue.peek(), Some(&5));
+        assert_eq!(queue.dequeue(), Some(5));
+        assert_eq!(queue.peek(), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut queue: RingDeque<isize> = RingDeque::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
+        queue.enqueue(3);
+        queue.enqueue(4);
+        queue.enqueue(5);
+
+        let item = queue.search(4).unwrap();
+        *item = 99;
+
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.dequeue(), Some(2));
+        assert_eq!(queue.dequeue(), Some(3));
+        assert_eq!(queue.dequeue(), Some(99));
+        assert_eq!(queue.dequeue(), Some(5));
+    }
+
+    #[test]
+    fn queue_peek_mut() {
+        let mut queue: RingDeque<isize> = RingDeque::new();
+        queue.enqueue(1);
+        queue.enqueue(2);
+        if let Some(v) = queue.peek_mut() {
+            *v = 99;
+        }
+        assert_eq!(queue.peek(), Some(&99));
+        assert_eq!(queue.dequeue(), Some(99));
+        assert_eq!(queue.length(), 1);
+    }
+
+    // тесты deque_std
+
+    #[test]
+    fn test_back_success() {
+        let mut deque: RingDeque<isize> = RingDeque::new();
+        deque.push_back(1);
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_back(4);
+        deque.push_back(5);
+
+        assert_eq!(deque.pop_back(), Some(5));
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_back(), Some(2));
+
+        assert_eq!(deque.back(), Some(&1));
+        assert_eq!(deque.pop_back(), Some(1));
+        assert_eq!(deque.back(), None);
+    }
+
+    #[test]
+    fn test_wrap_around_and_slices() {
+        let mut deque: RingDeque<i32> = RingDeque::with_capacity(4);
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        deque.push_front(0);
+        assert_eq!(deque.capacity(), 4);
+        assert_eq!(deque.as_slices(), (&[0, 1][..], &[2, 3][..]));
+        assert_eq!(deque[2], 2);
+        deque[3] = 30;
+
+        deque.push_back(4);
+        assert_eq!(deque.capacity(), 8);
+        assert_eq!(
+            deque.iter().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2, 30, 4]
+        );
+        assert_eq!(deque.get(5), None);
+    }
+
+    #[test]
+    fn test_rotate_and_make_contiguous() {
+        let mut deque: RingDeque<i32> = (0..7).collect();
+        deque.rotate_left(2);
+        assert_eq!(
+            deque.iter().copied().collect::<Vec<_>>(),
+            vec![2, 3, 4, 5, 6, 0, 1]
+        );
+        deque.rotate_right(5);
+        assert_eq!(
+            deque.iter().copied().collect::<Vec<_>>(),
+            vec![4, 5, 6, 0, 1, 2, 3]
+        );
+        deque.rotate_left(7);
+
+        deque.pop_front();
+        deque.push_front(-1);
+        deque.push_back(7);
+        assert!(!deque.as_slices().1.is_empty());
+        assert_eq!(deque.make_contiguous(), &[-1, 5, 6, 0, 1, 2, 3, 7]);
+        assert!(deque.as_slices().1.is_empty());
+    }
+
+    #[test]
+    fn test_double_ended_iterators() {
+        let mut deque: RingDeque<i32> = RingDeque::with_capacity(4);
+        deque.extend([3, 4]);
+        deque.push_front(2);
+        deque.push_front(1);
+
+        assert_eq!(deque.iter().len(), 4);
+        assert_eq!(
+            deque.iter().rev().copied().collect::<Vec<_>>(),
+            vec![4, 3, 2, 1]
+        );
+        let mut iter = deque.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.next(), None);
+
+        for el in deque.iter_mut().rev() {
+            *el *= 10;
+        }
+        assert_eq!(
+            deque.clone().into_iter().rev().collect::<Vec<_>>(),
+            vec![40, 30, 20, 10]
+        );
+        assert_eq!(format!("{:?}", deque), "[10, 20, 30, 40]");
+    }
+
+    #[test]
+    fn test_drop_elements() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        {
+            let mut deque: RingDeque<Rc<()>> = RingDeque::new();
+            for _ in 0..10 {
+                deque.push_front(counter.clone());
+                deque.push_back(counter.clone());
+            }
+            deque.pop_front();
+            deque.rotate_left(3);
+            assert_eq!(Rc::strong_count(&counter), 20);
+        }
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn test_same_as_vec_deque() {
+        use rand::Rng;
+        use std::collections::VecDeque;
+        let mut rng = rand::thread_rng();
+        let mut expected: VecDeque<u32> = VecDeque::new();
+        let mut deque: RingDeque<u32> = RingDeque::new();
+        for i in 0..2000 {
+            match rng.gen_range(0..5) {
+                0 => {
+                    expected.push_front(i);
+                    deque.push_front(i);
+                }
+                1 => {
+                    expected.push_back(i);
+                    deque.push_back(i);
+                }
+                2 => assert_eq!(deque.pop_front(), expected.pop_front()),
+                3 => assert_eq!(deque.pop_back(), expected.pop_back()),
+                _ => {
+                    let n = rng.gen_range(0..=expected.len());
+                    expected.rotate_left(n);
+                    deque.rotate_left(n);
+                }
+            }
+            assert_eq!(deque.len(), expected.len());
+        }
+        assert!(deque.iter().eq(expected.iter()));
+    }
+}
@@ -0,0 +1,264 @@
+#![allow(dead_code)]
+
+//! Указатели опасности (hazard pointers) для безопасного освобождения памяти в lock-free структурах.
+//!
+//! Поток, читающий разделяемый узел, публикует его адрес в `HazardPointer`.
+//! Удалённый из структуры узел передаётся в `retire` и освобождается только тогда,
+//! когда ни один указатель опасности на него не ссылается.
+//! Записи указателей опасности никогда не освобождаются и переиспользуются,
+//! поэтому их число ограничено максимальным числом одновременно захваченных указателей.
+pub use ds_hazard_pointer::{reclaim, retire, HazardPointer};
+mod ds_hazard_pointer {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::ptr;
+    use std::sync::atomic::{fence, AtomicBool, AtomicPtr, Ordering};
+    use std::sync::Mutex;
+
+    /// Сколько удалённых узлов копится в потоке перед проверкой указателей опасности
+    const SCAN_THRESHOLD: usize = 64;
+
+    struct Record {
+        hazard: AtomicPtr<u8>,
+        active: AtomicBool,
+        next: AtomicPtr<Record>,
+    }
+
+    struct Retired {
+        ptr: *mut u8,
+        drop: unsafe fn(*mut u8),
+    }
+
+    // SAFETY: retire принимает только узлы с T: Send
+    unsafe impl Send for Retired {}
+
+    /// Удалённые узлы потока. При завершении потока неосвобождённые узлы передаются в `ORPHANS`.
+    struct RetiredList(Vec<Retired>);
+
+    impl Drop for RetiredList {
+        fn drop(&mut self) {
+            let ready = take_unprotected(&mut self.0);
+            free(ready);
+            if !self.0.is_empty() {
+                lock_orphans().append(&mut self.0);
+            }
+        }
+    }
+
+    static RECORDS: AtomicPtr<Record> = AtomicPtr::new(ptr::null_mut());
+    static ORPHANS: Mutex<Vec<Retired>> = Mutex::new(Vec::new());
+
+    thread_local! {
+        static RETIRED: RefCell<RetiredList> = const { RefCell::new(RetiredList(Vec::new())) };
+    }
+
+    fn lock_orphans() -> std::sync::MutexGuard<'static, Vec<Retired>> {
+        ORPHANS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Защищает один узел от освобождения, пока указатель установлен
+    pub struct HazardPointer {
+        record: &'static Record,
+    }
+
+    impl Default for HazardPointer {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl HazardPointer {
+        /// Захватывает свободную запись или добавляет новую в глобальный список
+        pub fn new() -> Self {
+            let mut current = RECORDS.load(Ordering::Acquire);
+            while !current.is_null() {
+                // SAFETY: записи никогда не освобождаются
+                let record = unsafe { &*current };
+                if !record.active.load(Ordering::Relaxed)
+                    && record
+                        .active
+                        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                        .is_ok()
+                {
+                    return Self { record };
+                }
+                current = record.next.load(Ordering::Acquire);
+            }
+
+            let record: &'static Record = Box::leak(Box::new(Record {
+                hazard: AtomicPtr::new(ptr::null_mut()),
+                active: AtomicBool::new(true),
+                next: AtomicPtr::new(ptr::null_mut()),
+            }));
+            let mut head = RECORDS.load(Ordering::Acquire);
+            loop {
+                record.next.store(head, Ordering::Relaxed);
+                match RECORDS.compare_exchange_weak(
+                    head,
+                    record as *const Record as *mut Record,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => return Self { record },
+                    Err(actual) => head = actual,
+                }
+            }
+        }
+
+        /// Читает указатель из `src` и защищает его.
+        /// После возврата узел не будет освобождён, пока указатель не сброшен или не защищён другой узел.
+        pub fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+            let mut pointer = src.load(Ordering::Acquire);
+            loop {
+                self.record
+                    .hazard
+                    .store(pointer as *mut u8, Ordering::SeqCst);
+                let actual = src.load(Ordering::SeqCst);
+                if actual == pointer {
+                    return pointer;
+                }
+                pointer = actual;
+            }
+        }
+
+        /// Публикует указатель без проверки, вызывающий сам перепроверяет его актуальность
+        pub fn set<T>(&self, pointer: *mut T) {
+            self.record
+                .hazard
+                .store(pointer as *mut u8, Ordering::SeqCst);
+        }
+
+        pub fn reset(&self) {
+            self.record.hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+
+    impl Drop for HazardPointer {
+        fn drop(&mut self) {
+            self.reset();
+            self.record.active.store(false, Ordering::Release);
+        }
+    }
+
+    /// Откладывает освобождение узла до момента, когда его не защищает ни один указатель опасности.
+    ///
+    /// # Safety
+    /// `pointer` получен из `Box::into_raw`, уже недостижим из структуры
+    /// и передаётся в `retire` ровно один раз.
+    pub unsafe fn retire<T: Send>(pointer: *mut T) {
+        unsafe fn drop_box<T>(pointer: *mut u8) {
+            drop(Box::from_raw(pointer as *mut T));
+        }
+        let retired = Retired {
+            ptr: pointer as *mut u8,
+            drop: drop_box::<T>,
+        };
+        let ready = RETIRED.try_with(|list| {
+            let mut list = list.borrow_mut();
+            list.0.push(retired);
+            if list.0.len() >= SCAN_THRESHOLD {
+                take_unprotected(&mut list.0)
+            } else {
+                Vec::new()
+            }
+        });
+        match ready {
+            // освобождаем вне заимствования: деструктор T может снова вызвать retire
+            Ok(ready) => free(ready),
+            // поток завершается и его список уже уничтожен
+            Err(_) => lock_orphans().push(Retired {
+                ptr: pointer as *mut u8,
+                drop: drop_box::<T>,
+            }),
+        }
+    }
+
+    /// Освобождает все незащищённые узлы текущего потока и осиротевшие узлы завершившихся потоков
+    pub fn reclaim() {
+        let ready = RETIRED
+            .try_with(|list| take_unprotected(&mut list.borrow_mut().0))
+            .unwrap_or_default();
+        free(ready);
+    }
+
+    fn take_unprotected(retired: &mut Vec<Retired>) -> Vec<Retired> {
+        if let Ok(mut orphans) = ORPHANS.try_lock() {
+            retired.append(&mut orphans);
+        }
+        fence(Ordering::SeqCst);
+        let mut hazards = HashSet::new();
+        let mut current = RECORDS.load(Ordering::Acquire);
+        while !current.is_null() {
+            // SAFETY: записи никогда не освобождаются
+            let record = unsafe { &*current };
+            let hazard = record.hazard.load(Ordering::SeqCst);
+            if !hazard.is_null() {
+                hazards.insert(hazard);
+            }
+            current = record.next.load(Ordering::Acquire);
+        }
+        let (protected, ready) = retired
+            .drain(..)
+            .partition(|retired| hazards.contains(&retired.ptr));
+        *retired = protected;
+        ready
+    }
+
+    fn free(ready: Vec<Retired>) {
+        for retired in ready {
+            // SAFETY: узел недостижим и не защищён, освобождается один раз
+            unsafe { (retired.drop)(retired.ptr) };
+        }
+    }
+}
+
+/// $ cargo +nightly miri test hazard_pointer
+/// $ cargo test hazard_pointer
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_protected_node_is_not_freed() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let shared = AtomicPtr::new(Box::into_raw(Box::new(Counted(dropped.clone()))));
+
+        let hazard = HazardPointer::new();
+        let node = hazard.protect(&shared);
+        shared.store(std::ptr::null_mut(), Ordering::SeqCst);
+        unsafe { retire(node) };
+        reclaim();
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+        hazard.reset();
+        reclaim();
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retired_on_exited_thread() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let counter = dropped.clone();
+        std::thread::spawn(move || {
+            for _ in 0..10 {
+                unsafe { retire(Box::into_raw(Box::new(Counted(counter.clone())))) };
+            }
+        })
+        .join()
+        .unwrap();
+        reclaim();
+        assert_eq!(dropped.load(Ordering::SeqCst), 10);
+    }
+}
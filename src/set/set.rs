@@ -1,49 +1,19 @@
 #![allow(dead_code)]
 // https://github.com/PacktPublishing/Hands-On-Data-Structures-and-Algorithms-with-Rust/blob/master/Chapter06/src/set.rs
-use std::boxed::Box;
-use std::cell::RefCell;
-use std::collections::BTreeMap;
+use crate::radix_trie::{self, RadixTrieMap};
+use std::ops::{BitAnd, BitOr, BitXor, RangeBounds, Sub};
 
 pub type NetworkDeviceStore = TrieSet<u8>;
-type Link<K> = Box<Node<K>>;
-
-struct Node<K>
-where
-    K: PartialEq + Clone + Ord,
-{
-    pub key: K,
-    next: BTreeMap<K, Link<K>>,
-    ends_here: bool,
-}
-
-impl<K> Node<K>
-where
-    K: PartialEq + Ord + Clone,
-{
-    pub fn new(key: K, ends_here: bool) -> Link<K> {
-        Box::new(Node {
-            key: key,
-            next: BTreeMap::new(),
-            ends_here: ends_here,
-        })
-    }
-}
-
-impl<K> PartialEq for Node<K>
-where
-    K: PartialEq + Clone + Ord,
-{
-    fn eq(&self, other: &Node<K>) -> bool {
-        self.key == other.key
-    }
-}
 
+/// Множество последовательностей на сжатом префиксном дереве `RadixTrieMap<K, ()>`:
+/// цепочки узлов с единственным потомком хранятся одной меткой. Пустой ключ не хранится
+#[derive(Clone)]
 pub struct TrieSet<K>
 where
     K: PartialEq + Clone + Ord,
 {
     pub length: u64,
-    root: BTreeMap<K, Link<K>>,
+    root: RadixTrieMap<K, ()>,
 }
 
 impl<K> TrieSet<K>
@@ -53,128 +23,211 @@ where
     pub fn new_empty() -> TrieSet<K> {
         TrieSet {
             length: 0,
-            root: BTreeMap::new(),
+            root: RadixTrieMap::new(),
         }
     }
 
     pub fn insert(&mut self, elements: &[K]) {
-        let mut path = elements.into_iter();
-
-        if let Some(start) = path.next() {
-            let mut n = self
-                .root
-                .entry(start.clone())
-                .or_insert(Node::new(start.clone(), false));
-            for c in path {
-                let tmp = n
-                    .next
-                    .entry(c.clone())
-                    .or_insert(Node::new(c.clone(), false));
-                n = tmp;
-            }
-            if !n.ends_here {
-                self.length += 1;
-            }
-            n.ends_here = true;
+        if !elements.is_empty() && self.root.insert(elements.iter().cloned(), ()).is_none() {
+            self.length += 1;
         }
     }
 
     pub fn contains(&self, key: &[K]) -> bool {
-        let mut path = key.into_iter();
-
-        if let Some(start) = path.next() {
-            self.root.get(&start).map_or(false, |mut n| {
-                for c in path {
-                    match n.next.get(&c) {
-                        Some(ref tmp) => n = tmp,
-                        None => break,
-                    }
-                }
-                n.ends_here
-            })
-        } else {
-            false
-        }
+        self.root.contains_key(key.iter().cloned())
+    }
+
+    /// Ключи `self`, которых нет в `other`
+    pub fn difference(&self, other: &TrieSet<K>) -> TrieSet<K> {
+        let mut set = self.clone();
+        set.difference_with(other);
+        set
+    }
+
+    pub fn union(&self, other: &TrieSet<K>) -> TrieSet<K> {
+        let mut set = self.clone();
+        set.union_with(other);
+        set
     }
 
-    pub fn difference(self, other: TrieSet<K>) -> TrieSet<K> {
-        let new = RefCell::new(TrieSet::new_empty());
-        self.walk(|k| {
-            if !other.contains(k) {
-                new.borrow_mut().insert(k)
-            }
-        });
-        new.into_inner()
-    }
-
-    pub fn union(self, other: TrieSet<K>) -> TrieSet<K> {
-        let new = RefCell::new(TrieSet::new_empty());
-        self.walk(|k| new.borrow_mut().insert(k));
-        other.walk(|k| new.borrow_mut().insert(k));
-        new.into_inner()
-    }
-
-    pub fn intersection(self, other: TrieSet<K>) -> TrieSet<K> {
-        let new = RefCell::new(TrieSet::new_empty());
-        if self.length < other.length {
-            self.walk(|k| {
-                if other.contains(k) {
-                    new.borrow_mut().insert(k)
-                }
-            });
-        } else {
-            other.walk(|k| {
-                if self.contains(k) {
-                    new.borrow_mut().insert(k)
-                }
-            });
+    /// Строится сразу из общих ветвей, без копирования `self` целиком
+    pub fn intersection(&self, other: &TrieSet<K>) -> TrieSet<K> {
+        let root = self.root.intersection(&other.root);
+        TrieSet {
+            length: root.len() as u64,
+            root,
         }
-        new.into_inner()
     }
 
+    /// Ключи, которые есть ровно в одном из множеств
+    pub fn symmetric_difference(&self, other: &TrieSet<K>) -> TrieSet<K> {
+        let mut set = self.clone();
+        set.symmetric_difference_with(other);
+        set
+    }
+
+    /// Деревья сливаются за один проход, ветви `other`, которых нет в `self`, копируются целиком
+    pub fn union_with(&mut self, other: &TrieSet<K>) {
+        self.root.union_with(&other.root);
+        self.length = self.root.len() as u64;
+    }
+
+    /// Ветви, которых нет в `other`, отбрасываются целиком
+    pub fn intersection_with(&mut self, other: &TrieSet<K>) {
+        self.root.intersection_with(&other.root);
+        self.length = self.root.len() as u64;
+    }
+
+    /// Обходятся только общие ветви, опустевшие ветви удаляются
+    pub fn difference_with(&mut self, other: &TrieSet<K>) {
+        self.root.difference_with(&other.root);
+        self.length = self.root.len() as u64;
+    }
+
+    pub fn symmetric_difference_with(&mut self, other: &TrieSet<K>) {
+        self.root.symmetric_difference_with(&other.root);
+        self.length = self.root.len() as u64;
+    }
+
+    /// Все ключи `self` есть в `other`
+    pub fn is_subset(&self, other: &TrieSet<K>) -> bool {
+        self.root.is_subset(&other.root)
+    }
+
+    pub fn is_superset(&self, other: &TrieSet<K>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Нет общих ключей
+    pub fn is_disjoint(&self, other: &TrieSet<K>) -> bool {
+        self.root.is_disjoint(&other.root)
+    }
+
+    /// Ключи в лексикографическом порядке
     pub fn walk(&self, callback: impl Fn(&[K]) -> ()) {
-        for r in self.root.values() {
-            self.walk_r(&r, &vec![], &callback);
+        self.root.walk(|key, _| callback(key));
+    }
+
+    /// Ключи в лексикографическом порядке, без копирования всего множества
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            inner: self.root.iter(),
         }
     }
 
-    fn walk_r(&self, node: &Link<K>, value: &Vec<K>, callback: &impl Fn(&[K]) -> ()) {
-        let mut v = value.clone();
-        v.push(node.key.clone());
-        for n in node.next.values() {
-            self.walk_r(&n, &v, callback);
+    /// Ключи, начинающиеся с `prefix`, в лексикографическом порядке
+    pub fn iter_prefix(&self, prefix: &[K]) -> Iter<'_, K> {
+        Iter {
+            inner: self.root.iter_prefix(prefix.iter().cloned()),
         }
-        if node.ends_here {
-            callback(&v);
+    }
+
+    /// Ключи из диапазона `range` в лексикографическом порядке.
+    /// Поддеревья целиком левее начала диапазона пропускаются, обход останавливается за его концом
+    pub fn range<R: RangeBounds<Vec<K>>>(&self, range: R) -> Iter<'_, K> {
+        Iter {
+            inner: self.root.range(range),
         }
     }
-    pub fn into_iter(self) -> SetIterator<K> {
-        let v: RefCell<Vec<Vec<K>>> = RefCell::new(vec![]);
-        self.walk(|n| v.borrow_mut().push(n.to_vec()));
-        SetIterator::new(v.into_inner(), 0)
+}
+
+impl<K> BitOr<&TrieSet<K>> for &TrieSet<K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    type Output = TrieSet<K>;
+
+    fn bitor(self, other: &TrieSet<K>) -> TrieSet<K> {
+        self.union(other)
+    }
+}
+
+impl<K> BitAnd<&TrieSet<K>> for &TrieSet<K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    type Output = TrieSet<K>;
+
+    fn bitand(self, other: &TrieSet<K>) -> TrieSet<K> {
+        self.intersection(other)
     }
 }
 
-pub struct SetIterator<K>
+impl<K> Sub<&TrieSet<K>> for &TrieSet<K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    type Output = TrieSet<K>;
+
+    fn sub(self, other: &TrieSet<K>) -> TrieSet<K> {
+        self.difference(other)
+    }
+}
+
+impl<K> BitXor<&TrieSet<K>> for &TrieSet<K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    type Output = TrieSet<K>;
+
+    fn bitxor(self, other: &TrieSet<K>) -> TrieSet<K> {
+        self.symmetric_difference(other)
+    }
+}
+
+/// Ленивый обход ключей, см. `radix_trie::Iter`
+pub struct Iter<'a, K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    inner: radix_trie::Iter<'a, K, ()>,
+}
+
+impl<'a, K> Iterator for Iter<'a, K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    type Item = Vec<K>;
+
+    fn next(&mut self) -> Option<Vec<K>> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K> IntoIterator for &'a TrieSet<K>
 where
     K: PartialEq + Clone + Ord,
 {
-    data: Vec<Vec<K>>,
-    last_index: usize,
+    type Item = Vec<K>;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Iter<'a, K> {
+        self.iter()
+    }
 }
 
-impl<K> SetIterator<K>
+impl<K> IntoIterator for TrieSet<K>
 where
     K: PartialEq + Clone + Ord,
 {
-    fn new(data: Vec<Vec<K>>, start_at: usize) -> SetIterator<K> {
+    type Item = Vec<K>;
+    type IntoIter = SetIterator<K>;
+
+    fn into_iter(self) -> SetIterator<K> {
         SetIterator {
-            data: data,
-            last_index: start_at,
+            inner: self.root.into_iter(),
         }
     }
 }
 
+/// Поглощающий ленивый обход: узлы освобождаются по мере продвижения
+pub struct SetIterator<K>
+where
+    K: PartialEq + Clone + Ord,
+{
+    inner: radix_trie::IntoIter<K, ()>,
+}
+
 impl<K> Iterator for SetIterator<K>
 where
     K: PartialEq + Clone + Ord,
@@ -182,8 +235,184 @@ where
     type Item = Vec<K>;
 
     fn next(&mut self) -> Option<Vec<K>> {
-        let result = self.data.get(self.last_index);
-        self.last_index += 1;
-        result.cloned()
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+/// $ cargo test set::tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Bound;
+
+    fn set(keys: &[&str]) -> TrieSet<u8> {
+        let mut set = TrieSet::new_empty();
+        for key in keys {
+            set.insert(key.as_bytes());
+        }
+        set
+    }
+
+    fn strings(keys: impl Iterator<Item = Vec<u8>>) -> Vec<String> {
+        keys.map(|key| String::from_utf8(key).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_iter_order() {
+        let set = set(&["b", "abc", "a", "ab", "abd", "ac"]);
+        let expected = vec!["a", "ab", "abc", "abd", "ac", "b"];
+        assert_eq!(strings(set.iter()), expected);
+        assert_eq!(strings((&set).into_iter()), expected);
+        assert_eq!(strings(set.into_iter()), expected);
+    }
+
+    #[test]
+    fn test_insert_contains() {
+        let mut set = set(&["ab", "abcd", "b"]);
+        set.insert(b"");
+        set.insert(b"ab");
+        assert_eq!(set.length, 3);
+        assert!(set.contains(b"ab") && set.contains(b"abcd") && set.contains(b"b"));
+        // префиксы и продолжения хранимых ключей в множество не входят
+        assert!(!set.contains(b"a") && !set.contains(b"abc") && !set.contains(b"abx"));
+        assert!(!set.contains(b"") && !set.contains(b"bc"));
+    }
+
+    #[test]
+    fn test_iter_prefix() {
+        let set = set(&["b", "abc", "a", "ab", "abd", "ac"]);
+        assert_eq!(strings(set.iter_prefix(b"ab")), vec!["ab", "abc", "abd"]);
+        assert_eq!(strings(set.iter_prefix(b"b")), vec!["b"]);
+        assert!(set.iter_prefix(b"abx").next().is_none());
+        assert_eq!(set.iter_prefix(b"").count(), 6);
+    }
+
+    #[test]
+    fn test_range() {
+        let set = set(&["b", "abc", "a", "ab", "abd", "ac", "ba", "c"]);
+        assert_eq!(
+            strings(set.range(b"ab".to_vec()..b"b".to_vec())),
+            vec!["ab", "abc", "abd", "ac"]
+        );
+        assert_eq!(
+            strings(set.range(b"abc".to_vec()..=b"b".to_vec())),
+            vec!["abc", "abd", "ac", "b"]
+        );
+        assert_eq!(strings(set.range(b"b".to_vec()..)), vec!["b", "ba", "c"]);
+        assert_eq!(strings(set.range(..b"ab".to_vec())), vec!["a"]);
+        assert_eq!(
+            strings(
+                set.range((Bound::Excluded(b"ab".to_vec()), Bound::Unbounded))
+                    .take(2)
+            ),
+            vec!["abc", "abd"]
+        );
+        assert!(set.range(b"d".to_vec()..).next().is_none());
+    }
+
+    #[test]
+    fn test_range_random() {
+        use rand::Rng;
+        use std::collections::BTreeSet;
+        let mut rng = rand::thread_rng();
+        let mut set = TrieSet::new_empty();
+        let mut model = BTreeSet::new();
+        for _ in 0..500 {
+            let len = rng.gen_range(1..5);
+            let key: Vec<u8> = (0..len).map(|_| rng.gen_range(0..4)).collect();
+            set.insert(&key);
+            model.insert(key);
+        }
+        assert!(set.iter().eq(model.iter().cloned()));
+        for _ in 0..50 {
+            let a: Vec<u8> = (0..rng.gen_range(0..4))
+                .map(|_| rng.gen_range(0..4))
+                .collect();
+            let b: Vec<u8> = (0..rng.gen_range(0..4))
+                .map(|_| rng.gen_range(0..4))
+                .collect();
+            let (a, b) = if a <= b { (a, b) } else { (b, a) };
+            assert!(set
+                .range(a.clone()..b.clone())
+                .eq(model.range(a.clone()..b.clone()).cloned()));
+            assert!(set
+                .range(a.clone()..=b.clone())
+                .eq(model.range(a..=b).cloned()));
+        }
+    }
+
+    fn random_set(rng: &mut impl rand::Rng) -> (TrieSet<u8>, std::collections::BTreeSet<Vec<u8>>) {
+        let mut set = TrieSet::new_empty();
+        let mut model = std::collections::BTreeSet::new();
+        for _ in 0..rng.gen_range(0..60) {
+            let key: Vec<u8> = (0..rng.gen_range(1..4))
+                .map(|_| rng.gen_range(0..3))
+                .collect();
+            set.insert(&key);
+            model.insert(key);
+        }
+        (set, model)
+    }
+
+    fn check(set: &TrieSet<u8>, model: Vec<Vec<u8>>) {
+        assert_eq!(set.length as usize, model.len());
+        assert!(set.iter().eq(model.into_iter()));
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let a = set(&["ab", "abc", "b", "ca"]);
+        let b = set(&["abc", "b", "bd", "c"]);
+        assert_eq!(
+            strings(a.union(&b).into_iter()),
+            vec!["ab", "abc", "b", "bd", "c", "ca"]
+        );
+        assert_eq!(strings((&a & &b).into_iter()), vec!["abc", "b"]);
+        assert_eq!(strings((&a - &b).into_iter()), vec!["ab", "ca"]);
+        assert_eq!(strings((&a ^ &b).into_iter()), vec!["ab", "bd", "c", "ca"]);
+
+        assert!(!a.is_disjoint(&b));
+        assert!(a.is_disjoint(&set(&["a", "abcd", "bd"])));
+        assert!(set(&["abc", "c"]).is_subset(&b));
+        assert!(!set(&["ab"]).is_subset(&b));
+        assert!(a.is_superset(&set(&[])));
+
+        let mut c = a.clone();
+        c.difference_with(&a);
+        assert_eq!(c.length, 0);
+        assert!(c.root.is_empty());
+    }
+
+    #[test]
+    fn test_set_algebra_random() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let (a, model_a) = random_set(&mut rng);
+            let (b, model_b) = random_set(&mut rng);
+
+            check(&(&a | &b), model_a.union(&model_b).cloned().collect());
+            check(
+                &(&a & &b),
+                model_a.intersection(&model_b).cloned().collect(),
+            );
+            check(&(&a - &b), model_a.difference(&model_b).cloned().collect());
+            check(
+                &(&a ^ &b),
+                model_a.symmetric_difference(&model_b).cloned().collect(),
+            );
+            assert_eq!(a.is_subset(&b), model_a.is_subset(&model_b));
+            assert_eq!(a.is_disjoint(&b), model_a.is_disjoint(&model_b));
+
+            let mut c = a.clone();
+            c.intersection_with(&b);
+            check(&c, model_a.intersection(&model_b).cloned().collect());
+            c.union_with(&b);
+            check(&c, model_b.iter().cloned().collect());
+            c.symmetric_difference_with(&a);
+            check(
+                &c,
+                model_a.symmetric_difference(&model_b).cloned().collect(),
+            );
+        }
     }
 }
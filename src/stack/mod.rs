@@ -3,4 +3,6 @@ pub mod stack_linked_list;
 pub mod stack_linked_list_2;
 pub mod stack_linked_list_persistent;
 pub mod stack_linked_list_std;
+pub mod stack_lock_free;
+pub mod stack_min_max;
 pub mod stack_vec;
@@ -1,9 +1,9 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-pub use ds_stack_array::Stack;
+pub use ds_stack_array::{ArrayStack, Stack};
 
-/// Стек на массивах, фиксированного размера и значения не затираются, возвращает ссылки на значения
+/// Стек на массиве фиксированной ёмкости `N` без выделения памяти в куче, `pop` возвращает значение
 /// Визуализация https://www.cs.usfca.edu/~galles/visualization/StackArray.html
 ///
 /// Время поиска `O(n)`, время удаления pop и добавления push и просмотра вершины peek `O(1)`
@@ -15,64 +15,134 @@ pub use ds_stack_array::Stack;
 /// IsEmpty: проверьте, пуст ли стек
 /// IsFull: проверьте, заполнен ли стек
 /// Peek: получить значение верхнего элемента, не удаляя его.
+///
+/// Модуль использует только `core`, поэтому его можно перенести в `#![no_std]` прошивку без изменений.
 mod ds_stack_array {
-    use core::fmt::Debug;
+    use core::fmt::{self, Debug};
+    use core::mem::MaybeUninit;
+    use core::ptr;
+
+    /// Ячейки `0..len` инициализированы, остальные - нет
+    pub struct ArrayStack<T, const N: usize> {
+        data: [MaybeUninit<T>; N],
+        len: usize,
+    }
 
-    #[derive(Debug)]
-    pub struct Stack<T: Debug> {
-        data: [Option<T>; N],
-        index: usize,
+    /// Стек прежнего фиксированного размера
+    pub type Stack<T> = ArrayStack<T, 5>;
+
+    impl<T, const N: usize> Default for ArrayStack<T, N> {
+        fn default() -> Self {
+            Self::new()
+        }
     }
-    const N: usize = 5;
 
-    impl<T: Debug> Stack<T> {
-        pub fn new() -> Self {
-            Stack::<T> {
-                data: [None, None, None, None, None],
-                index: 0,
+    impl<T, const N: usize> ArrayStack<T, N> {
+        pub const fn new() -> Self {
+            Self {
+                data: [const { MaybeUninit::uninit() }; N],
+                len: 0,
             }
         }
 
+        /// Возвращает `false` и отбрасывает элемент, если стек заполнен
         pub fn push(&mut self, item: T) -> bool {
+            self.try_push(item).is_ok()
+        }
+
+        /// Возвращает элемент обратно, если стек заполнен
+        pub fn try_push(&mut self, item: T) -> Result<(), T> {
             if self.is_full() {
-                return false;
+                return Err(item);
             }
-
-            self.data[self.index] = Some(item);
-            self.index += 1;
-            return true;
+            self.data[self.len].write(item);
+            self.len += 1;
+            Ok(())
         }
 
-        pub fn pop(&mut self) -> Option<&T> {
+        pub fn pop(&mut self) -> Option<T> {
             if self.is_empty() {
                 return None;
             }
-            self.index -= 1;
-            self.data[self.index].as_ref()
+            self.len -= 1;
+            // SAFETY: ячейка len была инициализирована и теперь исключена из стека
+            Some(unsafe { self.data[self.len].assume_init_read() })
         }
 
         pub fn peek(&self) -> Option<&T> {
-            if !self.data.is_empty() && self.index > 0 {
-                let ret = self.data[self.index - 1].as_ref().unwrap();
-                return Some::<&T>(ret);
-            }
-            return None;
+            self.as_slice().last()
         }
 
         pub fn peek_mut(&mut self) -> Option<&mut T> {
-            if !self.data.is_empty() && self.index > 0 {
-                let ret = self.data[self.index - 1].as_mut().unwrap();
-                return Some::<&mut T>(ret);
-            }
-            return None;
+            self.as_mut_slice().last_mut()
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        pub const fn capacity(&self) -> usize {
+            N
         }
 
         pub fn is_full(&self) -> bool {
-            self.index >= N
+            self.len >= N
         }
 
         pub fn is_empty(&self) -> bool {
-            self.index == 0
+            self.len == 0
+        }
+
+        pub fn clear(&mut self) {
+            let initialized = self.len;
+            self.len = 0;
+            // SAFETY: ячейки 0..initialized инициализированы, len обнулён до drop на случай паники
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    self.data.as_mut_ptr() as *mut T,
+                    initialized,
+                ));
+            }
+        }
+
+        /// Элементы от дна к вершине
+        pub fn as_slice(&self) -> &[T] {
+            // SAFETY: ячейки 0..len инициализированы
+            unsafe { &*(ptr::slice_from_raw_parts(self.data.as_ptr() as *const T, self.len)) }
+        }
+
+        pub fn as_mut_slice(&mut self) -> &mut [T] {
+            // SAFETY: ячейки 0..len инициализированы
+            unsafe {
+                &mut *(ptr::slice_from_raw_parts_mut(self.data.as_mut_ptr() as *mut T, self.len))
+            }
+        }
+
+        /// Обход от вершины к дну
+        pub fn iter(&self) -> core::iter::Rev<core::slice::Iter<'_, T>> {
+            self.as_slice().iter().rev()
+        }
+    }
+
+    impl<T, const N: usize> Drop for ArrayStack<T, N> {
+        fn drop(&mut self) {
+            self.clear();
+        }
+    }
+
+    impl<T: Clone, const N: usize> Clone for ArrayStack<T, N> {
+        fn clone(&self) -> Self {
+            let mut stack = Self::new();
+            for item in self.as_slice() {
+                stack.push(item.clone());
+            }
+            stack
+        }
+    }
+
+    impl<T: Debug, const N: usize> Debug for ArrayStack<T, N> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.as_slice()).finish()
         }
     }
 }
@@ -93,16 +163,16 @@ mod tests {
         assert_eq!(false, stack.push(6));
 
         assert_eq!(Some(&5), stack.peek());
-        assert_eq!(Some(&5), stack.pop());
-        assert_eq!(Some(&4), stack.pop());
-        assert_eq!(Some(&3), stack.pop());
-        assert_eq!(Some(&2), stack.pop());
-        assert_eq!(Some(&1), stack.pop());
+        assert_eq!(Some(5), stack.pop());
+        assert_eq!(Some(4), stack.pop());
+        assert_eq!(Some(3), stack.pop());
+        assert_eq!(Some(2), stack.pop());
+        assert_eq!(Some(1), stack.pop());
         assert_eq!(None, stack.pop());
 
         stack.push(1);
         assert_eq!(Some(&1), stack.peek());
-        assert_eq!(Some(&1), stack.pop());
+        assert_eq!(Some(1), stack.pop());
         assert!(stack.is_empty());
     }
 
@@ -111,7 +181,7 @@ mod tests {
         let mut stack = Stack::<i32>::new();
         stack.push(8);
         let first = stack.pop();
-        assert_eq!(Some(&8), first);
+        assert_eq!(Some(8), first);
     }
 
     #[test]
@@ -141,6 +211,46 @@ mod tests {
         if let Some(v) = first {
             *v = 99;
         }
-        assert_eq!(Some(&99), stack.pop());
+        assert_eq!(Some(99), stack.pop());
+    }
+
+    #[test]
+    fn test_try_push() {
+        let mut stack: ArrayStack<String, 2> = ArrayStack::new();
+        assert_eq!(stack.try_push("a".to_string()), Ok(()));
+        assert_eq!(stack.try_push("b".to_string()), Ok(()));
+        assert_eq!(stack.try_push("c".to_string()), Err("c".to_string()));
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.capacity(), 2);
+        assert_eq!(stack.iter().collect::<Vec<_>>(), vec!["b", "a"]);
+        assert_eq!(format!("{:?}", stack), r#"["a", "b"]"#);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut stack: ArrayStack<i32, 0> = ArrayStack::new();
+        assert!(stack.is_full());
+        assert_eq!(stack.try_push(1), Err(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_drop_only_initialized() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        {
+            let mut stack: ArrayStack<Rc<()>, 8> = ArrayStack::new();
+            for _ in 0..5 {
+                stack.push(counter.clone());
+            }
+            let popped = stack.pop();
+            assert_eq!(Rc::strong_count(&counter), 6);
+            drop(popped);
+            let cloned = stack.clone();
+            assert_eq!(Rc::strong_count(&counter), 9);
+            drop(cloned);
+            assert_eq!(Rc::strong_count(&counter), 5);
+        }
+        assert_eq!(Rc::strong_count(&counter), 1);
     }
 }
@@ -1,34 +1,90 @@
 #![allow(unused)]
 
-pub use ds_stack_persistent::List;
+pub use ds_stack_persistent::{ArcList, ArcPointer, Iter, List, RcPointer, SharedPointer};
 ///! [Реализация неизменяемого stack на основе linked list](https://rust-unofficial.github.io/too-many-lists/third-final.html)
+///!
+///! Тип указателя на узлы задаётся параметром `P: SharedPointer`:
+///! `RcPointer` (по умолчанию) для одного потока, `ArcPointer` - чтобы делить версии между потоками.
 mod ds_stack_persistent {
+    use std::ops::Deref;
     use std::rc::Rc;
+    use std::sync::Arc;
 
-    pub struct List<T> {
-        head: Option<Rc<Node<T>>>,
+    /// Семейство разделяемых указателей с подсчётом ссылок
+    pub trait SharedPointer {
+        type Pointer<U>: Deref<Target = U> + Clone;
+
+        fn new<U>(value: U) -> Self::Pointer<U>;
+
+        /// Забирает значение, если это последняя ссылка, иначе только уменьшает счётчик.
+        /// Из нескольких одновременных вызовов на одно значение `Some` получит ровно один
+        fn into_inner<U>(pointer: Self::Pointer<U>) -> Option<U>;
     }
 
-    struct Node<T> {
+    pub struct RcPointer;
+
+    impl SharedPointer for RcPointer {
+        type Pointer<U> = Rc<U>;
+
+        fn new<U>(value: U) -> Rc<U> {
+            Rc::new(value)
+        }
+
+        fn into_inner<U>(pointer: Rc<U>) -> Option<U> {
+            Rc::into_inner(pointer)
+        }
+    }
+
+    pub struct ArcPointer;
+
+    impl SharedPointer for ArcPointer {
+        type Pointer<U> = Arc<U>;
+
+        fn new<U>(value: U) -> Arc<U> {
+            Arc::new(value)
+        }
+
+        fn into_inner<U>(pointer: Arc<U>) -> Option<U> {
+            Arc::into_inner(pointer)
+        }
+    }
+
+    /// Список, версии которого можно передавать между потоками
+    pub type ArcList<T> = List<T, ArcPointer>;
+
+    pub struct List<T, P: SharedPointer = RcPointer> {
+        head: Link<T, P>,
+    }
+
+    type Link<T, P> = Option<<P as SharedPointer>::Pointer<Node<T, P>>>;
+
+    struct Node<T, P: SharedPointer> {
         elem: T,
-        next: Option<Rc<Node<T>>>,
+        next: Link<T, P>,
     }
 
-    impl<T> List<T> {
+    impl<T, P: SharedPointer> Default for List<T, P> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, P: SharedPointer> List<T, P> {
+        /// Пустой список для любого `P`, например `ArcList::new()`
         pub fn new() -> Self {
             List { head: None }
         }
 
-        pub fn prepend(&self, elem: T) -> List<T> {
+        pub fn prepend(&self, elem: T) -> List<T, P> {
             List {
-                head: Some(Rc::new(Node {
+                head: Some(P::new(Node {
                     elem: elem,
                     next: self.head.clone(),
                 })),
             }
         }
 
-        pub fn tail(&self) -> List<T> {
+        pub fn tail(&self) -> List<T, P> {
             List {
                 head: self.head.as_ref().and_then(|node| node.next.clone()),
             }
@@ -38,31 +94,37 @@ mod ds_stack_persistent {
             self.head.as_ref().map(|node| &node.elem)
         }
 
-        pub fn iter(&self) -> Iter<'_, T> {
+        pub fn iter(&self) -> Iter<'_, T, P> {
             Iter {
                 next: self.head.as_deref(),
             }
         }
     }
 
-    impl<T> Drop for List<T> {
+    /// Копия разделяет все узлы, O(1)
+    impl<T, P: SharedPointer> Clone for List<T, P> {
+        fn clone(&self) -> Self {
+            List {
+                head: self.head.clone(),
+            }
+        }
+    }
+
+    impl<T, P: SharedPointer> Drop for List<T, P> {
         fn drop(&mut self) {
+            // узел, на который ещё есть ссылки, освободит тот, кто отпустит последнюю
             let mut head = self.head.take();
             while let Some(node) = head {
-                if let Ok(mut node) = Rc::try_unwrap(node) {
-                    head = node.next.take();
-                } else {
-                    break;
-                }
+                head = P::into_inner(node).and_then(|mut node| node.next.take());
             }
         }
     }
 
-    pub struct Iter<'a, T> {
-        next: Option<&'a Node<T>>,
+    pub struct Iter<'a, T, P: SharedPointer = RcPointer> {
+        next: Option<&'a Node<T, P>>,
     }
 
-    impl<'a, T> Iterator for Iter<'a, T> {
+    impl<'a, T, P: SharedPointer> Iterator for Iter<'a, T, P> {
         type Item = &'a T;
 
         fn next(&mut self) -> Option<Self::Item> {
@@ -77,11 +139,11 @@ mod ds_stack_persistent {
 /// $ cargo test stack_linked_list_persistent
 #[cfg(test)]
 mod test {
-    use super::List;
+    use super::{ArcList, List};
 
     #[test]
     fn basics() {
-        let list = List::new();
+        let list: List<i32> = List::new();
         assert_eq!(list.head(), None);
 
         let list = list.prepend(1).prepend(2).prepend(3).prepend(4).prepend(5);
@@ -109,11 +171,62 @@ mod test {
 
     #[test]
     fn iter() {
-        let list = List::new().prepend(1).prepend(2).prepend(3);
+        let list: List<i32> = List::new().prepend(1).prepend(2).prepend(3);
 
         let mut iter = list.iter();
         assert_eq!(iter.next(), Some(&3));
         assert_eq!(iter.next(), Some(&2));
         assert_eq!(iter.next(), Some(&1));
     }
+
+    #[test]
+    fn long_list_drop() {
+        let mut list: List<i32> = List::new();
+        for i in 0..200_000 {
+            list = list.prepend(i);
+        }
+        let shared = list.tail();
+        drop(list);
+        assert_eq!(shared.head(), Some(&199_998));
+    }
+
+    #[test]
+    fn arc_shared_between_threads() {
+        let history: ArcList<i32> = (1..=100).fold(ArcList::new(), |list, i| list.prepend(i));
+        let sums: Vec<i32> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|worker| {
+                    let version = history.prepend(worker);
+                    scope.spawn(move || version.iter().sum::<i32>())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(sums, vec![5050, 5051, 5052, 5053]);
+        assert_eq!(history.head(), Some(&100));
+    }
+
+    /// Два потока одновременно отпускают общий список: узлы освобождает ровно один из них,
+    /// циклом. Если обе попытки забрать узел не удались, последний `Arc` рекурсивно освободил бы
+    /// весь список и переполнил стек потока
+    #[test]
+    fn arc_concurrent_drop() {
+        use std::sync::Barrier;
+        for _ in 0..4 {
+            let shared: ArcList<u32> =
+                (0..1_000_000).fold(ArcList::new(), |list, i| list.prepend(i));
+            // обе версии начинаются с одного узла, потоки сталкиваются на первом же шаге
+            let versions = [shared.clone(), shared];
+            let barrier = Barrier::new(versions.len());
+            std::thread::scope(|scope| {
+                for version in versions {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        barrier.wait();
+                        drop(version);
+                    });
+                }
+            });
+        }
+    }
 }
@@ -0,0 +1,238 @@
+#![allow(dead_code)]
+
+//! Lock-free стек Трайбера: вершина меняется через CAS, поэтому стек можно разделять между потоками.
+//! Снятые узлы освобождаются через указатели опасности (`hazard_pointer`),
+//! что исключает чтение освобождённой памяти и проблему ABA.
+//!
+//! API повторяет `stack_linked_list::List`, но `push`/`pop` принимают `&self`.
+//! `peek` по `&self` возвращает копию вершины: ссылку отдать нельзя, узел может снять другой поток.
+pub use ds_stack_lock_free::{ConcurrentStack, IntoIter};
+mod ds_stack_lock_free {
+    use crate::hazard_pointer::{retire, HazardPointer};
+    use std::mem::ManuallyDrop;
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+
+    pub struct ConcurrentStack<T: Send> {
+        head: AtomicPtr<Node<T>>,
+    }
+
+    struct Node<T> {
+        // значение забирает `pop`, сам узел освобождается позже без повторного drop
+        elem: ManuallyDrop<T>,
+        next: *mut Node<T>,
+    }
+
+    // SAFETY: значения передаются между потоками только вместе с владением T: Send
+    unsafe impl<T: Send> Send for Node<T> {}
+    unsafe impl<T: Send> Send for ConcurrentStack<T> {}
+    unsafe impl<T: Send> Sync for ConcurrentStack<T> {}
+
+    impl<T: Send> Default for ConcurrentStack<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Send> ConcurrentStack<T> {
+        pub fn new() -> Self {
+            ConcurrentStack {
+                head: AtomicPtr::new(ptr::null_mut()),
+            }
+        }
+
+        pub fn push(&self, elem: T) {
+            let node = Box::into_raw(Box::new(Node {
+                elem: ManuallyDrop::new(elem),
+                next: ptr::null_mut(),
+            }));
+            let mut head = self.head.load(Ordering::Relaxed);
+            loop {
+                // SAFETY: узел ещё не опубликован и принадлежит этому потоку
+                unsafe { (*node).next = head };
+                match self.head.compare_exchange_weak(
+                    head,
+                    node,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return,
+                    Err(actual) => head = actual,
+                }
+            }
+        }
+
+        pub fn pop(&self) -> Option<T> {
+            let hazard = HazardPointer::new();
+            loop {
+                let head = hazard.protect(&self.head);
+                if head.is_null() {
+                    return None;
+                }
+                // SAFETY: head защищён указателем опасности, next после публикации не меняется
+                let next = unsafe { (*head).next };
+                if self
+                    .head
+                    .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    // SAFETY: узел снят этим потоком, значение забирается один раз
+                    let elem = unsafe { ptr::read(&*(*head).elem) };
+                    hazard.reset();
+                    // SAFETY: узел недостижим из стека
+                    unsafe { retire(head) };
+                    return Some(elem);
+                }
+            }
+        }
+
+        /// Копия вершины. Для значений без `Copy` используйте `peek_mut` или храните `Arc<T>`
+        pub fn peek(&self) -> Option<T>
+        where
+            T: Copy,
+        {
+            let hazard = HazardPointer::new();
+            let head = hazard.protect(&self.head);
+            // SAFETY: head защищён указателем опасности, побитовое чтение Copy-значения безопасно
+            (!head.is_null()).then(|| unsafe { *(*head).elem })
+        }
+
+        pub fn peek_mut(&mut self) -> Option<&mut T> {
+            // SAFETY: &mut self - других потоков нет
+            unsafe { self.head.get_mut().as_mut() }.map(|node| &mut *node.elem)
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.head.load(Ordering::Acquire).is_null()
+        }
+    }
+
+    impl<T: Send> IntoIterator for ConcurrentStack<T> {
+        type Item = T;
+        type IntoIter = IntoIter<T>;
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self)
+        }
+    }
+
+    impl<T: Send> Drop for ConcurrentStack<T> {
+        fn drop(&mut self) {
+            let mut current = *self.head.get_mut();
+            while !current.is_null() {
+                // SAFETY: &mut self - других потоков нет, узлы стека ещё не переданы в retire
+                let mut node = unsafe { Box::from_raw(current) };
+                unsafe { ManuallyDrop::drop(&mut node.elem) };
+                current = node.next;
+            }
+        }
+    }
+
+    pub struct IntoIter<T: Send>(ConcurrentStack<T>);
+
+    impl<T: Send> Iterator for IntoIter<T> {
+        type Item = T;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.pop()
+        }
+    }
+}
+
+/// $ cargo test stack_lock_free
+#[cfg(test)]
+mod test {
+    use super::ConcurrentStack;
+    use std::sync::Arc;
+
+    #[test]
+    fn basics() {
+        let stack = ConcurrentStack::new();
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+
+        stack.push(4);
+        stack.push(5);
+        assert_eq!(stack.pop(), Some(5));
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn peek() {
+        let mut stack = ConcurrentStack::new();
+        assert_eq!(stack.peek(), None);
+        assert_eq!(stack.peek_mut(), None);
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(2));
+
+        if let Some(value) = stack.peek_mut() {
+            *value = 42;
+        }
+        assert_eq!(stack.pop(), Some(42));
+        assert_eq!(stack.peek(), Some(1));
+    }
+
+    #[test]
+    fn into_iter() {
+        let stack = ConcurrentStack::new();
+        stack.push("a".to_string());
+        stack.push("b".to_string());
+
+        let mut iter = stack.into_iter();
+        assert_eq!(iter.next(), Some("b".to_string()));
+        assert_eq!(iter.next(), Some("a".to_string()));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn drop_remaining() {
+        let counter = Arc::new(());
+        {
+            let stack = ConcurrentStack::new();
+            for _ in 0..10 {
+                stack.push(counter.clone());
+            }
+            drop(stack.pop());
+            assert_eq!(Arc::strong_count(&counter), 10);
+        }
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn concurrent_push_pop() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 10_000;
+        let stack = ConcurrentStack::new();
+
+        let popped: Vec<Vec<usize>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let stack = &stack;
+                    scope.spawn(move || {
+                        let mut popped = vec![];
+                        for i in 0..PER_THREAD {
+                            stack.push(thread * PER_THREAD + i);
+                            if i % 2 == 1 {
+                                popped.extend(stack.pop());
+                            }
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut all: Vec<usize> = popped.into_iter().flatten().collect();
+        all.extend(stack.into_iter());
+        all.sort();
+        assert_eq!(all, (0..THREADS * PER_THREAD).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,143 @@
+#![allow(dead_code)]
+
+//! Стек с минимумом и максимумом за O(1).
+//!
+//! Рядом с основным `stack_vec::Stack` хранятся два вспомогательных стека позиций:
+//! позиция попадает в `mins`, если её элемент не больше текущего минимума, и в `maxs`,
+//! если не меньше текущего максимума. При `pop` позиция снимается со вспомогательного стека,
+//! если это была его вершина. Копии элементов не нужны, достаточно `T: Ord`.
+pub use ds_stack_min_max::MinMaxStack;
+mod ds_stack_min_max {
+    use crate::stack_vec::Stack;
+
+    #[derive(Debug)]
+    pub struct MinMaxStack<T: Ord> {
+        data: Stack<T>,
+        mins: Stack<usize>,
+        maxs: Stack<usize>,
+    }
+
+    impl<T: Ord> Default for MinMaxStack<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Ord> MinMaxStack<T> {
+        pub fn new() -> Self {
+            Self {
+                data: Stack::new(),
+                mins: Stack::new(),
+                maxs: Stack::new(),
+            }
+        }
+
+        pub fn push(&mut self, item: T) {
+            let index = self.data.len();
+            if self.min().is_none_or(|min| &item <= min) {
+                self.mins.push(index);
+            }
+            if self.max().is_none_or(|max| &item >= max) {
+                self.maxs.push(index);
+            }
+            self.data.push(item);
+        }
+
+        pub fn pop(&mut self) -> Option<T> {
+            let item = self.data.pop()?;
+            let index = self.data.len();
+            if self.mins.peek() == Some(&index) {
+                self.mins.pop();
+            }
+            if self.maxs.peek() == Some(&index) {
+                self.maxs.pop();
+            }
+            Some(item)
+        }
+
+        pub fn peek(&self) -> Option<&T> {
+            self.data.peek()
+        }
+
+        pub fn min(&self) -> Option<&T> {
+            self.mins.peek().and_then(|index| self.data.get(*index))
+        }
+
+        pub fn max(&self) -> Option<&T> {
+            self.maxs.peek().and_then(|index| self.data.get(*index))
+        }
+
+        /// Элемент по позиции от дна стека, 0 - самый нижний
+        pub fn get(&self, index: usize) -> Option<&T> {
+            self.data.get(index)
+        }
+
+        pub fn len(&self) -> usize {
+            self.data.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.data.is_empty()
+        }
+    }
+}
+
+/// $ cargo test --lib stack_min_max
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success() {
+        let mut stack = MinMaxStack::new();
+        assert_eq!(stack.min(), None);
+        assert_eq!(stack.max(), None);
+
+        let mut summary = vec![];
+        for item in [3, 5, 1, 1, 7, 2] {
+            stack.push(item);
+            summary.push((*stack.min().unwrap(), *stack.max().unwrap()));
+        }
+        assert_eq!(
+            summary,
+            vec![(3, 3), (3, 5), (1, 5), (1, 5), (1, 7), (1, 7)]
+        );
+        assert_eq!(stack.peek(), Some(&2));
+
+        let mut summary = vec![];
+        while let Some(item) = stack.pop() {
+            summary.push((item, stack.min().copied(), stack.max().copied()));
+        }
+        assert_eq!(
+            summary,
+            vec![
+                (2, Some(1), Some(7)),
+                (7, Some(1), Some(5)),
+                (1, Some(1), Some(5)),
+                (1, Some(3), Some(5)),
+                (5, Some(3), Some(3)),
+                (3, None, None)
+            ]
+        );
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut stack = MinMaxStack::new();
+        let mut model: Vec<u32> = vec![];
+        for _ in 0..2000 {
+            if rng.gen_bool(0.6) {
+                let item = rng.gen_range(0..100);
+                stack.push(item);
+                model.push(item);
+            } else {
+                assert_eq!(stack.pop(), model.pop());
+            }
+            assert_eq!(stack.min(), model.iter().min());
+            assert_eq!(stack.max(), model.iter().max());
+        }
+    }
+}
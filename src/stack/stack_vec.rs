@@ -47,6 +47,15 @@ mod ds_stack {
         pub fn is_empty(&self) -> bool {
             self.data.is_empty()
         }
+
+        pub fn len(&self) -> usize {
+            self.data.len()
+        }
+
+        /// Элемент по позиции от дна стека, 0 - самый нижний
+        pub fn get(&self, index: usize) -> Option<&T> {
+            self.data.get(index)
+        }
     }
 }
 
@@ -0,0 +1,447 @@
+#![allow(dead_code)]
+
+//! Автомат Ахо-Корасик: поиск множества образцов за один проход по тексту.
+//!
+//! Образцы складываются в префиксное дерево (состояния в `Vec`, потомки в `BTreeMap`),
+//! затем обходом в ширину каждому состоянию проставляется ссылка неудачи - на состояние
+//! самого длинного собственного суффикса, который тоже есть в дереве, и словарная ссылка -
+//! на ближайшее по ссылкам неудачи состояние, где заканчивается образец.
+//!
+//! Элементы текста - любые `K: Ord + Clone`: байты (`b"log".iter().copied()`) или символы (`"log".chars()`).
+//! Позиции совпадений считаются в элементах текста. Номер образца - его порядковый номер в `new`.
+//! Пустые образцы не сообщаются.
+//!
+//! - `find_overlapping` лениво выдаёт все совпадения, в том числе пересекающиеся;
+//! - `find_leftmost_longest` лениво выдаёт непересекающиеся совпадения: самое левое начало, из них самое длинное.
+//!   После совпадения поиск продолжается с его конца, поэтому последние прочитанные элементы текста
+//!   хранятся в буфере не длиннее самого длинного образца.
+pub use ds_aho_corasick::{AhoCorasick, FindLeftmostLongest, FindOverlapping, Match};
+mod ds_aho_corasick {
+    use crate::ring_deque::RingDeque;
+    use std::collections::BTreeMap;
+    use std::iter::Fuse;
+
+    const ROOT: usize = 0;
+
+    /// Совпадение образца `pattern` с элементами текста `start..end`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Match {
+        pub pattern: usize,
+        pub start: usize,
+        pub end: usize,
+    }
+
+    struct State<K> {
+        next: BTreeMap<K, usize>,
+        fail: usize,
+        // ближайшее по ссылкам неудачи состояние с непустым `outputs`
+        dictionary: Option<usize>,
+        // образцы, которые заканчиваются ровно в этом состоянии
+        outputs: Vec<usize>,
+        depth: usize,
+    }
+
+    impl<K: Ord> State<K> {
+        fn new(depth: usize) -> Self {
+            State {
+                next: BTreeMap::new(),
+                fail: ROOT,
+                dictionary: None,
+                outputs: vec![],
+                depth,
+            }
+        }
+    }
+
+    pub struct AhoCorasick<K> {
+        states: Vec<State<K>>,
+        lengths: Vec<usize>,
+    }
+
+    impl<K: Ord + Clone> AhoCorasick<K> {
+        pub fn new<I, P>(patterns: I) -> Self
+        where
+            I: IntoIterator<Item = P>,
+            P: IntoIterator<Item = K>,
+        {
+            let mut automaton = AhoCorasick {
+                states: vec![State::new(0)],
+                lengths: vec![],
+            };
+            for pattern in patterns {
+                automaton.add(pattern);
+            }
+            automaton.build_links();
+            automaton
+        }
+
+        /// Число образцов
+        pub fn len(&self) -> usize {
+            self.lengths.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.lengths.is_empty()
+        }
+
+        pub fn is_match<I: IntoIterator<Item = K>>(&self, text: I) -> bool {
+            self.find_overlapping(text).next().is_some()
+        }
+
+        /// Все совпадения в порядке их конца; при общем конце - от длинного образца к короткому
+        pub fn find_overlapping<I: IntoIterator<Item = K>>(
+            &self,
+            text: I,
+        ) -> FindOverlapping<'_, K, I::IntoIter> {
+            FindOverlapping {
+                automaton: self,
+                text: text.into_iter(),
+                position: 0,
+                state: ROOT,
+                output: None,
+            }
+        }
+
+        /// Непересекающиеся совпадения слева направо. Из совпадений с самым левым началом
+        /// берётся самое длинное, при равной длине - образец с меньшим номером
+        pub fn find_leftmost_longest<I: IntoIterator<Item = K>>(
+            &self,
+            text: I,
+        ) -> FindLeftmostLongest<'_, K, I::IntoIter> {
+            let longest = self.lengths.iter().copied().max().unwrap_or(0);
+            FindLeftmostLongest {
+                automaton: self,
+                text: text.into_iter().fuse(),
+                history: RingDeque::with_capacity(longest + 1),
+                replay: RingDeque::with_capacity(longest + 1),
+                longest,
+                position: 0,
+                state: ROOT,
+                best: None,
+            }
+        }
+
+        fn add<P: IntoIterator<Item = K>>(&mut self, pattern: P) {
+            let mut state = ROOT;
+            let mut length = 0;
+            for k in pattern {
+                length += 1;
+                state = match self.states[state].next.get(&k) {
+                    Some(&next) => next,
+                    None => {
+                        self.states.push(State::new(length));
+                        let next = self.states.len() - 1;
+                        self.states[state].next.insert(k, next);
+                        next
+                    }
+                };
+            }
+            if length > 0 {
+                self.states[state].outputs.push(self.lengths.len());
+            }
+            self.lengths.push(length);
+        }
+
+        /// Обход в ширину: ссылки неудачи родителя готовы раньше, чем у потомков
+        fn build_links(&mut self) {
+            let mut queue = RingDeque::new();
+            queue.push_back(ROOT);
+            while let Some(parent) = queue.pop_front() {
+                let children: Vec<(K, usize)> = self.states[parent]
+                    .next
+                    .iter()
+                    .map(|(k, &child)| (k.clone(), child))
+                    .collect();
+                for (k, child) in children {
+                    queue.push_back(child);
+                    if parent == ROOT {
+                        continue;
+                    }
+                    let fail = self.step(self.states[parent].fail, &k);
+                    let dictionary = if self.states[fail].outputs.is_empty() {
+                        self.states[fail].dictionary
+                    } else {
+                        Some(fail)
+                    };
+                    self.states[child].fail = fail;
+                    self.states[child].dictionary = dictionary;
+                }
+            }
+        }
+
+        /// Переход по элементу `k` с откатом по ссылкам неудачи
+        fn step(&self, mut state: usize, k: &K) -> usize {
+            loop {
+                if let Some(&next) = self.states[state].next.get(k) {
+                    return next;
+                }
+                if state == ROOT {
+                    return ROOT;
+                }
+                state = self.states[state].fail;
+            }
+        }
+    }
+
+    /// Ленивый поиск непересекающихся совпадений
+    pub struct FindLeftmostLongest<'a, K, I> {
+        automaton: &'a AhoCorasick<K>,
+        text: Fuse<I>,
+        // последние `longest` прочитанных элементов, из них берутся элементы для повторного чтения
+        history: RingDeque<K>,
+        // элементы после конца найденного совпадения, которые читаются заново
+        replay: RingDeque<K>,
+        longest: usize,
+        position: usize,
+        state: usize,
+        // лучшее совпадение среди начавшихся не правее уже пройденных
+        best: Option<Match>,
+    }
+
+    impl<K: Ord + Clone, I: Iterator<Item = K>> FindLeftmostLongest<'_, K, I> {
+        /// Продолжает поиск с конца совпадения. Прочитанные после него элементы лежат в `history`:
+        /// их не больше длины самого длинного образца
+        fn restart(&mut self, found: Match) -> Match {
+            for _ in found.end..self.position {
+                let k = self
+                    .history
+                    .pop_back()
+                    .expect("look-back within the longest pattern");
+                self.replay.push_front(k);
+            }
+            self.best = None;
+            self.state = ROOT;
+            self.position = found.end;
+            found
+        }
+    }
+
+    impl<K: Ord + Clone, I: Iterator<Item = K>> Iterator for FindLeftmostLongest<'_, K, I> {
+        type Item = Match;
+
+        fn next(&mut self) -> Option<Match> {
+            let automaton = self.automaton;
+            loop {
+                let Some(k) = self.replay.pop_front().or_else(|| self.text.next()) else {
+                    // дальше продолжаться нечему, после лучшего совпадения ищем заново
+                    let found = self.best?;
+                    return Some(self.restart(found));
+                };
+                self.state = automaton.step(self.state, &k);
+                self.position += 1;
+                if self.history.len() == self.longest {
+                    self.history.pop_front();
+                }
+                if self.longest > 0 {
+                    self.history.push_back(k);
+                }
+
+                let mut output = Some(self.state);
+                while let Some(current) = output {
+                    for &pattern in &automaton.states[current].outputs {
+                        let found = Match {
+                            pattern,
+                            start: self.position - automaton.lengths[pattern],
+                            end: self.position,
+                        };
+                        if self
+                            .best
+                            .is_none_or(|best| (found.start, best.end) < (best.start, found.end))
+                        {
+                            self.best = Some(found);
+                        }
+                    }
+                    output = automaton.states[current].dictionary;
+                }
+                // совпадения, которые ещё могут продолжиться, начинаются не раньше этой позиции
+                let earliest = self.position - automaton.states[self.state].depth;
+                if let Some(found) = self.best.filter(|best| best.start < earliest) {
+                    return Some(self.restart(found));
+                }
+            }
+        }
+    }
+
+    /// Ленивый поиск пересекающихся совпадений, текст читается один раз
+    pub struct FindOverlapping<'a, K, I> {
+        automaton: &'a AhoCorasick<K>,
+        text: I,
+        position: usize,
+        state: usize,
+        // состояние цепочки словарных ссылок и индекс следующего образца в нём
+        output: Option<(usize, usize)>,
+    }
+
+    impl<K: Ord + Clone, I: Iterator<Item = K>> Iterator for FindOverlapping<'_, K, I> {
+        type Item = Match;
+
+        fn next(&mut self) -> Option<Match> {
+            let states = &self.automaton.states;
+            loop {
+                if let Some((current, index)) = self.output {
+                    if let Some(&pattern) = states[current].outputs.get(index) {
+                        self.output = Some((current, index + 1));
+                        return Some(Match {
+                            pattern,
+                            start: self.position - self.automaton.lengths[pattern],
+                            end: self.position,
+                        });
+                    }
+                    self.output = states[current].dictionary.map(|next| (next, 0));
+                    continue;
+                }
+                let k = self.text.next()?;
+                self.state = self.automaton.step(self.state, &k);
+                self.position += 1;
+                self.output = Some((self.state, 0));
+            }
+        }
+    }
+}
+
+/// $ cargo test aho_corasick
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlapping(automaton: &AhoCorasick<u8>, text: &[u8]) -> Vec<(usize, usize)> {
+        automaton
+            .find_overlapping(text.iter().copied())
+            .map(|found| (found.pattern, found.start))
+            .collect()
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let patterns = ["he", "she", "his", "hers"];
+        let automaton = AhoCorasick::new(patterns.iter().map(|p| p.bytes()));
+        assert_eq!(automaton.len(), 4);
+        assert_eq!(
+            overlapping(&automaton, b"ushers"),
+            vec![(1, 1), (0, 2), (3, 2)]
+        );
+        assert_eq!(
+            overlapping(&automaton, b"hishe"),
+            vec![(2, 0), (1, 2), (0, 3)]
+        );
+        assert!(!automaton.is_match(b"sh hr".iter().copied()));
+    }
+
+    #[test]
+    fn test_chars() {
+        let devices = ["датчик-7", "датчик", "шлюз"];
+        let automaton = AhoCorasick::new(devices.iter().map(|p| p.chars()));
+        let found: Vec<Match> = automaton
+            .find_overlapping("шлюз: датчик-7 offline".chars())
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                Match {
+                    pattern: 2,
+                    start: 0,
+                    end: 4
+                },
+                Match {
+                    pattern: 1,
+                    start: 6,
+                    end: 12
+                },
+                Match {
+                    pattern: 0,
+                    start: 6,
+                    end: 14
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leftmost_longest() {
+        let automaton = AhoCorasick::new(
+            ["ab", "abcd", "bc", "cde", "", "e"]
+                .iter()
+                .map(|p| p.bytes()),
+        );
+        let found: Vec<(usize, usize)> = automaton
+            .find_leftmost_longest("abcdeabce".bytes())
+            .map(|found| (found.pattern, found.start))
+            .collect();
+        assert_eq!(found, vec![(1, 0), (5, 4), (0, 5), (5, 8)]);
+    }
+
+    /// Текст читается лениво: бесконечный поток, прочитано не больше найденного плюс длина образца
+    #[test]
+    fn test_leftmost_longest_lazy() {
+        use std::cell::Cell;
+        let automaton = AhoCorasick::new(["ab", "abcd", "cde"].iter().map(|p| p.bytes()));
+        let read = Cell::new(0);
+        let text = b"abcdeabce_".iter().copied().cycle().inspect(|_| {
+            read.set(read.get() + 1);
+        });
+        let found: Vec<(usize, usize)> = automaton
+            .find_leftmost_longest(text)
+            .take(4)
+            .map(|found| (found.pattern, found.start))
+            .collect();
+        assert_eq!(found, vec![(1, 0), (0, 5), (1, 10), (0, 15)]);
+        assert!(read.get() <= 17 + 4, "read {} elements", read.get());
+    }
+
+    /// Образцы и текст из маленького алфавита, сверка с перебором
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let patterns: Vec<Vec<u8>> = (0..rng.gen_range(1..8))
+                .map(|_| {
+                    (0..rng.gen_range(1..5))
+                        .map(|_| rng.gen_range(0..3))
+                        .collect()
+                })
+                .collect();
+            let text: Vec<u8> = (0..rng.gen_range(0..40))
+                .map(|_| rng.gen_range(0..3))
+                .collect();
+            let automaton = AhoCorasick::new(patterns.iter().cloned());
+
+            let mut expected = vec![];
+            for start in 0..text.len() {
+                for (pattern, p) in patterns.iter().enumerate() {
+                    if text[start..].starts_with(p) {
+                        expected.push(Match {
+                            pattern,
+                            start,
+                            end: start + p.len(),
+                        });
+                    }
+                }
+            }
+            let mut found: Vec<Match> = automaton.find_overlapping(text.iter().copied()).collect();
+            found.sort_by_key(|found| (found.start, found.pattern));
+            assert_eq!(found, expected);
+
+            let mut leftmost = vec![];
+            let mut position = 0;
+            for candidate in &expected {
+                if candidate.start < position {
+                    continue;
+                }
+                let best = expected
+                    .iter()
+                    .filter(|other| other.start == candidate.start)
+                    .min_by_key(|other| (usize::MAX - other.end, other.pattern))
+                    .unwrap();
+                leftmost.push(*best);
+                position = best.end;
+            }
+            assert_eq!(
+                automaton
+                    .find_leftmost_longest(text.iter().copied())
+                    .collect::<Vec<_>>(),
+                leftmost
+            );
+        }
+    }
+}
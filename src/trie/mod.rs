@@ -1 +1,5 @@
+pub mod aho_corasick;
+pub mod radix_trie;
+pub mod suffix_array;
 pub mod trie;
+pub mod trie_map;
@@ -0,0 +1,1127 @@
+#![allow(dead_code)]
+
+//! Сжатое префиксное дерево (radix / Patricia trie) с тем же API, что у `trie_map::TrieMap`.
+//!
+//! Рёбра помечены срезами `Box<[K]>`, а не отдельными элементами, поэтому цепочка узлов
+//! с единственным потомком хранится одним узлом. Потомки узла лежат в `Vec`,
+//! отсортированном по первому элементу метки, - без `HashMap`/`BTreeMap` на каждый узел.
+//!
+//! `insert` разрезает метку, когда новый ключ расходится с ней посередине,
+//! `remove` склеивает узел без значения с его единственным потомком.
+//! `iter`/`iter_prefix`/`range` обходят дерево лениво в лексикографическом порядке,
+//! `into_iter` освобождает узлы по мере обхода.
+//!
+//! Алгебра множеств ключей (`union_with`, `intersection`, `difference_with`, `is_subset`, ...)
+//! обходит оба дерева вместе: потомки сливаются по отсортированным спискам, метки режутся там,
+//! где ключи расходятся, общие ветви обходятся за один проход, а недостающие копируются или
+//! отбрасываются целиком, без поиска каждого ключа.
+//!
+//! Подсказки для ввода: `complete`/`complete_by` - первые `limit` ключей с префиксом
+//! (по алфавиту или по весу), `fuzzy` - ключи на расстоянии Левенштейна не больше `max_edits`.
+pub use ds_radix_trie::{IntoIter, Iter, RadixTrieMap};
+mod ds_radix_trie {
+    use crate::heap::heap::BinaryHeap;
+    use std::cmp::Ordering;
+    use std::ops::{Bound, RangeBounds};
+    use std::{mem, slice, vec};
+
+    #[derive(Clone)]
+    struct Node<K, V> {
+        label: Box<[K]>,
+        value: Option<V>,
+        // отсортированы по label[0], метки потомков непустые
+        children: Vec<Node<K, V>>,
+    }
+
+    impl<K: Ord + Clone, V> Node<K, V> {
+        fn new(label: &[K], value: Option<V>) -> Self {
+            Node {
+                label: label.into(),
+                value,
+                children: vec![],
+            }
+        }
+
+        fn child_position(&self, first: &K) -> Result<usize, usize> {
+            self.children
+                .binary_search_by(|child| child.label[0].cmp(first))
+        }
+
+        fn child(&self, key: &[K]) -> Option<&Node<K, V>> {
+            let index = self.child_position(key.first()?).ok()?;
+            Some(&self.children[index])
+        }
+
+        fn insert(&mut self, key: &[K], value: V) -> Option<V> {
+            let Some(first) = key.first() else {
+                return self.value.replace(value);
+            };
+            let index = match self.child_position(first) {
+                Ok(index) => index,
+                Err(index) => {
+                    self.children.insert(index, Node::new(key, Some(value)));
+                    return None;
+                }
+            };
+            let child = &mut self.children[index];
+            let common = common_prefix(&child.label, key);
+            if common < child.label.len() {
+                // ключ расходится с меткой посередине: разрезаем ребро
+                child.split(common);
+            }
+            child.insert(&key[common..], value)
+        }
+
+        /// Разрезает метку: в узле остаётся `label[..at]`, остальное уходит в единственного потомка
+        fn split(&mut self, at: usize) {
+            let tail = Node {
+                label: self.label[at..].into(),
+                value: self.value.take(),
+                children: mem::take(&mut self.children),
+            };
+            self.label = self.label[..at].into();
+            self.children = vec![tail];
+        }
+
+        /// Убирает потомка без значений, потомка без значения с одним потомком склеивает с ним.
+        /// Возвращает, остался ли потомок на месте `index`
+        fn prune(&mut self, index: usize) -> bool {
+            let child = &mut self.children[index];
+            if child.value.is_some() {
+                return true;
+            }
+            match child.children.len() {
+                0 => {
+                    self.children.remove(index);
+                    return false;
+                }
+                1 => {
+                    // склеиваем с единственным потомком
+                    let grandchild = child.children.pop().expect("one child");
+                    let mut label = child.label.to_vec();
+                    label.extend_from_slice(&grandchild.label);
+                    *child = Node {
+                        label: label.into(),
+                        ..grandchild
+                    };
+                }
+                _ => {}
+            }
+            true
+        }
+
+        fn count(&self) -> usize {
+            usize::from(self.value.is_some()) + self.children.iter().map(Node::count).sum::<usize>()
+        }
+
+        fn remove(&mut self, key: &[K]) -> Option<V> {
+            let Some(first) = key.first() else {
+                return self.value.take();
+            };
+            let index = self.child_position(first).ok()?;
+            let child = &mut self.children[index];
+            let rest = key.strip_prefix(&child.label[..])?;
+            let removed = child.remove(rest)?;
+            self.prune(index);
+            Some(removed)
+        }
+
+        /// Добавляет ключи из позиции `other`, стоящей на том же ключе, что и конец `self`
+        fn union_with(&mut self, other: Pos<'_, K, V>, length: &mut usize)
+        where
+            V: Clone,
+        {
+            if let (None, Some(value)) = (&self.value, other.value()) {
+                self.value = Some(value.clone());
+                *length += 1;
+            }
+            for edge in other.edges() {
+                let index = match self.child_position(&edge.label()[0]) {
+                    Ok(index) => index,
+                    Err(index) => {
+                        *length += edge.node.count();
+                        self.children.insert(index, edge.to_node());
+                        continue;
+                    }
+                };
+                let child = &mut self.children[index];
+                let common = common_prefix(&child.label, edge.label());
+                if common < child.label.len() {
+                    child.split(common);
+                }
+                child.union_with(edge.advance(common), length);
+            }
+        }
+
+        /// Оставляет только ключи, которые есть и в `other`
+        fn intersection_with<W>(&mut self, other: Pos<'_, K, W>, length: &mut usize) {
+            if self.value.is_some() && other.value().is_none() {
+                self.value = None;
+                *length -= 1;
+            }
+            let mut index = 0;
+            while index < self.children.len() {
+                let child = &mut self.children[index];
+                match other.follow(&child.label) {
+                    Some((edge, common)) => {
+                        if common < child.label.len() {
+                            child.split(common);
+                        }
+                        child.intersection_with(edge.advance(common), length);
+                    }
+                    None => {
+                        // общих ключей в ветви нет
+                        *length -= child.count();
+                        self.children.remove(index);
+                        continue;
+                    }
+                }
+                if self.prune(index) {
+                    index += 1;
+                }
+            }
+        }
+
+        /// Убирает ключи, которые есть в `other`; ветви, которых в `other` нет, не обходятся
+        fn difference_with<W>(&mut self, other: Pos<'_, K, W>, length: &mut usize) {
+            if self.value.is_some() && other.value().is_some() {
+                self.value = None;
+                *length -= 1;
+            }
+            let mut index = 0;
+            while index < self.children.len() {
+                let child = &mut self.children[index];
+                if let Some((edge, common)) = other.follow(&child.label) {
+                    if common < child.label.len() {
+                        child.split(common);
+                    }
+                    child.difference_with(edge.advance(common), length);
+                }
+                if self.prune(index) {
+                    index += 1;
+                }
+            }
+        }
+
+        /// Ключи, которые есть ровно в одном из деревьев
+        fn symmetric_difference_with(&mut self, other: Pos<'_, K, V>, length: &mut usize)
+        where
+            V: Clone,
+        {
+            if let Some(value) = other.value() {
+                if self.value.take().is_some() {
+                    *length -= 1;
+                } else {
+                    self.value = Some(value.clone());
+                    *length += 1;
+                }
+            }
+            for edge in other.edges() {
+                let index = match self.child_position(&edge.label()[0]) {
+                    Ok(index) => index,
+                    Err(index) => {
+                        *length += edge.node.count();
+                        self.children.insert(index, edge.to_node());
+                        continue;
+                    }
+                };
+                let child = &mut self.children[index];
+                let common = common_prefix(&child.label, edge.label());
+                if common < child.label.len() {
+                    child.split(common);
+                }
+                child.symmetric_difference_with(edge.advance(common), length);
+                self.prune(index);
+            }
+        }
+    }
+
+    /// Позиция в дереве перед `node.label[skip]`, при `skip == label.len()` - в самом узле.
+    /// Через позиции сравниваются деревья, метки которых разрезаны в разных местах
+    struct Pos<'a, K, V> {
+        node: &'a Node<K, V>,
+        skip: usize,
+    }
+
+    impl<K, V> Clone for Pos<'_, K, V> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<K, V> Copy for Pos<'_, K, V> {}
+
+    impl<'a, K: Ord + Clone, V> Pos<'a, K, V> {
+        /// Конец узла
+        fn at(node: &'a Node<K, V>) -> Self {
+            Pos {
+                node,
+                skip: node.label.len(),
+            }
+        }
+
+        /// Непройденная часть метки
+        fn label(self) -> &'a [K] {
+            &self.node.label[self.skip..]
+        }
+
+        fn value(self) -> Option<&'a V> {
+            match self.label().is_empty() {
+                true => self.node.value.as_ref(),
+                false => None,
+            }
+        }
+
+        fn advance(self, by: usize) -> Self {
+            Pos {
+                node: self.node,
+                skip: self.skip + by,
+            }
+        }
+
+        /// Рёбра из позиции по возрастанию первого элемента: потомки узла или остаток метки
+        fn edges(self) -> impl Iterator<Item = Pos<'a, K, V>> {
+            let (children, inside): (&[Node<K, V>], _) = match self.label().is_empty() {
+                true => (&self.node.children, None),
+                false => (&[], Some(self)),
+            };
+            children
+                .iter()
+                .map(|child| Pos {
+                    node: child,
+                    skip: 0,
+                })
+                .chain(inside)
+        }
+
+        /// Ребро, совпадающее с началом `label`, и длина совпадения, если `label` и ребро
+        /// не расходятся посередине. Иначе у ветви `label` и позиции нет общих ключей
+        fn follow(self, label: &[K]) -> Option<(Self, usize)> {
+            let edge = match self.label().first() {
+                Some(first) => (first == &label[0]).then_some(self)?,
+                None => {
+                    let index = self.node.child_position(&label[0]).ok()?;
+                    Pos {
+                        node: &self.node.children[index],
+                        skip: 0,
+                    }
+                }
+            };
+            let common = common_prefix(label, edge.label());
+            (common == label.len() || common == edge.label().len()).then_some((edge, common))
+        }
+
+        /// Копия ветви, начинающейся с ребра
+        fn to_node(self) -> Node<K, V>
+        where
+            V: Clone,
+        {
+            Node {
+                label: self.label().into(),
+                value: self.node.value.clone(),
+                children: self.node.children.clone(),
+            }
+        }
+
+        /// Общие ключи двух позиций - новый узел с меткой `label`
+        fn intersection<W>(
+            self,
+            other: Pos<'_, K, W>,
+            label: &[K],
+            length: &mut usize,
+        ) -> Node<K, V>
+        where
+            V: Clone,
+        {
+            let value = other.value().and(self.value()).cloned();
+            *length += usize::from(value.is_some());
+            let mut node = Node::new(label, value);
+            for edge in self.edges() {
+                if let Some((theirs, common)) = other.follow(edge.label()) {
+                    let child = edge.advance(common).intersection(
+                        theirs.advance(common),
+                        &edge.label()[..common],
+                        length,
+                    );
+                    node.children.push(child);
+                    node.prune(node.children.len() - 1);
+                }
+            }
+            node
+        }
+
+        /// Все ключи позиции есть в `other`. Ветви в дереве непусты, поэтому ребро,
+        /// которого нет в `other`, уже даёт лишний ключ
+        fn is_subset<W>(self, other: Pos<'_, K, W>) -> bool {
+            if self.value().is_some() && other.value().is_none() {
+                return false;
+            }
+            self.edges().all(|edge| match other.follow(edge.label()) {
+                Some((theirs, common)) => edge.advance(common).is_subset(theirs.advance(common)),
+                None => false,
+            })
+        }
+
+        fn is_disjoint<W>(self, other: Pos<'_, K, W>) -> bool {
+            if self.value().is_some() && other.value().is_some() {
+                return false;
+            }
+            self.edges().all(|edge| match other.follow(edge.label()) {
+                Some((theirs, common)) => edge.advance(common).is_disjoint(theirs.advance(common)),
+                None => true,
+            })
+        }
+    }
+
+    fn fuzzy_r<'a, K: Clone + PartialEq, V>(
+        node: &'a Node<K, V>,
+        query: &[K],
+        parent_row: &[usize],
+        max_edits: usize,
+        key: &mut Vec<K>,
+        found: &mut Vec<(Vec<K>, &'a V, usize)>,
+    ) {
+        let key_len = key.len();
+        let mut row = parent_row.to_vec();
+        for k in node.label.iter() {
+            let mut next = vec![row[0] + 1];
+            for (j, q) in query.iter().enumerate() {
+                let replace = row[j] + usize::from(q != k);
+                next.push(replace.min(row[j + 1] + 1).min(next[j] + 1));
+            }
+            row = next;
+            key.push(k.clone());
+            if row.iter().min().is_some_and(|min| *min > max_edits) {
+                key.truncate(key_len);
+                return;
+            }
+        }
+        let distance = row[query.len()];
+        if let Some(value) = node.value.as_ref() {
+            if distance <= max_edits {
+                found.push((key.clone(), value, distance));
+            }
+        }
+        for child in node.children.iter() {
+            fuzzy_r(child, query, &row, max_edits, key, found);
+        }
+        key.truncate(key_len);
+    }
+
+    fn common_prefix<K: PartialEq>(a: &[K], b: &[K]) -> usize {
+        a.iter().zip(b).take_while(|(a, b)| a == b).count()
+    }
+
+    #[derive(Clone)]
+    pub struct RadixTrieMap<K, V> {
+        // метка корня всегда пуста
+        root: Node<K, V>,
+        length: usize,
+    }
+
+    impl<K: Ord + Clone, V> Default for RadixTrieMap<K, V> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<K: Ord + Clone, V> RadixTrieMap<K, V> {
+        pub fn new() -> Self {
+            RadixTrieMap {
+                root: Node::new(&[], None),
+                length: 0,
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.length
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.length == 0
+        }
+
+        /// Возвращает прежнее значение ключа
+        pub fn insert<I: IntoIterator<Item = K>>(&mut self, key: I, value: V) -> Option<V> {
+            let key: Vec<K> = key.into_iter().collect();
+            let old = self.root.insert(&key, value);
+            if old.is_none() {
+                self.length += 1;
+            }
+            old
+        }
+
+        pub fn get<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&V> {
+            let key: Vec<K> = key.into_iter().collect();
+            let mut node = &self.root;
+            let mut rest = &key[..];
+            while !rest.is_empty() {
+                node = node.child(rest)?;
+                rest = rest.strip_prefix(&node.label[..])?;
+            }
+            node.value.as_ref()
+        }
+
+        pub fn get_mut<I: IntoIterator<Item = K>>(&mut self, key: I) -> Option<&mut V> {
+            let key: Vec<K> = key.into_iter().collect();
+            let mut node = &mut self.root;
+            let mut rest = &key[..];
+            while let Some(first) = rest.first() {
+                let index = node.child_position(first).ok()?;
+                node = &mut node.children[index];
+                rest = rest.strip_prefix(&node.label[..])?;
+            }
+            node.value.as_mut()
+        }
+
+        pub fn contains_key<I: IntoIterator<Item = K>>(&self, key: I) -> bool {
+            self.get(key).is_some()
+        }
+
+        /// Удаляет ключ, пустые узлы и склеивает узлы с одним потомком
+        pub fn remove<I: IntoIterator<Item = K>>(&mut self, key: I) -> Option<V> {
+            let key: Vec<K> = key.into_iter().collect();
+            let removed = self.root.remove(&key);
+            if removed.is_some() {
+                self.length -= 1;
+            }
+            removed
+        }
+
+        /// Самый длинный сохранённый префикс `key` (включая весь ключ):
+        /// число совпавших элементов ключа и значение
+        pub fn longest_prefix_match<I: IntoIterator<Item = K>>(
+            &self,
+            key: I,
+        ) -> Option<(usize, &V)> {
+            let key: Vec<K> = key.into_iter().collect();
+            let mut node = &self.root;
+            let mut depth = 0;
+            let mut best = node.value.as_ref().map(|value| (0, value));
+            while let Some(child) = node.child(&key[depth..]) {
+                if !key[depth..].starts_with(&child.label) {
+                    break;
+                }
+                node = child;
+                depth += node.label.len();
+                if let Some(value) = node.value.as_ref() {
+                    best = Some((depth, value));
+                }
+            }
+            best
+        }
+
+        /// Добавляет ключи `other`, которых нет в `self`; у общих ключей остаются значения `self`.
+        /// Недостающие ветви `other` копируются целиком
+        pub fn union_with(&mut self, other: &Self)
+        where
+            V: Clone,
+        {
+            self.root.union_with(Pos::at(&other.root), &mut self.length);
+        }
+
+        /// Оставляет ключи, которые есть в `other`
+        pub fn intersection_with<W>(&mut self, other: &RadixTrieMap<K, W>) {
+            self.root
+                .intersection_with(Pos::at(&other.root), &mut self.length);
+        }
+
+        /// Убирает ключи, которые есть в `other`
+        pub fn difference_with<W>(&mut self, other: &RadixTrieMap<K, W>) {
+            self.root
+                .difference_with(Pos::at(&other.root), &mut self.length);
+        }
+
+        /// Оставляет ключи, которые есть ровно в одном из деревьев
+        pub fn symmetric_difference_with(&mut self, other: &Self)
+        where
+            V: Clone,
+        {
+            self.root
+                .symmetric_difference_with(Pos::at(&other.root), &mut self.length);
+        }
+
+        /// Ключи `self`, которые есть в `other`, со значениями из `self`.
+        /// Строится только из общих ветвей, без копирования `self` целиком
+        pub fn intersection<W>(&self, other: &RadixTrieMap<K, W>) -> Self
+        where
+            V: Clone,
+        {
+            let mut length = 0;
+            let root = Pos::at(&self.root).intersection(Pos::at(&other.root), &[], &mut length);
+            RadixTrieMap { root, length }
+        }
+
+        /// Все ключи `self` есть в `other`
+        pub fn is_subset<W>(&self, other: &RadixTrieMap<K, W>) -> bool {
+            self.length <= other.length && Pos::at(&self.root).is_subset(Pos::at(&other.root))
+        }
+
+        /// Нет общих ключей
+        pub fn is_disjoint<W>(&self, other: &RadixTrieMap<K, W>) -> bool {
+            Pos::at(&self.root).is_disjoint(Pos::at(&other.root))
+        }
+
+        /// Все пары в лексикографическом порядке ключей
+        pub fn iter(&self) -> Iter<'_, K, V> {
+            Iter::new(vec![], Some(&self.root))
+        }
+
+        /// Пары, ключи которых начинаются с `prefix`, в лексикографическом порядке
+        pub fn iter_prefix<I: IntoIterator<Item = K>>(&self, prefix: I) -> Iter<'_, K, V> {
+            let prefix: Vec<K> = prefix.into_iter().collect();
+            let mut node = &self.root;
+            let mut path = vec![];
+            while path.len() < prefix.len() {
+                let rest = &prefix[path.len()..];
+                let Some(child) = node.child(rest) else {
+                    return Iter::new(vec![], None);
+                };
+                // префикс может закончиться посреди метки
+                let common = common_prefix(&child.label, rest);
+                if common < child.label.len() && common < rest.len() {
+                    return Iter::new(vec![], None);
+                }
+                path.extend_from_slice(&child.label);
+                node = child;
+            }
+            Iter::new(path, Some(node))
+        }
+
+        /// Пары с ключами из диапазона `range` в лексикографическом порядке.
+        /// Поддеревья целиком левее начала диапазона пропускаются, обход останавливается за его концом
+        pub fn range<R: RangeBounds<Vec<K>>>(&self, range: R) -> Iter<'_, K, V> {
+            let mut iter = self.iter();
+            iter.start = range.start_bound().cloned();
+            iter.end = range.end_bound().cloned();
+            iter
+        }
+
+        pub fn walk(&self, mut callback: impl FnMut(&[K], &V)) {
+            let mut iter = self.iter();
+            while let Some(value) = iter.next_value() {
+                callback(&iter.key, value);
+            }
+        }
+
+        /// Первые `limit` ключей с префиксом `prefix` в лексикографическом порядке
+        pub fn complete<I: IntoIterator<Item = K>>(
+            &self,
+            prefix: I,
+            limit: usize,
+        ) -> Vec<(Vec<K>, &V)> {
+            self.iter_prefix(prefix).take(limit).collect()
+        }
+
+        /// `limit` ключей с префиксом `prefix` с наибольшим весом `weight(value)`,
+        /// при равном весе - в лексикографическом порядке. Куча хранит не больше `limit` кандидатов
+        pub fn complete_by<I, W, F>(&self, prefix: I, limit: usize, weight: F) -> Vec<(Vec<K>, &V)>
+        where
+            I: IntoIterator<Item = K>,
+            W: Ord,
+            F: Fn(&V) -> W,
+        {
+            if limit == 0 {
+                return vec![];
+            }
+            // на вершине худший из кандидатов: меньший вес, затем больший ключ
+            let worse = |a: &(W, Vec<K>, &V), b: &(W, Vec<K>, &V)| -> Ordering {
+                b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1))
+            };
+            let mut best = BinaryHeap::with_comparator(worse);
+            for (key, value) in self.iter_prefix(prefix) {
+                best.push((weight(value), key, value));
+                if best.len() > limit {
+                    best.pop();
+                }
+            }
+            best.into_sorted_vec()
+                .into_iter()
+                .map(|(_, key, value)| (key, value))
+                .collect()
+        }
+
+        /// Ключи на расстоянии Левенштейна не больше `max_edits` от `query`
+        /// в лексикографическом порядке, с расстоянием.
+        /// Обход хранит строку таблицы динамического программирования для текущего пути
+        /// и не спускается в ветви, где минимум строки уже больше `max_edits`
+        pub fn fuzzy<I: IntoIterator<Item = K>>(
+            &self,
+            query: I,
+            max_edits: usize,
+        ) -> Vec<(Vec<K>, &V, usize)> {
+            let query: Vec<K> = query.into_iter().collect();
+            let row: Vec<usize> = (0..=query.len()).collect();
+            let mut found = vec![];
+            if let Some(value) = self.root.value.as_ref() {
+                if query.len() <= max_edits {
+                    found.push((vec![], value, query.len()));
+                }
+            }
+            let mut key = vec![];
+            for child in self.root.children.iter() {
+                fuzzy_r(child, &query, &row, max_edits, &mut key, &mut found);
+            }
+            found
+        }
+
+        /// Число узлов дерева, включая корень
+        #[cfg(test)]
+        pub(super) fn node_count(&self) -> usize {
+            fn count<K, V>(node: &Node<K, V>) -> usize {
+                1 + node.children.iter().map(count).sum::<usize>()
+            }
+            count(&self.root)
+        }
+    }
+
+    impl<K: Ord + Clone, V, I: IntoIterator<Item = K>> FromIterator<(I, V)> for RadixTrieMap<K, V> {
+        fn from_iter<T: IntoIterator<Item = (I, V)>>(iter: T) -> Self {
+            let mut map = RadixTrieMap::new();
+            for (key, value) in iter {
+                map.insert(key, value);
+            }
+            map
+        }
+    }
+
+    /// Ленивый обход в глубину: на стеке итераторы по потомкам и длина ключа их родителя
+    pub struct Iter<'a, K, V> {
+        key: Vec<K>,
+        root: Option<&'a Node<K, V>>,
+        stack: Vec<(slice::Iter<'a, Node<K, V>>, usize)>,
+        start: Bound<Vec<K>>,
+        end: Bound<Vec<K>>,
+    }
+
+    impl<'a, K: Ord + Clone, V> Iter<'a, K, V> {
+        fn new(key: Vec<K>, root: Option<&'a Node<K, V>>) -> Self {
+            Iter {
+                key,
+                root,
+                stack: vec![],
+                start: Bound::Unbounded,
+                end: Bound::Unbounded,
+            }
+        }
+
+        fn before_start(&self, key: &[K]) -> bool {
+            match &self.start {
+                Bound::Included(start) => key < &start[..],
+                Bound::Excluded(start) => key <= &start[..],
+                Bound::Unbounded => false,
+            }
+        }
+
+        /// Все ключи с префиксом `key` левее начала диапазона
+        fn subtree_before_start(&self, key: &[K]) -> bool {
+            match &self.start {
+                Bound::Included(start) | Bound::Excluded(start) => {
+                    key < &start[..] && !start.starts_with(key)
+                }
+                Bound::Unbounded => false,
+            }
+        }
+
+        fn after_end(&self, key: &[K]) -> bool {
+            match &self.end {
+                Bound::Included(end) => key > &end[..],
+                Bound::Excluded(end) => key >= &end[..],
+                Bound::Unbounded => false,
+            }
+        }
+
+        /// Следующее значение, его ключ остаётся в `self.key`
+        fn next_value(&mut self) -> Option<&'a V> {
+            if let Some(node) = self.root.take() {
+                if self.after_end(&self.key) {
+                    return None;
+                }
+                self.stack.push((node.children.iter(), self.key.len()));
+                if let Some(value) = node.value.as_ref() {
+                    if !self.before_start(&self.key) {
+                        return Some(value);
+                    }
+                }
+            }
+            loop {
+                let (children, parent_len) = self.stack.last_mut()?;
+                let parent_len = *parent_len;
+                let Some(child) = children.next() else {
+                    self.stack.pop();
+                    continue;
+                };
+                self.key.truncate(parent_len);
+                self.key.extend_from_slice(&child.label);
+                if self.after_end(&self.key) {
+                    // потомки и все следующие ключи ещё больше
+                    self.stack.clear();
+                    return None;
+                }
+                if self.subtree_before_start(&self.key) {
+                    continue;
+                }
+                self.stack.push((child.children.iter(), self.key.len()));
+                if let Some(value) = child.value.as_ref() {
+                    if !self.before_start(&self.key) {
+                        return Some(value);
+                    }
+                }
+            }
+        }
+    }
+
+    impl<'a, K: Ord + Clone, V> Iterator for Iter<'a, K, V> {
+        type Item = (Vec<K>, &'a V);
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_value().map(|value| (self.key.clone(), value))
+        }
+    }
+
+    impl<K: Ord + Clone, V> IntoIterator for RadixTrieMap<K, V> {
+        type Item = (Vec<K>, V);
+        type IntoIter = IntoIter<K, V>;
+
+        fn into_iter(self) -> IntoIter<K, V> {
+            IntoIter {
+                key: vec![],
+                root: Some(self.root),
+                stack: vec![],
+            }
+        }
+    }
+
+    /// Поглощающий ленивый обход: узлы освобождаются по мере продвижения
+    pub struct IntoIter<K, V> {
+        key: Vec<K>,
+        root: Option<Node<K, V>>,
+        stack: Vec<(vec::IntoIter<Node<K, V>>, usize)>,
+    }
+
+    impl<K: Clone, V> Iterator for IntoIter<K, V> {
+        type Item = (Vec<K>, V);
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if let Some(node) = self.root.take() {
+                self.stack.push((node.children.into_iter(), 0));
+                if let Some(value) = node.value {
+                    return Some((vec![], value));
+                }
+            }
+            loop {
+                let (children, parent_len) = self.stack.last_mut()?;
+                let parent_len = *parent_len;
+                let Some(child) = children.next() else {
+                    self.stack.pop();
+                    continue;
+                };
+                self.key.truncate(parent_len);
+                self.key.extend_from_slice(&child.label);
+                self.stack
+                    .push((child.children.into_iter(), self.key.len()));
+                if let Some(value) = child.value {
+                    return Some((self.key.clone(), value));
+                }
+            }
+        }
+    }
+}
+
+/// $ cargo test radix_trie
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie_map::TrieMap;
+
+    fn keys<'a, V: 'a>(iter: impl Iterator<Item = (Vec<char>, &'a V)>) -> Vec<String> {
+        iter.map(|(key, _)| key.into_iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        let mut map = RadixTrieMap::new();
+        map.insert("/building/floor/room".chars(), 1);
+        assert_eq!(map.node_count(), 2);
+
+        // разрез метки "/building/floor/room" на "/building/floor" и "/room"
+        map.insert("/building/floor".chars(), 2);
+        assert_eq!(map.node_count(), 3);
+        // разрез с новой веткой: "/building/" -> {"floor", "garage"}
+        map.insert("/building/garage".chars(), 3);
+        assert_eq!(map.node_count(), 5);
+
+        assert_eq!(map.get("/building/floor".chars()), Some(&2));
+        assert_eq!(map.get("/building/".chars()), None);
+        assert_eq!(map.get("/building/floor/ro".chars()), None);
+
+        assert_eq!(map.remove("/building/garage".chars()), Some(3));
+        // "/building/" без значения склеился с "floor"
+        assert_eq!(map.node_count(), 3);
+        assert_eq!(map.remove("/building/floor".chars()), Some(2));
+        assert_eq!(map.node_count(), 2);
+        assert_eq!(map.get("/building/floor/room".chars()), Some(&1));
+        assert_eq!(map.remove("/building/floor/room".chars()), Some(1));
+        assert_eq!(map.node_count(), 1);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_prefix_and_longest_match() {
+        let map: RadixTrieMap<char, usize> = ["b", "a", "abc", "ab", "abd", "ac", "b/xyz"]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.chars(), i))
+            .collect();
+
+        assert_eq!(
+            keys(map.iter()),
+            vec!["a", "ab", "abc", "abd", "ac", "b", "b/xyz"]
+        );
+        assert_eq!(
+            keys(map.iter_prefix("ab".chars())),
+            vec!["ab", "abc", "abd"]
+        );
+        // префикс заканчивается посреди метки "/xyz"
+        assert_eq!(keys(map.iter_prefix("b/x".chars())), vec!["b/xyz"]);
+        assert!(keys(map.iter_prefix("b/y".chars())).is_empty());
+
+        assert_eq!(map.longest_prefix_match("abcd".chars()), Some((3, &2)));
+        assert_eq!(map.longest_prefix_match("b/xy".chars()), Some((1, &0)));
+        assert_eq!(map.longest_prefix_match("z".chars()), None);
+    }
+
+    #[test]
+    fn test_range_and_into_iter() {
+        let map: RadixTrieMap<char, usize> = ["", "b", "a", "abc", "ab", "abd", "b/xyz", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.chars(), i))
+            .collect();
+        let range = |start: &str, end: &str| (start.chars().collect(), end.chars().collect());
+
+        let (start, end): (Vec<char>, Vec<char>) = range("ab", "b/x");
+        assert_eq!(keys(map.range(start..end)), vec!["ab", "abc", "abd", "b"]);
+        // границы посреди меток "bc"/"d" и "/xyz"
+        let (start, end) = range("abcd", "b/xyz");
+        assert_eq!(keys(map.range(start..=end)), vec!["abd", "b", "b/xyz"]);
+        assert_eq!(keys(map.range(..vec!['a'])), vec![""]);
+        assert!(map.range(vec!['d']..).next().is_none());
+
+        let owned: Vec<(String, usize)> = map
+            .into_iter()
+            .map(|(key, value)| (key.into_iter().collect(), value))
+            .collect();
+        let expected = [("", 0), ("a", 2), ("ab", 4), ("abc", 3), ("abd", 5)];
+        assert_eq!(
+            owned[..5],
+            expected.map(|(key, value)| (key.to_string(), value))
+        );
+        assert_eq!(owned.len(), 8);
+    }
+
+    #[test]
+    fn test_random_against_trie_map() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut radix = RadixTrieMap::new();
+        let mut trie = TrieMap::new();
+        for i in 0..3000 {
+            let len = rng.gen_range(0..6);
+            let key: Vec<u8> = (0..len).map(|_| rng.gen_range(b'a'..b'd')).collect();
+            if rng.gen_bool(0.6) {
+                assert_eq!(
+                    radix.insert(key.iter().copied(), i),
+                    trie.insert(key.iter().copied(), i)
+                );
+            } else {
+                assert_eq!(
+                    radix.remove(key.iter().copied()),
+                    trie.remove(key.iter().copied())
+                );
+            }
+            assert_eq!(radix.len(), trie.len());
+            assert_eq!(
+                radix.longest_prefix_match(key.iter().copied()),
+                trie.longest_prefix_match(key.iter().copied())
+            );
+        }
+        assert!(radix.iter().eq(trie.iter()));
+        assert!(radix
+            .iter_prefix(b"ab".iter().copied())
+            .eq(trie.iter_prefix(b"ab".iter().copied())));
+        let mut walked = 0;
+        radix.walk(|key, value| {
+            assert_eq!(trie.get(key.iter().copied()), Some(value));
+            walked += 1;
+        });
+        assert_eq!(walked, trie.len());
+    }
+
+    fn levenshtein(a: &[u8], b: &[u8]) -> usize {
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, x) in a.iter().enumerate() {
+            let mut next = vec![i + 1];
+            for (j, y) in b.iter().enumerate() {
+                next.push(
+                    (row[j] + usize::from(x != y))
+                        .min(row[j + 1] + 1)
+                        .min(next[j] + 1),
+                );
+            }
+            row = next;
+        }
+        row[b.len()]
+    }
+
+    /// Слияния деревьев против `BTreeMap`; результат остаётся сжатым: столько же узлов,
+    /// сколько у дерева, построенного вставками тех же ключей
+    #[test]
+    fn test_set_algebra_random() {
+        use rand::Rng;
+        use std::collections::BTreeMap;
+        let mut rng = rand::thread_rng();
+        let mut random_map = |tag: u32| {
+            let mut model = BTreeMap::new();
+            for i in 0..rng.gen_range(0..40) {
+                let key: Vec<u8> = (0..rng.gen_range(0..7))
+                    .map(|_| rng.gen_range(0..3))
+                    .collect();
+                model.insert(key, tag + i);
+            }
+            let map: RadixTrieMap<u8, u32> = model.iter().map(|(k, v)| (k.clone(), *v)).collect();
+            (map, model)
+        };
+        let check = |map: &RadixTrieMap<u8, u32>, model: BTreeMap<Vec<u8>, u32>| {
+            assert_eq!(map.len(), model.len());
+            let rebuilt: RadixTrieMap<u8, u32> = model.clone().into_iter().collect();
+            assert_eq!(map.node_count(), rebuilt.node_count());
+            assert!(map.iter().map(|(k, v)| (k, *v)).eq(model.into_iter()));
+        };
+        for _ in 0..300 {
+            let (a, model_a) = random_map(0);
+            let (b, model_b) = random_map(1000);
+            let only = |keep: &dyn Fn(&Vec<u8>) -> bool, model: &BTreeMap<Vec<u8>, u32>| {
+                model
+                    .iter()
+                    .filter(|(k, _)| keep(k))
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect::<BTreeMap<_, _>>()
+            };
+
+            let mut union = a.clone();
+            union.union_with(&b);
+            let mut expected = model_b.clone();
+            expected.extend(model_a.clone());
+            check(&union, expected);
+
+            let common = only(&|k| model_b.contains_key(k), &model_a);
+            check(&a.intersection(&b), common.clone());
+            let mut intersection = a.clone();
+            intersection.intersection_with(&b);
+            check(&intersection, common.clone());
+
+            let mut difference = a.clone();
+            difference.difference_with(&b);
+            check(&difference, only(&|k| !model_b.contains_key(k), &model_a));
+
+            let mut symmetric = a.clone();
+            symmetric.symmetric_difference_with(&b);
+            let mut expected = only(&|k| !model_b.contains_key(k), &model_a);
+            expected.extend(only(&|k| !model_a.contains_key(k), &model_b));
+            check(&symmetric, expected);
+
+            let subset = model_a.keys().all(|k| model_b.contains_key(k));
+            assert_eq!(a.is_subset(&b), subset);
+            assert!(a.is_subset(&union) && common.is_empty() == a.is_disjoint(&b));
+        }
+    }
+
+    #[test]
+    fn test_complete() {
+        let map: RadixTrieMap<char, u32> = [
+            ("/building/floor", 5),
+            ("/building/fan", 9),
+            ("/building/door", 5),
+            ("/building/alarm", 1),
+            ("/garage", 100),
+        ]
+        .into_iter()
+        .map(|(key, weight)| (key.chars(), weight))
+        .collect();
+
+        assert_eq!(
+            keys(map.complete("/building/".chars(), 2).into_iter()),
+            vec!["/building/alarm", "/building/door"]
+        );
+        let ranked: Vec<String> = map
+            .complete_by("/building/".chars(), 3, |weight| *weight)
+            .into_iter()
+            .map(|(key, _)| key.into_iter().collect())
+            .collect();
+        assert_eq!(
+            ranked,
+            vec!["/building/fan", "/building/door", "/building/floor"]
+        );
+        assert!(map.complete_by("/building/".chars(), 0, |w| *w).is_empty());
+        assert!(map.complete("/yard".chars(), 3).is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let map: RadixTrieMap<char, usize> =
+            ["sensor", "sensors", "censor", "senior", "tensor", "sen"]
+                .iter()
+                .enumerate()
+                .map(|(i, key)| (key.chars(), i))
+                .collect();
+
+        let found: Vec<(String, usize)> = map
+            .fuzzy("sensor".chars(), 1)
+            .into_iter()
+            .map(|(key, _, distance)| (key.into_iter().collect(), distance))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("censor".to_string(), 1),
+                ("senior".to_string(), 1),
+                ("sensor".to_string(), 0),
+                ("sensors".to_string(), 1),
+                ("tensor".to_string(), 1)
+            ]
+        );
+        assert_eq!(map.fuzzy("sensor".chars(), 0).len(), 1);
+    }
+
+    #[test]
+    fn test_fuzzy_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let words: Vec<Vec<u8>> = (0..300)
+            .map(|_| {
+                let len = rng.gen_range(0..7);
+                (0..len).map(|_| rng.gen_range(b'a'..b'd')).collect()
+            })
+            .collect();
+        let map: RadixTrieMap<u8, ()> = words.iter().map(|w| (w.iter().copied(), ())).collect();
+        for _ in 0..50 {
+            let len = rng.gen_range(0..7);
+            let query: Vec<u8> = (0..len).map(|_| rng.gen_range(b'a'..b'd')).collect();
+            let max_edits = rng.gen_range(0..3);
+            let found: Vec<(Vec<u8>, usize)> = map
+                .fuzzy(query.iter().copied(), max_edits)
+                .into_iter()
+                .map(|(key, _, distance)| (key, distance))
+                .collect();
+            let expected: Vec<(Vec<u8>, usize)> = map
+                .iter()
+                .map(|(key, _)| {
+                    let distance = levenshtein(&key, &query);
+                    (key, distance)
+                })
+                .filter(|(_, distance)| *distance <= max_edits)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+}
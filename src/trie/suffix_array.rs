@@ -0,0 +1,246 @@
+#![allow(dead_code)]
+
+//! Обобщённый суффиксный массив с массивом LCP над несколькими последовательностями.
+//!
+//! Суффикс задаётся парой `(номер текста, смещение)` и заканчивается вместе со своим текстом,
+//! поэтому разделители между текстами не нужны. Суффиксы сортируются удвоением префиксов:
+//! на шаге `k` ранг суффикса - пара рангов его половин длины `k`, O(n log² n).
+//! LCP соседних суффиксов считается алгоритмом Касаи за O(n).
+//!
+//! Все суффиксы, начинающиеся с образца, лежат в массиве подряд, поэтому `find` -
+//! два двоичных поиска, O(m log n) для образца длины `m`.
+pub use ds_suffix_array::SuffixArray;
+mod ds_suffix_array {
+    pub struct SuffixArray<K> {
+        texts: Vec<Vec<K>>,
+        // (номер текста, смещение) в лексикографическом порядке суффиксов
+        suffixes: Vec<(usize, usize)>,
+        // lcp[i] - длина общего префикса суффиксов i - 1 и i, lcp[0] = 0
+        lcp: Vec<usize>,
+    }
+
+    impl<K: Ord> SuffixArray<K> {
+        pub fn new<I, T>(texts: I) -> Self
+        where
+            I: IntoIterator<Item = T>,
+            T: IntoIterator<Item = K>,
+        {
+            let texts: Vec<Vec<K>> = texts
+                .into_iter()
+                .map(|text| text.into_iter().collect())
+                .collect();
+            let suffixes = Self::sort_suffixes(&texts);
+            let lcp = Self::kasai(&texts, &suffixes);
+            SuffixArray {
+                texts,
+                suffixes,
+                lcp,
+            }
+        }
+
+        pub fn texts(&self) -> &[Vec<K>] {
+            &self.texts
+        }
+
+        /// Число суффиксов, равно суммарной длине текстов
+        pub fn len(&self) -> usize {
+            self.suffixes.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.suffixes.is_empty()
+        }
+
+        /// `index`-й в лексикографическом порядке суффикс
+        pub fn suffix(&self, index: usize) -> Option<&[K]> {
+            self.suffixes
+                .get(index)
+                .map(|&(text, offset)| &self.texts[text][offset..])
+        }
+
+        pub fn lcp(&self) -> &[usize] {
+            &self.lcp
+        }
+
+        /// Все вхождения `pattern`: номер текста и смещение, в порядке суффиксов
+        pub fn find(&self, pattern: &[K]) -> &[(usize, usize)] {
+            let suffix = |&(text, offset): &(usize, usize)| &self.texts[text][offset..];
+            let from = self
+                .suffixes
+                .partition_point(|position| suffix(position) < pattern);
+            let to = self.suffixes.partition_point(|position| {
+                let suffix = suffix(position);
+                &suffix[..suffix.len().min(pattern.len())] <= pattern
+            });
+            &self.suffixes[from..to]
+        }
+
+        /// Номера текстов, содержащих `pattern`, по возрастанию
+        pub fn texts_containing(&self, pattern: &[K]) -> Vec<usize> {
+            let mut texts: Vec<usize> = self.find(pattern).iter().map(|&(text, _)| text).collect();
+            texts.sort_unstable();
+            texts.dedup();
+            texts
+        }
+
+        /// Самая длинная подпоследовательность, которая встречается хотя бы дважды
+        /// (в одном тексте или в разных): максимум LCP соседних суффиксов
+        pub fn longest_repeated(&self) -> Option<&[K]> {
+            let (index, &length) = self
+                .lcp
+                .iter()
+                .enumerate()
+                .max_by_key(|&(index, length)| (length, std::cmp::Reverse(index)))?;
+            let (text, offset) = self.suffixes[index];
+            (length > 0).then(|| &self.texts[text][offset..offset + length])
+        }
+
+        fn sort_suffixes(texts: &[Vec<K>]) -> Vec<(usize, usize)> {
+            let mut suffixes: Vec<(usize, usize)> = texts
+                .iter()
+                .enumerate()
+                .flat_map(|(text, items)| (0..items.len()).map(move |offset| (text, offset)))
+                .collect();
+            // ранги начинаются с 1, ранг 0 - за концом текста
+            let mut rank: Vec<Vec<usize>> = texts.iter().map(|text| vec![0; text.len()]).collect();
+            suffixes.sort_by(|&(a, i), &(b, j)| texts[a][i].cmp(&texts[b][j]));
+            let mut distinct = Self::assign_ranks(&suffixes, &mut rank, |index| {
+                let ((a, i), (b, j)) = (suffixes[index - 1], suffixes[index]);
+                texts[a][i] == texts[b][j]
+            });
+
+            let longest = texts.iter().map(Vec::len).max().unwrap_or(0);
+            let mut k = 1;
+            while !distinct && k < longest {
+                let key = |&(text, offset): &(usize, usize)| {
+                    let second = rank[text].get(offset + k).copied().unwrap_or(0);
+                    (rank[text][offset], second)
+                };
+                suffixes.sort_by_key(key);
+                let keys: Vec<(usize, usize)> = suffixes.iter().map(key).collect();
+                distinct = Self::assign_ranks(&suffixes, &mut rank, |index| {
+                    keys[index - 1] == keys[index]
+                });
+                k *= 2;
+            }
+            suffixes
+        }
+
+        /// Ранги по отсортированным суффиксам, `same(i)` - суффикс `i` равен предыдущему.
+        /// `true`, если все ранги различны
+        fn assign_ranks(
+            suffixes: &[(usize, usize)],
+            rank: &mut [Vec<usize>],
+            same: impl Fn(usize) -> bool,
+        ) -> bool {
+            let mut current = 0;
+            for (index, &(text, offset)) in suffixes.iter().enumerate() {
+                if index == 0 || !same(index) {
+                    current += 1;
+                }
+                rank[text][offset] = current;
+            }
+            current == suffixes.len()
+        }
+
+        /// Касаи: LCP суффикса со смещением `i + 1` не меньше LCP суффикса `i` минус 1
+        fn kasai(texts: &[Vec<K>], suffixes: &[(usize, usize)]) -> Vec<usize> {
+            let mut index: Vec<Vec<usize>> = texts.iter().map(|text| vec![0; text.len()]).collect();
+            for (i, &(text, offset)) in suffixes.iter().enumerate() {
+                index[text][offset] = i;
+            }
+            let mut lcp = vec![0; suffixes.len()];
+            for (text, items) in texts.iter().enumerate() {
+                let mut common = 0;
+                for offset in 0..items.len() {
+                    let i = index[text][offset];
+                    if i == 0 {
+                        common = 0;
+                        continue;
+                    }
+                    let (other, other_offset) = suffixes[i - 1];
+                    let (a, b) = (&items[offset..], &texts[other][other_offset..]);
+                    while common < a.len() && common < b.len() && a[common] == b[common] {
+                        common += 1;
+                    }
+                    lcp[i] = common;
+                    common = common.saturating_sub(1);
+                }
+            }
+            lcp
+        }
+    }
+}
+
+/// $ cargo test suffix_array
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banana() {
+        let array = SuffixArray::new(["banana".bytes()]);
+        let suffixes: Vec<&[u8]> = (0..array.len()).map(|i| array.suffix(i).unwrap()).collect();
+        assert_eq!(
+            suffixes,
+            vec![&b"a"[..], b"ana", b"anana", b"banana", b"na", b"nana"]
+        );
+        assert_eq!(array.lcp(), &[0, 1, 3, 0, 0, 2]);
+        assert_eq!(array.find(b"ana"), &[(0, 3), (0, 1)]);
+        assert!(array.find(b"nab").is_empty());
+        assert_eq!(array.longest_repeated(), Some(&b"ana"[..]));
+    }
+
+    #[test]
+    fn test_many_texts() {
+        let paths = ["/garden/sensor", "/garage/door", "/sensors/hub", "/hub"];
+        let array = SuffixArray::new(paths.iter().map(|path| path.chars()));
+        let pattern: Vec<char> = "sensor".chars().collect();
+        assert_eq!(array.texts_containing(&pattern), vec![0, 2]);
+        let pattern: Vec<char> = "/hub".chars().collect();
+        assert_eq!(array.texts_containing(&pattern), vec![2, 3]);
+        assert_eq!(array.texts_containing(&[]).len(), 4);
+        assert!(array.texts_containing(&['x']).is_empty());
+    }
+
+    /// Сверка порядка суффиксов, LCP и поиска с перебором
+    #[test]
+    fn test_random() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let texts: Vec<Vec<u8>> = (0..rng.gen_range(0..5))
+                .map(|_| {
+                    (0..rng.gen_range(0..20))
+                        .map(|_| rng.gen_range(0..3))
+                        .collect()
+                })
+                .collect();
+            let array = SuffixArray::new(texts.clone());
+
+            let mut expected: Vec<&[u8]> = texts
+                .iter()
+                .flat_map(|text| (0..text.len()).map(move |offset| &text[offset..]))
+                .collect();
+            expected.sort();
+            let suffixes: Vec<&[u8]> = (0..array.len()).map(|i| array.suffix(i).unwrap()).collect();
+            assert_eq!(suffixes, expected);
+            for i in 1..suffixes.len() {
+                let common = suffixes[i - 1]
+                    .iter()
+                    .zip(suffixes[i])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                assert_eq!(array.lcp()[i], common);
+            }
+
+            let pattern: Vec<u8> = (0..rng.gen_range(1..4))
+                .map(|_| rng.gen_range(0..3))
+                .collect();
+            let containing: Vec<usize> = (0..texts.len())
+                .filter(|&i| texts[i].windows(pattern.len()).any(|w| w == &pattern[..]))
+                .collect();
+            assert_eq!(array.texts_containing(&pattern), containing);
+        }
+    }
+}
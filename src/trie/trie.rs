@@ -6,6 +6,10 @@ use std::boxed::Box;
 use std::collections::HashMap;
 use std::mem;
 use std::str::Chars;
+use std::sync::OnceLock;
+
+use super::radix_trie::RadixTrieMap;
+use super::suffix_array::SuffixArray;
 
 #[derive(Clone, Debug)]
 pub struct IoTDevice {
@@ -30,88 +34,235 @@ impl PartialEq for IoTDevice {
     }
 }
 
-type Link = Box<Node>;
-
-struct Node {
-    pub key: char,
-    next: HashMap<char, Link>,
-    pub value: Option<IoTDevice>,
-}
-
-impl Node {
-    pub fn new(key: char, device: Option<IoTDevice>) -> Link {
-        Box::new(Node {
-            key: key,
-            next: HashMap::new(),
-            value: device,
-        })
-    }
-}
-
-impl PartialEq for Node {
-    fn eq(&self, other: &Node) -> bool {
-        self.key == other.key
-    }
+/// Устройство и его вес для ранжирования подсказок
+struct Registered {
+    device: IoTDevice,
+    weight: u64,
 }
 
+/// Реестр устройств по пути `path` поверх сжатого `radix_trie::RadixTrieMap`.
+/// Для поиска по подстроке пути лениво строится `suffix_array::SuffixArray`,
+/// он сбрасывается при добавлении и удалении устройств
 pub struct BestDeviceRegistry {
     pub length: u64,
-    root: HashMap<char, Link>,
+    devices: RadixTrieMap<char, Registered>,
+    substrings: OnceLock<SuffixArray<char>>,
 }
 
 impl BestDeviceRegistry {
     pub fn new_empty() -> BestDeviceRegistry {
         BestDeviceRegistry {
             length: 0,
-            root: HashMap::new(),
+            devices: RadixTrieMap::new(),
+            substrings: OnceLock::new(),
         }
     }
 
+    /// Добавляет устройство с весом 0 или заменяет устройство с тем же путём. Пустой путь игнорируется
     pub fn add(&mut self, device: IoTDevice) {
-        let p = device.path.clone();
-        let mut path = p.chars();
-
-        if let Some(start) = path.next() {
-            self.length += 1;
-            let mut n = self.root.entry(start).or_insert(Node::new(start, None));
-            for c in path {
-                let tmp = n.next.entry(c).or_insert(Node::new(c, None));
-                n = tmp;
+        if device.path.is_empty() {
+            return;
+        }
+        let path = device.path.clone();
+        self.devices
+            .insert(path.chars(), Registered { device, weight: 0 });
+        self.length = self.devices.len() as u64;
+        self.substrings.take();
+    }
+
+    /// Задаёт вес устройства для `complete`. `false`, если устройства нет
+    pub fn set_weight(&mut self, path: &str, weight: u64) -> bool {
+        match self.devices.get_mut(path.chars()) {
+            Some(registered) => {
+                registered.weight = weight;
+                true
             }
-            n.value = Some(device);
+            None => false,
         }
     }
 
+    /// Устройство с точно таким путём
     pub fn find(&self, path: &str) -> Option<IoTDevice> {
-        let mut path = path.chars();
-
-        if let Some(start) = path.next() {
-            self.root.get(&start).map_or(None, |mut n| {
-                for c in path {
-                    match n.next.get(&c) {
-                        Some(ref tmp) => n = tmp,
-                        None => break,
-                    }
-                }
-                n.value.clone()
-            })
-        } else {
-            None
-        }
+        self.devices
+            .get(path.chars())
+            .map(|registered| registered.device.clone())
     }
 
-    pub fn walk(&self, callback: impl Fn(&IoTDevice) -> ()) {
-        for r in self.root.values() {
-            self.walk_r(&r, &callback);
+    pub fn remove(&mut self, path: &str) -> Option<IoTDevice> {
+        let removed = self.devices.remove(path.chars());
+        self.length = self.devices.len() as u64;
+        if removed.is_some() {
+            self.substrings.take();
         }
+        removed.map(|registered| registered.device)
     }
 
-    fn walk_r(&self, node: &Link, callback: &impl Fn(&IoTDevice) -> ()) {
-        for n in node.next.values() {
-            self.walk_r(&n, callback);
-        }
-        if let Some(ref dev) = node.value {
-            callback(dev);
-        }
+    /// Устройство с самым длинным путём, который является префиксом `path`.
+    /// Например, для `/building/floor/room` подойдёт устройство `/building/floor`
+    pub fn longest_prefix_match(&self, path: &str) -> Option<&IoTDevice> {
+        self.devices
+            .longest_prefix_match(path.chars())
+            .map(|(_, registered)| &registered.device)
+    }
+
+    /// Устройства, пути которых начинаются с `prefix`, в лексикографическом порядке путей
+    pub fn iter_prefix(&self, prefix: &str) -> impl Iterator<Item = &IoTDevice> {
+        self.devices
+            .iter_prefix(prefix.chars())
+            .map(|(_, registered)| &registered.device)
+    }
+
+    /// До `limit` устройств с путём, начинающимся с `prefix`:
+    /// по убыванию веса, при равном весе - по алфавиту путей
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<&IoTDevice> {
+        self.devices
+            .complete_by(prefix.chars(), limit, |registered| registered.weight)
+            .into_iter()
+            .map(|(_, registered)| &registered.device)
+            .collect()
+    }
+
+    /// Устройства, путь которых отличается от `query` не больше чем на `max_edits` правок
+    /// (вставка, удаление, замена символа), с числом правок, в алфавитном порядке путей
+    pub fn fuzzy(&self, query: &str, max_edits: usize) -> Vec<(&IoTDevice, usize)> {
+        self.devices
+            .fuzzy(query.chars(), max_edits)
+            .into_iter()
+            .map(|(_, registered, distance)| (&registered.device, distance))
+            .collect()
+    }
+
+    /// Устройства, в пути которых есть `pattern`, в алфавитном порядке путей.
+    /// Первый запрос после изменения реестра строит индекс за O(n log² n) от суммарной длины путей
+    pub fn find_substring(&self, pattern: &str) -> Vec<&IoTDevice> {
+        let index = self
+            .substrings
+            .get_or_init(|| SuffixArray::new(self.devices.iter().map(|(path, _)| path)));
+        let pattern: Vec<char> = pattern.chars().collect();
+        // пути в индексе лежат в порядке обхода дерева, то есть по алфавиту
+        index
+            .texts_containing(&pattern)
+            .into_iter()
+            .filter_map(|text| self.devices.get(index.texts()[text].iter().copied()))
+            .map(|registered| &registered.device)
+            .collect()
+    }
+
+    pub fn walk(&self, callback: impl Fn(&IoTDevice)) {
+        self.devices
+            .walk(|_, registered| callback(&registered.device));
+    }
+}
+
+/// $ cargo test trie::tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> BestDeviceRegistry {
+        let mut registry = BestDeviceRegistry::new_empty();
+        registry.add(IoTDevice::new(1, "10.0.0.1", "/building"));
+        registry.add(IoTDevice::new(2, "10.0.0.2", "/building/floor"));
+        registry.add(IoTDevice::new(3, "10.0.0.3", "/building/floor/room"));
+        registry.add(IoTDevice::new(4, "10.0.0.4", "/garage"));
+        registry
+    }
+
+    #[test]
+    fn test_find_exact() {
+        let registry = registry();
+        assert_eq!(registry.length, 4);
+        assert_eq!(
+            registry.find("/building/floor"),
+            Some(IoTDevice::new(2, "10.0.0.2", "/building/floor"))
+        );
+        // раньше возвращалось устройство самого глубокого совпавшего узла
+        assert_eq!(registry.find("/building/floor/room/lamp"), None);
+        assert_eq!(registry.find("/building/fl"), None);
+        assert_eq!(registry.find(""), None);
+    }
+
+    #[test]
+    fn test_remove_and_prefix() {
+        let mut registry = registry();
+        assert_eq!(
+            registry.remove("/building/floor").map(|d| d.numerical_id),
+            Some(2)
+        );
+        assert_eq!(registry.remove("/building/floor"), None);
+        assert_eq!(registry.length, 3);
+
+        let ids: Vec<u64> = registry
+            .iter_prefix("/building")
+            .map(|d| d.numerical_id)
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(
+            registry
+                .longest_prefix_match("/building/floor/lamp")
+                .map(|d| d.numerical_id),
+            Some(1)
+        );
+        assert_eq!(
+            registry
+                .longest_prefix_match("/building/floor/room/lamp")
+                .map(|d| d.numerical_id),
+            Some(3)
+        );
+        assert!(registry.longest_prefix_match("/yard").is_none());
+    }
+
+    #[test]
+    fn test_complete_and_fuzzy() {
+        let mut registry = registry();
+        let ids = |devices: Vec<&IoTDevice>| -> Vec<u64> {
+            devices.iter().map(|d| d.numerical_id).collect()
+        };
+        assert_eq!(ids(registry.complete("/b", 2)), vec![1, 2]);
+        assert!(registry.set_weight("/building/floor/room", 10));
+        assert!(!registry.set_weight("/yard", 10));
+        assert_eq!(ids(registry.complete("/b", 2)), vec![3, 1]);
+
+        let found: Vec<(u64, usize)> = registry
+            .fuzzy("/garages", 1)
+            .into_iter()
+            .map(|(d, distance)| (d.numerical_id, distance))
+            .collect();
+        assert_eq!(found, vec![(4, 1)]);
+        assert!(registry.fuzzy("/building/flor", 0).is_empty());
+        assert_eq!(registry.fuzzy("/building/flor", 1)[0].0.numerical_id, 2);
+    }
+
+    #[test]
+    fn test_find_substring() {
+        let mut registry = registry();
+        registry.add(IoTDevice::new(5, "10.0.0.5", "/garden/sensor"));
+        registry.add(IoTDevice::new(6, "10.0.0.6", "/sensors/hub"));
+        let ids = |devices: Vec<&IoTDevice>| -> Vec<u64> {
+            devices.iter().map(|d| d.numerical_id).collect()
+        };
+        assert_eq!(ids(registry.find_substring("sensor")), vec![5, 6]);
+        assert_eq!(ids(registry.find_substring("floor")), vec![2, 3]);
+        assert!(registry.find_substring("lamp").is_empty());
+
+        // индекс перестраивается после изменений
+        registry.remove("/garden/sensor");
+        registry.add(IoTDevice::new(7, "10.0.0.7", "/building/sensor"));
+        assert_eq!(ids(registry.find_substring("sensor")), vec![7, 6]);
+        assert_eq!(registry.find_substring("").len(), 6);
+    }
+
+    /// Индекс подстрок строится один раз, даже если реестр читают из нескольких потоков
+    #[test]
+    fn test_find_substring_shared() {
+        let registry = registry();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| registry.find_substring("floor").len()))
+                .collect();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), 2);
+            }
+        });
     }
 }
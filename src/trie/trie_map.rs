@@ -0,0 +1,333 @@
+#![allow(dead_code)]
+
+//! Префиксное дерево (trie), отображающее последовательности `K` в значения `V`.
+//!
+//! Ключ - любая последовательность элементов `K`: `"path".chars()`, `b"path".iter().copied()`, `vec![1, 2]`.
+//! Потомки узла хранятся в `BTreeMap`, поэтому обход идёт в лексикографическом порядке ключей.
+//!
+//! - `get` находит значение только при точном совпадении всего ключа;
+//! - `remove` удаляет опустевшие ветви;
+//! - `iter_prefix` лениво обходит ключи с заданным префиксом;
+//! - `longest_prefix_match` находит самый длинный сохранённый префикс ключа (маршрутизация по путям).
+pub use ds_trie_map::{Iter, TrieMap};
+mod ds_trie_map {
+    use std::collections::{btree_map, BTreeMap};
+
+    struct Node<K, V> {
+        next: BTreeMap<K, Box<Node<K, V>>>,
+        value: Option<V>,
+    }
+
+    impl<K: Ord, V> Node<K, V> {
+        fn new() -> Self {
+            Node {
+                next: BTreeMap::new(),
+                value: None,
+            }
+        }
+
+        fn is_empty(&self) -> bool {
+            self.value.is_none() && self.next.is_empty()
+        }
+
+        fn remove<I: Iterator<Item = K>>(&mut self, mut key: I) -> Option<V> {
+            let Some(k) = key.next() else {
+                return self.value.take();
+            };
+            let child = self.next.get_mut(&k)?;
+            let removed = child.remove(key)?;
+            if child.is_empty() {
+                self.next.remove(&k);
+            }
+            Some(removed)
+        }
+    }
+
+    pub struct TrieMap<K, V> {
+        root: Node<K, V>,
+        length: usize,
+    }
+
+    impl<K: Ord + Clone, V> Default for TrieMap<K, V> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<K: Ord + Clone, V> TrieMap<K, V> {
+        pub fn new() -> Self {
+            TrieMap {
+                root: Node::new(),
+                length: 0,
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.length
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.length == 0
+        }
+
+        /// Возвращает прежнее значение ключа
+        pub fn insert<I: IntoIterator<Item = K>>(&mut self, key: I, value: V) -> Option<V> {
+            let mut node = &mut self.root;
+            for k in key {
+                node = node.next.entry(k).or_insert_with(|| Box::new(Node::new()));
+            }
+            let old = node.value.replace(value);
+            if old.is_none() {
+                self.length += 1;
+            }
+            old
+        }
+
+        pub fn get<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&V> {
+            self.node(key).and_then(|node| node.value.as_ref())
+        }
+
+        pub fn get_mut<I: IntoIterator<Item = K>>(&mut self, key: I) -> Option<&mut V> {
+            let mut node = &mut self.root;
+            for k in key {
+                node = node.next.get_mut(&k)?;
+            }
+            node.value.as_mut()
+        }
+
+        pub fn contains_key<I: IntoIterator<Item = K>>(&self, key: I) -> bool {
+            self.get(key).is_some()
+        }
+
+        /// Удаляет ключ и ветви, в которых не осталось значений
+        pub fn remove<I: IntoIterator<Item = K>>(&mut self, key: I) -> Option<V> {
+            let removed = self.root.remove(key.into_iter());
+            if removed.is_some() {
+                self.length -= 1;
+            }
+            removed
+        }
+
+        /// Самый длинный сохранённый префикс `key` (включая весь ключ):
+        /// число совпавших элементов ключа и значение
+        pub fn longest_prefix_match<I: IntoIterator<Item = K>>(
+            &self,
+            key: I,
+        ) -> Option<(usize, &V)> {
+            let mut node = &self.root;
+            let mut best = node.value.as_ref().map(|value| (0, value));
+            for (depth, k) in key.into_iter().enumerate() {
+                match node.next.get(&k) {
+                    Some(next) => node = next,
+                    None => break,
+                }
+                if let Some(value) = node.value.as_ref() {
+                    best = Some((depth + 1, value));
+                }
+            }
+            best
+        }
+
+        /// Все пары в лексикографическом порядке ключей
+        pub fn iter(&self) -> Iter<'_, K, V> {
+            Iter::new(vec![], Some(&self.root))
+        }
+
+        /// Пары, ключи которых начинаются с `prefix`, в лексикографическом порядке
+        pub fn iter_prefix<I: IntoIterator<Item = K>>(&self, prefix: I) -> Iter<'_, K, V> {
+            let prefix: Vec<K> = prefix.into_iter().collect();
+            let node = self.node(prefix.iter().cloned());
+            Iter::new(prefix, node)
+        }
+
+        pub fn walk(&self, mut callback: impl FnMut(&[K], &V)) {
+            let mut iter = self.iter();
+            while let Some(value) = iter.next_value() {
+                callback(&iter.key, value);
+            }
+        }
+
+        /// Число узлов дерева, включая корень
+        #[cfg(test)]
+        pub(super) fn node_count(&self) -> usize {
+            fn count<K, V>(node: &Node<K, V>) -> usize {
+                1 + node.next.values().map(|child| count(child)).sum::<usize>()
+            }
+            count(&self.root)
+        }
+
+        fn node<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&Node<K, V>> {
+            let mut node = &self.root;
+            for k in key {
+                node = node.next.get(&k)?;
+            }
+            Some(node)
+        }
+    }
+
+    impl<K: Ord + Clone, V, I: IntoIterator<Item = K>> FromIterator<(I, V)> for TrieMap<K, V> {
+        fn from_iter<T: IntoIterator<Item = (I, V)>>(iter: T) -> Self {
+            let mut map = TrieMap::new();
+            for (key, value) in iter {
+                map.insert(key, value);
+            }
+            map
+        }
+    }
+
+    /// Ленивый обход в глубину: на стеке лежат итераторы по потомкам узлов текущего пути
+    pub struct Iter<'a, K, V> {
+        key: Vec<K>,
+        start: Option<&'a Node<K, V>>,
+        stack: Vec<btree_map::Iter<'a, K, Box<Node<K, V>>>>,
+    }
+
+    impl<'a, K: Clone, V> Iter<'a, K, V> {
+        fn new(key: Vec<K>, start: Option<&'a Node<K, V>>) -> Self {
+            Iter {
+                key,
+                start,
+                stack: vec![],
+            }
+        }
+
+        /// Следующее значение, его ключ остаётся в `self.key`
+        fn next_value(&mut self) -> Option<&'a V> {
+            if let Some(node) = self.start.take() {
+                self.stack.push(node.next.iter());
+                if let Some(value) = node.value.as_ref() {
+                    return Some(value);
+                }
+            }
+            loop {
+                match self.stack.last_mut()?.next() {
+                    Some((k, child)) => {
+                        self.key.push(k.clone());
+                        self.stack.push(child.next.iter());
+                        if let Some(value) = child.value.as_ref() {
+                            return Some(value);
+                        }
+                    }
+                    None => {
+                        self.stack.pop();
+                        if !self.stack.is_empty() {
+                            self.key.pop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    impl<'a, K: Clone, V> Iterator for Iter<'a, K, V> {
+        type Item = (Vec<K>, &'a V);
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_value().map(|value| (self.key.clone(), value))
+        }
+    }
+}
+
+/// $ cargo test trie_map
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys<V>(iter: Iter<'_, char, V>) -> Vec<String> {
+        iter.map(|(key, _)| key.into_iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_exact_lookup() {
+        let mut map = TrieMap::new();
+        assert_eq!(map.insert("/building/floor".chars(), 1), None);
+        assert_eq!(map.insert("/building".chars(), 2), None);
+        assert_eq!(map.insert("/building".chars(), 3), Some(2));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get("/building".chars()), Some(&3));
+        assert_eq!(map.get("/building/floor".chars()), Some(&1));
+        // промежуточный узел без значения и отсутствующий путь
+        assert_eq!(map.get("/build".chars()), None);
+        assert_eq!(map.get("/building/floor/room".chars()), None);
+        assert_eq!(map.get("/garage".chars()), None);
+
+        if let Some(value) = map.get_mut("/building".chars()) {
+            *value = 4;
+        }
+        assert!(map.contains_key("/building".chars()));
+        assert_eq!(map.get("/building".chars()), Some(&4));
+    }
+
+    #[test]
+    fn test_remove_prunes() {
+        let mut map: TrieMap<u8, &str> = TrieMap::new();
+        map.insert(b"abc".iter().copied(), "abc");
+        map.insert(b"abd".iter().copied(), "abd");
+        map.insert(b"a".iter().copied(), "a");
+
+        assert_eq!(map.node_count(), 5);
+        assert_eq!(map.remove(b"ab".iter().copied()), None);
+        assert_eq!(map.remove(b"abc".iter().copied()), Some("abc"));
+        assert_eq!(map.remove(b"abc".iter().copied()), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.node_count(), 4);
+        assert_eq!(map.get(b"abd".iter().copied()), Some(&"abd"));
+
+        assert_eq!(map.remove(b"abd".iter().copied()), Some("abd"));
+        assert_eq!(map.remove(b"a".iter().copied()), Some("a"));
+        assert!(map.is_empty());
+        assert_eq!(map.node_count(), 1);
+    }
+
+    #[test]
+    fn test_iter_prefix() {
+        let map: TrieMap<char, usize> = ["b", "a", "abc", "ab", "abd", "ac", "b/x"]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.chars(), i))
+            .collect();
+
+        assert_eq!(
+            keys(map.iter()),
+            vec!["a", "ab", "abc", "abd", "ac", "b", "b/x"]
+        );
+        assert_eq!(
+            keys(map.iter_prefix("ab".chars())),
+            vec!["ab", "abc", "abd"]
+        );
+        assert_eq!(keys(map.iter_prefix("b/".chars())), vec!["b/x"]);
+        assert!(keys(map.iter_prefix("z".chars())).is_empty());
+        assert_eq!(
+            map.iter_prefix("abc".chars()).collect::<Vec<_>>(),
+            vec![(vec!['a', 'b', 'c'], &2)]
+        );
+
+        let mut walked = vec![];
+        map.walk(|key, value| walked.push((key.len(), *value)));
+        assert_eq!(walked.len(), map.len());
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut routes = TrieMap::new();
+        routes.insert("/building".chars(), "gateway");
+        routes.insert("/building/floor".chars(), "floor");
+        routes.insert("/building/floor/room".chars(), "room");
+
+        assert_eq!(
+            routes.longest_prefix_match("/building/floor/room/sensor".chars()),
+            Some((20, &"room"))
+        );
+        assert_eq!(
+            routes.longest_prefix_match("/building/floor2".chars()),
+            Some((15, &"floor"))
+        );
+        assert_eq!(routes.longest_prefix_match("/garage".chars()), None);
+
+        routes.insert("".chars(), "default");
+        assert_eq!(
+            routes.longest_prefix_match("/garage".chars()),
+            Some((0, &"default"))
+        );
+    }
+}